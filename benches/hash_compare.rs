@@ -0,0 +1,26 @@
+// Compare reading two copies of a 10MB file and checking their bytes with
+// hashing each copy in 64KB chunks, as status does
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs;
+
+fn compare_10mb_file(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let content: Vec<u8> = (0..10 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+    let repo = dir.path().join("repo");
+    let local = dir.path().join("local");
+    fs::write(&repo, &content).unwrap();
+    fs::write(&local, &content).unwrap();
+
+    let mut group = c.benchmark_group("compare 10MB file");
+    group.sample_size(20);
+    group.bench_function("bytes", |b| {
+        b.iter(|| fs::read(&repo).unwrap() == fs::read(&local).unwrap())
+    });
+    group.bench_function("sha256", |b| {
+        b.iter(|| dotfiles_rs::hash_file(&repo).unwrap() == dotfiles_rs::hash_file(&local).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, compare_10mb_file);
+criterion_main!(benches);
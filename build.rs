@@ -1,23 +1,86 @@
 use std::env;
-use std::fs::{self, File};
+use std::fs::File;
 use std::io::Write;
-use std::path::Path;
+#[cfg(feature = "embedded")]
+use std::fs;
+#[cfg(feature = "embedded")]
+use std::path::{Component, Path, PathBuf};
 
 // Note: Add this to Cargo.toml:
 // [build-dependencies]
 // toml = "0.8"
+#[cfg(feature = "embedded")]
 use toml::Value;
+#[cfg(feature = "embedded")]
+use sha2::{Digest, Sha256};
+
+// Most files watched in the config/<tool>/ directories, so a large tree
+// doesn't swamp cargo's dependency tracking
+#[cfg(feature = "embedded")]
+const MAX_TRACKED_FILES: usize = 1000;
 
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
     
+    // Without the `embedded` feature, or without dotfiles to embed, the binary
+    // only manages a repository on disk
+    #[cfg(feature = "embedded")]
+    if embed_dotfiles() {
+        return;
+    }
+    write_stub();
+}
+
+// Write an embedded_files.rs without any files
+fn write_stub() {
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
+    let mut file_map = File::create(format!("{}/embedded_files.rs", out_dir))
+        .expect("Failed to create embedded_files.rs");
+    
+    writeln!(file_map, "// Auto-generated stub, dotfiles-rs was built without embedded dotfiles").unwrap();
+    writeln!(file_map, "use once_cell::sync::Lazy;").unwrap();
+    writeln!(file_map).unwrap();
+    writeln!(file_map, "pub fn has_embedded_files() -> bool {{").unwrap();
+    writeln!(file_map, "    false").unwrap();
+    writeln!(file_map, "}}").unwrap();
+    writeln!(file_map).unwrap();
+    writeln!(file_map, "pub const DISTRIBUTION_TOML: &[u8] = &[];").unwrap();
+    writeln!(file_map, "pub const DOTIGNORE: &[u8] = &[];").unwrap();
+    writeln!(file_map, "pub const HAS_DOTIGNORE: bool = false;").unwrap();
+    writeln!(file_map).unwrap();
+    writeln!(file_map, "pub static EMBEDDED_FILES: Lazy<HashMap<String, &'static [u8]>> = Lazy::new(HashMap::new);").unwrap();
+    
+    write_checksums(&out_dir, &[]);
+    
+    println!("cargo:rustc-env=BUILD_IDENTITY=00000000-00-000000");
+    println!("cargo:rustc-env=NEWEST_FILE=unknown");
+}
+
+// Write embedded_checksums.rs with the SHA-256 of each embedded file, so the
+// binary can check its archive at runtime
+fn write_checksums(out_dir: &str, checksums: &[(String, String)]) {
+    let mut file = File::create(format!("{}/embedded_checksums.rs", out_dir))
+        .expect("Failed to create embedded_checksums.rs");
+    
+    writeln!(file, "// Auto-generated SHA-256 checksums of the embedded dotfiles").unwrap();
+    writeln!(file, "pub static EMBEDDED_CHECKSUMS: &[(&str, &str)] = &[").unwrap();
+    for (path, hash) in checksums {
+        writeln!(file, "    (\"{}\", \"{}\"),", path, hash).unwrap();
+    }
+    writeln!(file, "];").unwrap();
+}
+
+// Embed distribution.toml, .dotignore and every tracked file from
+// $HOME/repos/dotfiles. Returns false when there is nothing to embed
+#[cfg(feature = "embedded")]
+fn embed_dotfiles() -> bool {
     // Paths
     let home = match env::var("HOME") {
         Ok(h) => h,
         Err(_) => {
             // If HOME isn't available, we just build without embedded files
             println!("cargo:warning=HOME environment variable not set, building without embedded files");
-            return;
+            return false;
         }
     };
     
@@ -28,7 +91,7 @@ fn main() {
     // Check if distribution file exists
     if !Path::new(&distribution_path).exists() {
         println!("cargo:warning=distribution.toml not found at {}, building without embedded files", distribution_path);
-        return;
+        return false;
     }
     
     println!("cargo:rerun-if-changed={}", distribution_path);
@@ -38,41 +101,68 @@ fn main() {
         Ok(content) => content,
         Err(e) => {
             println!("cargo:warning=Failed to read distribution.toml: {}", e);
-            return;
+            return false;
         }
     };
     
+    // Size limits for embedded files, overridable per file in [_build]
+    println!("cargo:rerun-if-env-changed=BUILD_MAX_EMBED_BYTES");
+    println!("cargo:rerun-if-env-changed=BUILD_SIZE_WARN_BYTES");
+    
+    // Nothing to regenerate when distribution.toml and the limits are the
+    // same as last time and no embedded file changed since
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
+    let dist_hash = distribution_hash(&distribution_content);
+    if reuse_previous_build(&out_dir, &dist_hash) {
+        return true;
+    }
+    
     // Parse TOML
-    let distribution: Value = match toml::from_str(&distribution_content) {
+    let mut distribution: Value = match toml::from_str(&distribution_content) {
         Ok(parsed) => parsed,
         Err(e) => {
             println!("cargo:warning=Failed to parse distribution.toml: {}", e);
-            return;
+            return false;
         }
     };
+    
+    let max_embed_bytes = env_bytes("BUILD_MAX_EMBED_BYTES", 1024 * 1024);
+    let size_warn_bytes = env_bytes("BUILD_SIZE_WARN_BYTES", 100 * 1024);
+    let large_files: Vec<String> = distribution.get("_build")
+        .and_then(|build| build.get("large_files"))
+        .and_then(Value::as_array)
+        .map(|files| files.iter().filter_map(Value::as_str).map(String::from).collect())
+        .unwrap_or_default();
+    
+    // Pull in the sections of included files so their config files are embedded too
+    let includes = resolve_includes(&dotfiles_dir, Path::new(""), &mut distribution, &mut vec![PathBuf::from("distribution.toml")], 0);
 
-    // Create output directory if needed
-    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
+    // Every file the output depends on, replayed when the build is reused
+    let mut watched = vec![distribution_path.clone()];
+    watched.extend(includes.iter().map(|include| format!("{}/{}", dotfiles_dir, include.display())));
     
     // Create output file for file mappings
     let mut file_map = match File::create(format!("{}/embedded_files.rs", out_dir)) {
         Ok(file) => file,
         Err(e) => {
             println!("cargo:warning=Failed to create output file: {}", e);
-            return;
+            return false;
         }
     };
     
     // Write header
     writeln!(file_map, "// Auto-generated file mapping for embedded dotfiles").unwrap();
     writeln!(file_map, "use once_cell::sync::Lazy;").unwrap();
-    writeln!(file_map, "").unwrap();
+    writeln!(file_map).unwrap();
     
     // Define function to check if we have embedded files
     writeln!(file_map, "pub fn has_embedded_files() -> bool {{").unwrap();
     writeln!(file_map, "    !EMBEDDED_FILES.is_empty()").unwrap();
     writeln!(file_map, "}}").unwrap();
-    writeln!(file_map, "").unwrap();
+    writeln!(file_map).unwrap();
+    
+    // (map key, SHA-256) of every embedded file
+    let mut checksums = Vec::new();
     
     // Embed distribution.toml itself
     writeln!(file_map, "pub const DISTRIBUTION_TOML: &[u8] = include_bytes!(\"{}\");", distribution_path).unwrap();
@@ -80,13 +170,14 @@ fn main() {
     // Embed dotignore if it exists
     if Path::new(&dotignore_path).exists() {
         println!("cargo:rerun-if-changed={}", dotignore_path);
+        watched.push(dotignore_path.clone());
         writeln!(file_map, "pub const DOTIGNORE: &[u8] = include_bytes!(\"{}\");", dotignore_path).unwrap();
         writeln!(file_map, "pub const HAS_DOTIGNORE: bool = true;").unwrap();
     } else {
         writeln!(file_map, "pub const DOTIGNORE: &[u8] = &[];").unwrap();
         writeln!(file_map, "pub const HAS_DOTIGNORE: bool = false;").unwrap();
     }
-    writeln!(file_map, "").unwrap();
+    writeln!(file_map).unwrap();
     
     // Start files map
     writeln!(file_map, "pub static EMBEDDED_FILES: Lazy<HashMap<String, &'static [u8]>> = Lazy::new(|| {{").unwrap();
@@ -94,14 +185,28 @@ fn main() {
     
     // Add distribution.toml to the map
     writeln!(file_map, "    map.insert(\"distribution.toml\".to_string(), DISTRIBUTION_TOML);").unwrap();
+    add_checksum(&mut checksums, "distribution.toml", &distribution_path);
     
     // Add dotignore to the map if it exists
     if Path::new(&dotignore_path).exists() {
         writeln!(file_map, "    map.insert(\".dotignore\".to_string(), DOTIGNORE);").unwrap();
+        add_checksum(&mut checksums, ".dotignore", &dotignore_path);
+    }
+    
+    // Add included distribution files, keyed by their path in the repo
+    for include in &includes {
+        let include_path = format!("{}/{}", dotfiles_dir, include.display());
+        let const_name = format!("INCLUDE_{}",
+            include.to_string_lossy().replace(|c: char| !c.is_alphanumeric() && c != '_', "_").to_uppercase());
+        writeln!(file_map, "    const {}: &[u8] = include_bytes!(\"{}\");", const_name, include_path).unwrap();
+        writeln!(file_map, "    map.insert(\"{}\".to_string(), {});", include.display(), const_name).unwrap();
+        add_checksum(&mut checksums, &include.to_string_lossy(), &include_path);
     }
     
     // Process each section in distribution.toml
     let mut embedded_count = 0;
+    let mut embedded_bytes = 0;
+    let mut tool_dirs: Vec<String> = Vec::new();
     
     // Clone distribution for embedding files
     let distribution_clone = distribution.clone();
@@ -114,31 +219,53 @@ fn main() {
             }
             
             if let Value::Table(table) = section_data {
+                // Encrypted sections store <file>.gpg in the repo
+                let extension = match table.get("encrypted").and_then(Value::as_bool) {
+                    Some(true) => ".gpg",
+                    _ => "",
+                };
                 if let Some(Value::Array(files)) = table.get("files") {
-                    for file_value in files {
-                        if let Value::String(file) = file_value {
-                            let file_path = format!("{}/config/{}/{}", dotfiles_dir, section_name, file);
-                            let map_key = format!("config/{}/{}", section_name, file);
+                    for file in files.iter().filter_map(repo_file_name) {
+                        let allow_large = large_files.iter().any(|large| *large == format!("{}/{}", section_name, file));
+                        let file = format!("{}{}", file, extension);
+                        let file_path = format!("{}/config/{}/{}", dotfiles_dir, section_name, file);
+                        let map_key = format!("config/{}/{}", section_name, file);
+                        
+                        // Check if file exists
+                        if let Ok(metadata) = fs::metadata(&file_path) {
+                            println!("cargo:rerun-if-changed={}", file_path);
+                            watched.push(file_path.clone());
                             
-                            // Check if file exists
-                            if Path::new(&file_path).exists() {
-                                println!("cargo:rerun-if-changed={}", file_path);
-                                
-                                // Create a safe constant name by removing all problematic characters
-                                // Using uppercase for constants to follow Rust conventions
-                                let const_name = format!("FILE_{}", 
-                                    map_key.replace(|c: char| !c.is_alphanumeric() && c != '_', "_").to_uppercase());
-                                
-                                // Include the file and add to map
-                                writeln!(file_map, "    const {}: &[u8] = include_bytes!(\"{}\");", 
-                                    const_name, file_path).unwrap();
-                                writeln!(file_map, "    map.insert(\"{}\".to_string(), {});", 
-                                    map_key, const_name).unwrap();
-                                
-                                embedded_count += 1;
-                            } else {
-                                println!("cargo:warning=File not found: {}", file_path);
+                            let size = metadata.len();
+                            if !allow_large {
+                                if size > max_embed_bytes {
+                                    println!("cargo:warning=Skipping large file: {} ({} bytes)", file_path, size);
+                                    continue;
+                                }
+                                if size > size_warn_bytes {
+                                    println!("cargo:warning=Embedding large file: {} ({} bytes)", file_path, size);
+                                }
                             }
+                            
+                            // Create a safe constant name by removing all problematic characters
+                            // Using uppercase for constants to follow Rust conventions
+                            let const_name = format!("FILE_{}", 
+                                map_key.replace(|c: char| !c.is_alphanumeric() && c != '_', "_").to_uppercase());
+                            
+                            // Include the file and add to map
+                            writeln!(file_map, "    const {}: &[u8] = include_bytes!(\"{}\");", 
+                                const_name, file_path).unwrap();
+                            writeln!(file_map, "    map.insert(\"{}\".to_string(), {});", 
+                                map_key, const_name).unwrap();
+                            add_checksum(&mut checksums, &map_key, &file_path);
+                            
+                            embedded_count += 1;
+                            embedded_bytes += size;
+                            if !tool_dirs.contains(&section_name) {
+                                tool_dirs.push(section_name.clone());
+                            }
+                        } else {
+                            println!("cargo:warning=File not found: {}", file_path);
                         }
                     }
                 }
@@ -150,7 +277,20 @@ fn main() {
     writeln!(file_map, "    map").unwrap();
     writeln!(file_map, "}});").unwrap();
     
-    println!("cargo:warning=Embedded {} files from distribution.toml into the binary", embedded_count);
+    // Watch the whole directory of each embedded tool, so files added there
+    // without touching distribution.toml still trigger a rebuild
+    let mut tracked = 0;
+    for tool in &tool_dirs {
+        let dir = PathBuf::from(format!("{}/config/{}", dotfiles_dir, tool));
+        if !track_directory_tree(&dir, &mut watched, &mut tracked) {
+            println!("cargo:warning=Only watching the first {} files in config/ for changes", MAX_TRACKED_FILES);
+            break;
+        }
+    }
+    
+    write_checksums(&out_dir, &checksums);
+    
+    println!("cargo:warning=Embedded {} files ({} bytes) from distribution.toml into the binary", embedded_count, embedded_bytes);
     
     // Find the newest file timestamp to use as build identity
     let mut newest_timestamp = 0;
@@ -169,14 +309,8 @@ fn main() {
         }
     }
     
-    // Process all embedded files - use a fresh parse to avoid ownership issues
-    let distribution_for_timestamp = match fs::read_to_string(&distribution_path) {
-        Ok(content) => match toml::from_str::<Value>(&content) {
-            Ok(parsed) => parsed,
-            Err(_) => Value::Table(toml::map::Map::new()),
-        },
-        Err(_) => Value::Table(toml::map::Map::new()),
-    };
+    // Process all embedded files, including those listed in included files
+    let distribution_for_timestamp = distribution;
     
     if let Value::Table(sections) = distribution_for_timestamp {
         for (section_name, section_data) in sections {
@@ -186,18 +320,16 @@ fn main() {
             
             if let Value::Table(table) = section_data {
                 if let Some(Value::Array(files)) = table.get("files") {
-                    for file_value in files {
-                        if let Value::String(file) = file_value {
-                            let file_path = format!("{}/config/{}/{}", dotfiles_dir, section_name, file);
-                            
-                            if let Ok(metadata) = std::fs::metadata(&file_path) {
-                                if let Ok(modified_time) = metadata.modified() {
-                                    if let Ok(system_time) = modified_time.duration_since(std::time::SystemTime::UNIX_EPOCH) {
-                                        let timestamp = system_time.as_secs();
-                                        if timestamp > newest_timestamp {
-                                            newest_timestamp = timestamp;
-                                            newest_file = file_path.clone();
-                                        }
+                    for file in files.iter().filter_map(repo_file_name) {
+                        let file_path = format!("{}/config/{}/{}", dotfiles_dir, section_name, file);
+                        
+                        if let Ok(metadata) = std::fs::metadata(&file_path) {
+                            if let Ok(modified_time) = metadata.modified() {
+                                if let Ok(system_time) = modified_time.duration_since(std::time::SystemTime::UNIX_EPOCH) {
+                                    let timestamp = system_time.as_secs();
+                                    if timestamp > newest_timestamp {
+                                        newest_timestamp = timestamp;
+                                        newest_file = file_path.clone();
                                     }
                                 }
                             }
@@ -209,6 +341,10 @@ fn main() {
     }
     
     // Convert timestamp to formatted datetime string
+    let mut build_env = vec![
+        "BUILD_IDENTITY=00000000-00-000000".to_string(),
+        "NEWEST_FILE=unknown".to_string(),
+    ];
     if newest_timestamp > 0 {
         use std::time::{UNIX_EPOCH, Duration};
         use chrono::{DateTime, Utc, Datelike, Timelike};
@@ -221,10 +357,275 @@ fn main() {
             week_num,
             dt.hour(), dt.minute(), dt.second());
             
-        println!("cargo:rustc-env=BUILD_IDENTITY={}", build_identity);
-        println!("cargo:rustc-env=NEWEST_FILE={}", newest_file);
-    } else {
-        println!("cargo:rustc-env=BUILD_IDENTITY=00000000-00-000000");
-        println!("cargo:rustc-env=NEWEST_FILE=unknown");
+        build_env = vec![
+            format!("BUILD_IDENTITY={}", build_identity),
+            format!("NEWEST_FILE={}", newest_file),
+        ];
+    }
+    for value in &build_env {
+        println!("cargo:rustc-env={}", value);
+    }
+    
+    record_build(&out_dir, &dist_hash, &watched, &build_env);
+    true
+}
+
+// SHA-256 of distribution.toml and the size limits, which together decide
+// what gets embedded
+#[cfg(feature = "embedded")]
+fn distribution_hash(distribution_content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(distribution_content.as_bytes());
+    for name in ["BUILD_MAX_EMBED_BYTES", "BUILD_SIZE_WARN_BYTES"] {
+        hasher.update(format!("\n{}={}", name, env::var(name).unwrap_or_default()).as_bytes());
+    }
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Save what reuse_previous_build needs: the distribution hash in
+// dist_hash.txt and the cargo directives in dist_deps.txt
+#[cfg(feature = "embedded")]
+fn record_build(out_dir: &str, dist_hash: &str, watched: &[String], build_env: &[String]) {
+    let mut deps: Vec<String> = watched.iter().map(|path| format!("cargo:rerun-if-changed={}", path)).collect();
+    deps.extend(build_env.iter().map(|value| format!("cargo:rustc-env={}", value)));
+    
+    let written = fs::write(format!("{}/dist_deps.txt", out_dir), deps.join("\n"))
+        .and_then(|_| fs::write(format!("{}/dist_hash.txt", out_dir), dist_hash));
+    if let Err(e) = written {
+        println!("cargo:warning=Failed to record build dependencies: {}", e);
+    }
+}
+
+// Reuse the generated files of the previous build when its distribution hash
+// matches and every file it depends on is older than embedded_files.rs. The
+// recorded directives are printed again so cargo keeps watching those files
+#[cfg(feature = "embedded")]
+fn reuse_previous_build(out_dir: &str, dist_hash: &str) -> bool {
+    let previous_hash = fs::read_to_string(format!("{}/dist_hash.txt", out_dir)).unwrap_or_default();
+    if previous_hash.trim() != dist_hash {
+        return false;
+    }
+    let Ok(deps) = fs::read_to_string(format!("{}/dist_deps.txt", out_dir)) else {
+        return false;
+    };
+    let Some(generated) = modified(&format!("{}/embedded_files.rs", out_dir)) else {
+        return false;
+    };
+    if !Path::new(&format!("{}/embedded_checksums.rs", out_dir)).exists() {
+        return false;
+    }
+    
+    let unchanged = deps.lines()
+        .filter_map(|line| line.strip_prefix("cargo:rerun-if-changed="))
+        .all(|path| modified(path).is_some_and(|time| time < generated));
+    if !unchanged {
+        return false;
+    }
+    
+    for line in deps.lines() {
+        println!("{}", line);
+    }
+    true
+}
+
+// Watch `dir`, its subdirectories and every file in them. Directories are
+// watched too, since adding a file only changes its directory. Returns false
+// once `tracked` reaches MAX_TRACKED_FILES
+#[cfg(feature = "embedded")]
+fn track_directory_tree(dir: &Path, watched: &mut Vec<String>, tracked: &mut usize) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return true;
+    };
+    watch(dir, watched);
+    
+    let mut entries: Vec<fs::DirEntry> = entries.filter_map(|entry| entry.ok()).collect();
+    entries.sort_by_key(fs::DirEntry::path);
+    for entry in entries {
+        // Don't follow symlinked directories, which may loop
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            if !track_directory_tree(&entry.path(), watched, tracked) {
+                return false;
+            }
+            continue;
+        }
+        if *tracked >= MAX_TRACKED_FILES {
+            return false;
+        }
+        *tracked += 1;
+        watch(&entry.path(), watched);
+    }
+    true
+}
+
+// Tell cargo to rerun when `path` changes, unless it is already watched
+#[cfg(feature = "embedded")]
+fn watch(path: &Path, watched: &mut Vec<String>) {
+    let path = path.display().to_string();
+    if !watched.contains(&path) {
+        println!("cargo:rerun-if-changed={}", path);
+        watched.push(path);
+    }
+}
+
+#[cfg(feature = "embedded")]
+fn modified(path: &str) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// Hash the file at `path` and record it under its archive key
+#[cfg(feature = "embedded")]
+fn add_checksum(checksums: &mut Vec<(String, String)>, key: &str, path: &str) {
+    match fs::read(path) {
+        Ok(content) => {
+            let hash = Sha256::digest(&content).iter().map(|b| format!("{:02x}", b)).collect();
+            checksums.push((key.to_string(), hash));
+        },
+        Err(e) => println!("cargo:warning=Failed to hash {}: {}", path, e),
+    }
+}
+
+// Read a byte count from the environment, falling back to `default`
+#[cfg(feature = "embedded")]
+fn env_bytes(name: &str, default: u64) -> u64 {
+    match env::var(name) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            println!("cargo:warning={} is not a number of bytes: {}, using {}", name, value, default);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+// A files entry is a plain name or a { from, to } rename; the repo copy uses `from`
+#[cfg(feature = "embedded")]
+fn repo_file_name(entry: &Value) -> Option<&str> {
+    match entry {
+        Value::String(file) => Some(file),
+        Value::Table(table) => table.get("from").and_then(Value::as_str),
+        _ => None,
+    }
+}
+
+// Merge the sections of the files listed in [_includes] into `distribution`,
+// the same way the binary does at runtime. Returns the included files' paths
+// relative to the repo; a file is only included once
+#[cfg(feature = "embedded")]
+fn resolve_includes(dotfiles_dir: &str, base: &Path, distribution: &mut Value, visited: &mut Vec<PathBuf>, depth: usize) -> Vec<PathBuf> {
+    let Value::Table(sections) = distribution else {
+        return Vec::new();
+    };
+    let files: Vec<String> = match sections.remove("_includes") {
+        Some(includes) => includes.get("files")
+            .and_then(Value::as_array)
+            .map(|files| files.iter().filter_map(Value::as_str).map(String::from).collect())
+            .unwrap_or_default(),
+        None => return Vec::new(),
+    };
+    
+    if depth >= 5 {
+        println!("cargo:warning=Includes are nested more than 5 levels deep, ignoring the rest");
+        return Vec::new();
+    }
+    
+    let mut included_files = Vec::new();
+    for file in files {
+        let relative = normalize(&base.join(&file));
+        if visited.contains(&relative) {
+            continue;
+        }
+        visited.push(relative.clone());
+        
+        let path = format!("{}/{}", dotfiles_dir, relative.display());
+        println!("cargo:rerun-if-changed={}", path);
+        
+        let mut included: Value = match fs::read_to_string(&path).map_err(|e| e.to_string())
+            .and_then(|content| toml::from_str(&content).map_err(|e| e.to_string())) {
+            Ok(included) => included,
+            Err(e) => {
+                println!("cargo:warning=Failed to read included file {}: {}", path, e);
+                continue;
+            }
+        };
+        
+        let parent = relative.parent().map(Path::to_path_buf).unwrap_or_default();
+        included_files.push(relative);
+        included_files.extend(resolve_includes(dotfiles_dir, &parent, &mut included, visited, depth + 1));
+        
+        if let Value::Table(included) = included {
+            for (name, section) in included {
+                if name.starts_with('_') {
+                    continue;
+                }
+                
+                let new_files = section.get("files").and_then(Value::as_array).cloned().unwrap_or_default();
+                match sections.get_mut(&name).and_then(|existing| existing.get_mut("files")) {
+                    Some(Value::Array(existing)) => {
+                        for file in new_files {
+                            if !existing.contains(&file) {
+                                existing.push(file);
+                            }
+                        }
+                    },
+                    _ => {
+                        sections.insert(name, section);
+                    }
+                }
+            }
+        }
+    }
+    
+    included_files
+}
+
+// Resolve `..` and `.` without touching the file system
+#[cfg(feature = "embedded")]
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            Component::CurDir => {},
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// Run through tests/build_script.rs, which includes this file as a module
+#[cfg(all(test, feature = "embedded"))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    
+    #[test]
+    fn record_build_writes_the_distribution_hash() {
+        let out = tempfile::tempdir().unwrap();
+        let hash = distribution_hash("[zsh]\nfiles = [\".zshrc\"]\n");
+        record_build(out.path().to_str().unwrap(), &hash, &[], &[]);
+        
+        assert_eq!(fs::read_to_string(out.path().join("dist_hash.txt")).unwrap(), hash);
+    }
+    
+    #[test]
+    fn reuse_previous_build_needs_the_same_hash_and_older_files() {
+        let out = tempfile::tempdir().unwrap();
+        let out_dir = out.path().to_str().unwrap();
+        let watched = out.path().join("distribution.toml");
+        fs::write(&watched, "[zsh]\nfiles = [\".zshrc\"]\n").unwrap();
+        File::options().write(true).open(&watched).unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(60)).unwrap();
+        write_checksums(out_dir, &[]);
+        fs::write(out.path().join("embedded_files.rs"), "").unwrap();
+        
+        let hash = distribution_hash("[zsh]\nfiles = [\".zshrc\"]\n");
+        record_build(out_dir, &hash, &[watched.display().to_string()], &[]);
+        assert!(reuse_previous_build(out_dir, &hash));
+        assert!(!reuse_previous_build(out_dir, &distribution_hash("[git]\nfiles = []\n")));
+        
+        File::options().write(true).open(&watched).unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        assert!(!reuse_previous_build(out_dir, &hash));
     }
-}
\ No newline at end of file
+}
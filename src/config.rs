@@ -1,168 +1,2647 @@
 // No top-level imports needed
 
+mod toolconfig {
+    use anyhow::{Context, Result};
+    use dirs::home_dir;
+    use serde::{Deserialize, Serialize};
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::DotfilesError;
+    use super::{DistributionSettings, FilePaths};
+
+    /// Settings for dotfiles-rs itself, stored in `$XDG_CONFIG_HOME/dotfiles-rs/config.toml`
+    /// (`~/.config/dotfiles-rs/config.toml` when `XDG_CONFIG_HOME` is unset).
+    ///
+    /// Every field is optional in the file and falls back to its default:
+    ///
+    /// ```toml
+    /// repo_dir = "~/repos/dotfiles"
+    /// config_dir = "~/.config"
+    /// symlink_mode = false
+    /// backup_dir = "~/repos/dotfiles/backups"
+    /// color_output = "auto"
+    /// default_conflict_strategy = "skip"
+    /// ```
+    ///
+    /// Paths may start with `~`, which expands to the home directory.
+    /// `symlink` and `conflict_strategy` in the `_settings` section of
+    /// `distribution.toml` take precedence over the values here.
+    ///
+    /// ```
+    /// use dotfiles_rs::ToolConfig;
+    ///
+    /// let config = ToolConfig {
+    ///     repo_dir: "~/src/dotfiles".to_string(),
+    ///     ..ToolConfig::default()
+    /// };
+    /// assert_eq!(config.config_dir, "~/.config");
+    /// ```
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct ToolConfig {
+        /// Repository holding `distribution.toml` and the dotfiles
+        pub repo_dir: String,
+        /// Directory the dotfiles are installed into
+        pub config_dir: String,
+        /// Link installed files to the repository instead of copying them
+        pub symlink_mode: bool,
+        /// Where install copies files before overwriting them, in one
+        /// timestamped directory per run; empty turns backups off
+        pub backup_dir: String,
+        /// `always`, `auto` or `never`
+        pub color_output: String,
+        /// `overwrite`, `skip` or `ask`
+        pub default_conflict_strategy: String,
+    }
+
+    impl Default for ToolConfig {
+        fn default() -> Self {
+            Self {
+                repo_dir: "~/repos/dotfiles".to_string(),
+                config_dir: "~/.config".to_string(),
+                symlink_mode: false,
+                backup_dir: "~/repos/dotfiles/backups".to_string(),
+                color_output: "auto".to_string(),
+                default_conflict_strategy: "skip".to_string(),
+            }
+        }
+    }
+
+    impl ToolConfig {
+        pub fn config_file() -> Result<PathBuf> {
+            let home = home_dir().ok_or_else(|| DotfilesError::RepoNotFound("Home directory not found".to_string()))?;
+            Ok(FilePaths::config_home(&home).join("dotfiles-rs").join("config.toml"))
+        }
+
+        // Load the config file, falling back to defaults when it doesn't exist
+        pub fn load() -> Result<Self> {
+            let path = Self::config_file()?;
+            if !path.exists() {
+                return Ok(Self::default());
+            }
+
+            let content = fs::read_to_string(&path)
+                .context("Failed to read dotfiles-rs config file")?;
+            let config: ToolConfig = toml::from_str(&content)
+                .map_err(|e| DotfilesError::InvalidCommand(format!("Invalid config file {}: {}", path.display(), e)))?;
+
+            Ok(config)
+        }
+
+        /// Load the config file, then apply `DOTFILES_RS_<KEY>` environment
+        /// variables on top, e.g. `DOTFILES_RS_REPO_DIR=~/src/dotfiles`.
+        pub fn from_env_and_file() -> Result<Self> {
+            let mut config = Self::load()?;
+            for (key, _) in config.entries() {
+                if let Ok(value) = std::env::var(format!("DOTFILES_RS_{}", key.to_uppercase())) {
+                    config.set(key, &value)?;
+                }
+            }
+
+            Ok(config)
+        }
+
+        pub fn save(&self) -> Result<PathBuf> {
+            let path = Self::config_file()?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let content = toml::to_string(self)
+                .map_err(|e| DotfilesError::InvalidCommand(format!("Failed to serialize config: {}", e)))?;
+            fs::write(&path, content)?;
+
+            Ok(path)
+        }
+
+        // Key/value pairs in the order they appear in the config file
+        pub fn entries(&self) -> Vec<(&'static str, String)> {
+            vec![
+                ("repo_dir", self.repo_dir.clone()),
+                ("config_dir", self.config_dir.clone()),
+                ("symlink_mode", self.symlink_mode.to_string()),
+                ("backup_dir", self.backup_dir.clone()),
+                ("color_output", self.color_output.clone()),
+                ("default_conflict_strategy", self.default_conflict_strategy.clone()),
+            ]
+        }
+
+        // Settings given in distribution.toml take precedence over the config file
+        pub fn merge_distribution_settings(&mut self, settings: &DistributionSettings) {
+            if let Some(symlink) = settings.symlink {
+                self.symlink_mode = symlink;
+            }
+            if let Some(conflict_strategy) = &settings.conflict_strategy {
+                self.default_conflict_strategy = conflict_strategy.clone();
+            }
+        }
+
+        pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+            match key {
+                "repo_dir" => self.repo_dir = value.to_string(),
+                "config_dir" => self.config_dir = value.to_string(),
+                "backup_dir" => self.backup_dir = value.to_string(),
+                "symlink_mode" => {
+                    self.symlink_mode = value.parse().map_err(|_| DotfilesError::InvalidCommand(
+                        format!("Invalid value for symlink_mode: {} (expected true or false)", value)))?;
+                },
+                "color_output" => {
+                    if !["always", "auto", "never"].contains(&value) {
+                        return Err(DotfilesError::InvalidCommand(
+                            format!("Invalid value for color_output: {} (expected always, auto or never)", value)).into());
+                    }
+                    self.color_output = value.to_string();
+                },
+                "default_conflict_strategy" => {
+                    if !["overwrite", "skip", "ask", "error"].contains(&value) {
+                        return Err(DotfilesError::InvalidCommand(
+                            format!("Invalid value for default_conflict_strategy: {} (expected overwrite, skip, ask or error)", value)).into());
+                    }
+                    self.default_conflict_strategy = value.to_string();
+                },
+                _ => return Err(DotfilesError::InvalidCommand(format!("Unknown config key: {}", key)).into()),
+            }
+            
+            Ok(())
+        }
+    }
+    
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        
+        fn custom_config() -> ToolConfig {
+            ToolConfig {
+                symlink_mode: true,
+                default_conflict_strategy: "overwrite".to_string(),
+                ..ToolConfig::default()
+            }
+        }
+        
+        #[test]
+        fn merge_keeps_values_missing_from_settings() {
+            let settings: DistributionSettings = toml::from_str("auto_sort = true").unwrap();
+            let mut config = custom_config();
+            config.merge_distribution_settings(&settings);
+            
+            assert!(config.symlink_mode);
+            assert_eq!(config.default_conflict_strategy, "overwrite");
+        }
+        
+        #[test]
+        fn merge_overrides_values_present_in_settings() {
+            let settings: DistributionSettings = toml::from_str("symlink = false\nconflict_strategy = \"ask\"").unwrap();
+            let mut config = custom_config();
+            config.merge_distribution_settings(&settings);
+            
+            assert!(!config.symlink_mode);
+            assert_eq!(config.default_conflict_strategy, "ask");
+        }
+    }
+}
+
 mod filepaths {
     use anyhow::Result;
     use dirs::home_dir;
-    use std::path::PathBuf;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
     use crate::DotfilesError;
+    use super::{DirType, ToolConfig};
 
     pub struct FilePaths {
+        pub home_dir: PathBuf,
         pub repo_dir: PathBuf,
         pub config_dir: PathBuf,
         pub distribution_file: PathBuf,
         pub dotignore_file: PathBuf,
+        // ~/.dotignore, whose patterns apply to every repository
+        pub global_dotignore_file: PathBuf,
+        // XDG base directories for tools with a dir_type other than config
+        pub data_dir: PathBuf,
+        pub state_dir: PathBuf,
+        pub cache_dir: PathBuf,
+        // ~/Library/Application Support and ~/Library/Preferences on macOS,
+        // config_dir everywhere else
+        pub app_support_dir: PathBuf,
+        pub preferences_dir: PathBuf,
+        // Expanded source_dir/target_dir overrides by tool
+        section_dirs: HashMap<String, SectionDirs<PathBuf>>,
+        pub env: EnvExpander,
     }
     
+    // Expands $VAR and ${VAR} references in distribution values. $HOME,
+    // $XDG_CONFIG_HOME and $HOSTNAME have fallbacks when they aren't set
+    #[derive(Debug, Clone)]
+    pub struct EnvExpander {
+        home_dir: PathBuf,
+        // Leave undefined variables as written instead of failing
+        pub allow_undefined: bool,
+    }
+    
+    impl EnvExpander {
+        pub fn new(home_dir: PathBuf) -> Self {
+            Self { home_dir, allow_undefined: false }
+        }
+        
+        pub fn expand(&self, raw: &str) -> Result<String> {
+            let mut expanded = String::new();
+            let mut rest = raw;
+            while let Some(start) = rest.find('$') {
+                expanded.push_str(&rest[..start]);
+                let after = &rest[start + 1..];
+                let (name, remainder) = match after.strip_prefix('{') {
+                    Some(braced) => match braced.split_once('}') {
+                        Some(split) => split,
+                        None => return Err(DotfilesError::InvalidCommand(format!("Unclosed ${{ in: {}", raw)).into()),
+                    },
+                    None => {
+                        let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    },
+                };
+                if name.is_empty() {
+                    return Err(DotfilesError::InvalidCommand(format!("Missing variable name after $ in: {}", raw)).into());
+                }
+                match self.variable(name) {
+                    Some(value) => expanded.push_str(&value),
+                    None if self.allow_undefined => expanded.push_str(&rest[start..rest.len() - remainder.len()]),
+                    None => return Err(DotfilesError::InvalidCommand(format!("Undefined variable: ${} (in {})", name, raw)).into()),
+                }
+                rest = remainder;
+            }
+            expanded.push_str(rest);
+            
+            Ok(expanded)
+        }
+        
+        fn variable(&self, name: &str) -> Option<String> {
+            if name == "XDG_CONFIG_HOME" {
+                return Some(FilePaths::config_home(&self.home_dir).to_string_lossy().to_string());
+            }
+            if let Some(value) = std::env::var(name).ok().filter(|value| !value.is_empty()) {
+                return Some(value);
+            }
+            match name {
+                "HOME" => Some(self.home_dir.to_string_lossy().to_string()),
+                // Shells set $HOSTNAME without exporting it
+                "HOSTNAME" => hostname::get().ok().map(|name| name.to_string_lossy().to_string()),
+                _ => None,
+            }
+        }
+    }
+    
+    // Where a tool's files live locally when not under <config_dir>/<tool>
+    #[derive(Debug, Clone, Default)]
+    pub struct SectionDirs<T = String> {
+        pub source: Option<T>,
+        pub target: Option<T>,
+        pub dir_type: DirType,
+    }
+
+    // Builds FilePaths from explicit locations; anything left unset falls back
+    // to the defaults under the home directory
+    #[derive(Default)]
+    pub struct FilePathsBuilder {
+        home_dir: Option<PathBuf>,
+        repo_dir: Option<PathBuf>,
+        config_dir: Option<PathBuf>,
+        distribution_file: Option<PathBuf>,
+        dotignore_file: Option<PathBuf>,
+    }
+    
+    impl FilePathsBuilder {
+        pub fn home_dir(mut self, dir: PathBuf) -> Self {
+            self.home_dir = Some(dir);
+            self
+        }
+        
+        pub fn repo_dir(mut self, dir: PathBuf) -> Self {
+            self.repo_dir = Some(dir);
+            self
+        }
+        
+        pub fn config_dir(mut self, dir: PathBuf) -> Self {
+            self.config_dir = Some(dir);
+            self
+        }
+        
+        // The CLI always uses the files inside the repository; these are for
+        // callers that keep them elsewhere
+        #[allow(dead_code)]
+        pub fn distribution_file(mut self, file: PathBuf) -> Self {
+            self.distribution_file = Some(file);
+            self
+        }
+        
+        #[allow(dead_code)]
+        pub fn dotignore_file(mut self, file: PathBuf) -> Self {
+            self.dotignore_file = Some(file);
+            self
+        }
+        
+        pub fn build(self) -> Result<FilePaths> {
+            let home = match self.home_dir {
+                Some(home) => home,
+                None => home_dir().ok_or_else(|| DotfilesError::RepoNotFound("Home directory not found".to_string()))?,
+            };
+            
+            let defaults = ToolConfig::default();
+            let repo_dir = self.repo_dir.unwrap_or_else(|| FilePaths::expand_home(&home, &defaults.repo_dir));
+            let config_dir = self.config_dir.unwrap_or_else(|| FilePaths::config_home(&home));
+            let distribution_file = self.distribution_file.unwrap_or_else(|| FilePaths::find_distribution_file(&repo_dir));
+            let dotignore_file = self.dotignore_file.unwrap_or_else(|| repo_dir.join(".dotignore"));
+            let library_dir = |name: &str| if FilePaths::is_macos() { home.join("Library").join(name) } else { config_dir.clone() };
+            
+            Ok(FilePaths {
+                data_dir: FilePaths::xdg_dir("XDG_DATA_HOME", &home.join(".local").join("share")),
+                state_dir: FilePaths::xdg_dir("XDG_STATE_HOME", &home.join(".local").join("state")),
+                cache_dir: FilePaths::xdg_dir("XDG_CACHE_HOME", &home.join(".cache")),
+                app_support_dir: library_dir("Application Support"),
+                preferences_dir: library_dir("Preferences"),
+                env: EnvExpander::new(home.clone()),
+                global_dotignore_file: home.join(".dotignore"),
+                home_dir: home,
+                repo_dir,
+                config_dir,
+                distribution_file,
+                dotignore_file,
+                section_dirs: HashMap::new(),
+            })
+        }
+    }
+
     impl FilePaths {
-        pub fn new() -> Result<Self> {
+        pub fn new(tool_config: &ToolConfig) -> Result<Self> {
             let home = home_dir().ok_or_else(|| DotfilesError::RepoNotFound("Home directory not found".to_string()))?;
+
+            // An explicit config_dir wins over $XDG_CONFIG_HOME
+            let config_dir = if tool_config.config_dir == ToolConfig::default().config_dir {
+                Self::config_home(&home)
+            } else {
+                Self::expand_home(&home, &tool_config.config_dir)
+            };
+            
+            FilePathsBuilder::default()
+                .repo_dir(Self::expand_home(&home, &tool_config.repo_dir))
+                .config_dir(config_dir)
+                .home_dir(home)
+                .build()
+        }
+        
+        // $XDG_CONFIG_HOME, or ~/.config when it is unset, empty or relative
+        pub fn config_home(home: &Path) -> PathBuf {
+            Self::xdg_dir("XDG_CONFIG_HOME", &home.join(".config"))
+        }
+        
+        // The same paths with another repository; the distribution and
+        // .dotignore files are looked up in the new repository
+        pub fn with_repo_dir(&self, dir: PathBuf) -> FilePaths {
+            FilePaths {
+                home_dir: self.home_dir.clone(),
+                distribution_file: Self::find_distribution_file(&dir),
+                dotignore_file: dir.join(".dotignore"),
+                global_dotignore_file: self.global_dotignore_file.clone(),
+                repo_dir: dir,
+                config_dir: self.config_dir.clone(),
+                data_dir: self.data_dir.clone(),
+                state_dir: self.state_dir.clone(),
+                cache_dir: self.cache_dir.clone(),
+                app_support_dir: self.app_support_dir.clone(),
+                preferences_dir: self.preferences_dir.clone(),
+                section_dirs: self.section_dirs.clone(),
+                env: self.env.clone(),
+            }
+        }
+        
+        // Use the source_dir/target_dir overrides from the distribution file.
+        // Overrides that don't expand are left out; precheck reports them
+        pub fn set_section_dirs(&mut self, dirs: HashMap<String, SectionDirs>) {
+            self.section_dirs = dirs.into_iter()
+                .map(|(tool, dirs)| {
+                    let expanded = SectionDirs {
+                        source: dirs.source.and_then(|raw| self.expand_path(&raw).ok()),
+                        target: dirs.target.and_then(|raw| self.expand_path(&raw).ok()),
+                        dir_type: dirs.dir_type,
+                    };
+                    (tool, expanded)
+                })
+                .collect();
+        }
+        
+        // Expand a leading ~ and $VAR or ${VAR} references. $HOME and
+        // $XDG_CONFIG_HOME fall back to the home directory and ~/.config
+        pub fn expand_path(&self, raw: &str) -> Result<PathBuf> {
+            Ok(Self::expand_home(&self.home_dir, &self.expand_env(raw)?))
+        }
+        
+        // Expand $VAR and ${VAR} references, failing on undefined variables
+        // unless env.allow_undefined is set
+        pub fn expand_env(&self, raw: &str) -> Result<String> {
+            self.env.expand(raw)
+        }
+        
+        // Prefer distribution.toml, falling back to a YAML distribution if only that exists
+        fn find_distribution_file(repo_dir: &Path) -> PathBuf {
+            let toml_file = repo_dir.join("distribution.toml");
+            if toml_file.exists() {
+                return toml_file;
+            }
+            
+            ["distribution.yaml", "distribution.yml", "distribution.json"].iter()
+                .map(|name| repo_dir.join(name))
+                .find(|path| path.exists())
+                .unwrap_or(toml_file)
+        }
+        
+        // An XDG base directory variable, which the spec only honors when it
+        // is an absolute path
+        fn xdg_dir(var: &str, default: &Path) -> PathBuf {
+            match std::env::var(var) {
+                Ok(value) if Path::new(&value).is_absolute() => PathBuf::from(value),
+                _ => default.to_path_buf(),
+            }
+        }
+        
+        // The base directory for tools of `dir_type`
+        pub fn base_dir(&self, dir_type: DirType) -> &Path {
+            match dir_type {
+                DirType::Config => &self.config_dir,
+                DirType::Data => &self.data_dir,
+                DirType::State => &self.state_dir,
+                DirType::Cache => &self.cache_dir,
+                DirType::AppSupport => &self.app_support_dir,
+                DirType::Preferences => &self.preferences_dir,
+            }
+        }
+        
+        // The app_support and preferences dir types only apply on macOS
+        pub fn is_macos() -> bool {
+            std::env::consts::OS == "macos"
+        }
+        
+        // Resolve a leading ~ against the home directory
+        fn expand_home(home: &Path, raw: &str) -> PathBuf {
+            if raw == "~" {
+                home.to_path_buf()
+            } else if let Some(rest) = raw.strip_prefix("~/") {
+                home.join(rest)
+            } else {
+                PathBuf::from(raw)
+            }
+        }
+
+        pub fn repo_config_dir(&self, section: &str) -> PathBuf {
+            self.repo_dir.join("config").join(section)
+        }
+        
+        // Sections named with a leading ~ (e.g. ~home) track files directly in $HOME
+        pub fn config_section_dir(&self, section: &str) -> PathBuf {
+            let dirs = self.section_dirs.get(section);
+            if let Some(source) = dirs.and_then(|dirs| dirs.source.as_ref()) {
+                source.clone()
+            } else if Self::is_home_section(section) {
+                self.home_dir.clone()
+            } else {
+                self.base_dir(dirs.map(|dirs| dirs.dir_type).unwrap_or_default()).join(section)
+            }
+        }
+        
+        // `path` relative to the home directory, or as it is when outside it
+        pub fn home_relative(&self, path: &Path) -> PathBuf {
+            path.strip_prefix(&self.home_dir).map(Path::to_path_buf).unwrap_or_else(|_| path.to_path_buf())
+        }
+        
+        // `path` with the home directory shown as ~, for messages
+        pub fn tilde(&self, path: &Path) -> String {
+            match path.strip_prefix(&self.home_dir) {
+                Ok(relative) => Path::new("~").join(relative).display().to_string(),
+                Err(_) => path.display().to_string(),
+            }
+        }
+        
+        pub fn is_home_section(section: &str) -> bool {
+            section.starts_with('~')
+        }
+        
+        pub fn repo_file_path(&self, section: &str, file: &str) -> PathBuf {
+            self.repo_config_dir(section).join(file)
+        }
+        
+        pub fn config_file_path(&self, section: &str, file: &str) -> PathBuf {
+            self.config_section_dir(section).join(file)
+        }
+        
+        // Where install puts a tool's files: target_dir when set, otherwise
+        // the directory sync reads from
+        pub fn install_section_dir(&self, section: &str) -> PathBuf {
+            match self.section_dirs.get(section).and_then(|dirs| dirs.target.as_ref()) {
+                Some(target) => target.clone(),
+                None => self.config_section_dir(section),
+            }
+        }
+        
+        pub fn install_file_path(&self, section: &str, file: &str) -> PathBuf {
+            self.install_section_dir(section).join(file)
+        }
+    }
+    
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::fs;
+        use tempfile::TempDir;
+        
+        fn paths() -> FilePaths {
+            FilePathsBuilder::default()
+                .home_dir(PathBuf::from("/home/me"))
+                .repo_dir(PathBuf::from("/home/me/dotfiles"))
+                .config_dir(PathBuf::from("/home/me/.config"))
+                .build()
+                .unwrap()
+        }
+        
+        #[test]
+        fn home_sections_use_the_home_directory() {
+            let paths = paths();
+            assert_eq!(paths.config_file_path("~home", ".bashrc"), PathBuf::from("/home/me/.bashrc"));
+            assert_eq!(paths.install_file_path("~home", ".bashrc"), PathBuf::from("/home/me/.bashrc"));
+            assert_eq!(paths.repo_file_path("~home", ".bashrc"), PathBuf::from("/home/me/dotfiles/config/~home/.bashrc"));
+            assert_eq!(paths.config_file_path("zsh", ".zshrc"), PathBuf::from("/home/me/.config/zsh/.zshrc"));
+        }
+        
+        #[test]
+        fn source_dir_and_target_dir_override_the_config_directory() {
+            let mut paths = paths();
+            paths.set_section_dirs(HashMap::from([
+                ("vim".to_string(), SectionDirs { source: Some("~/.vim".to_string()), target: None, dir_type: DirType::Config }),
+                ("bash".to_string(), SectionDirs { source: Some("~".to_string()), target: Some("/etc/skel".to_string()), dir_type: DirType::Config }),
+            ]));
+            
+            assert_eq!(paths.config_file_path("vim", "vimrc"), PathBuf::from("/home/me/.vim/vimrc"));
+            assert_eq!(paths.install_file_path("vim", "vimrc"), PathBuf::from("/home/me/.vim/vimrc"));
+            assert_eq!(paths.config_file_path("bash", ".bashrc"), PathBuf::from("/home/me/.bashrc"));
+            assert_eq!(paths.install_file_path("bash", ".bashrc"), PathBuf::from("/etc/skel/.bashrc"));
+        }
+        
+        #[test]
+        fn builder_fills_in_defaults_under_home() {
+            let home = TempDir::new().unwrap();
+            let config_dir = home.path().join("config");
+            let paths = FilePathsBuilder::default()
+                .home_dir(home.path().to_path_buf())
+                .config_dir(config_dir.clone())
+                .build()
+                .unwrap();
+            
+            assert_eq!(paths.home_dir, home.path());
+            let repo_dir = home.path().join("repos").join("dotfiles");
+            assert_eq!(paths.repo_dir, repo_dir);
+            assert_eq!(paths.config_dir, config_dir);
+            assert_eq!(paths.distribution_file, repo_dir.join("distribution.toml"));
+            assert_eq!(paths.dotignore_file, repo_dir.join(".dotignore"));
+            assert_eq!(paths.global_dotignore_file, home.path().join(".dotignore"));
+            
+            let custom = FilePathsBuilder::default()
+                .home_dir(home.path().to_path_buf())
+                .distribution_file(home.path().join("dist.toml"))
+                .dotignore_file(home.path().join("ignore"))
+                .build()
+                .unwrap();
+            assert_eq!(custom.distribution_file, home.path().join("dist.toml"));
+            assert_eq!(custom.dotignore_file, home.path().join("ignore"));
+        }
+        
+        #[test]
+        fn with_repo_dir_looks_up_files_in_the_new_repository() {
+            let home = TempDir::new().unwrap();
+            let other = home.path().join("other");
+            fs::create_dir_all(&other).unwrap();
+            fs::write(other.join("distribution.yaml"), "zsh:\n  files: []\n").unwrap();
+            let mut paths = FilePathsBuilder::default()
+                .home_dir(home.path().to_path_buf())
+                .repo_dir(home.path().join("dotfiles"))
+                .config_dir(home.path().join("config"))
+                .build()
+                .unwrap();
+            paths.set_section_dirs(HashMap::from([
+                ("vim".to_string(), SectionDirs { source: Some("~/.vim".to_string()), target: None, dir_type: DirType::Config }),
+            ]));
+            
+            let moved = paths.with_repo_dir(other.clone());
+            assert_eq!(moved.repo_dir, other);
+            assert_eq!(moved.distribution_file, other.join("distribution.yaml"));
+            assert_eq!(moved.dotignore_file, other.join(".dotignore"));
+            assert_eq!(moved.config_dir, paths.config_dir);
+            assert_eq!(moved.global_dotignore_file, paths.global_dotignore_file);
+            assert_eq!(moved.config_file_path("vim", "vimrc"), home.path().join(".vim").join("vimrc"));
+            assert_eq!(moved.repo_file_path("zsh", "zshrc"), other.join("config").join("zsh").join("zshrc"));
+        }
+        
+        fn config_dir_with(xdg_config_home: &str, tool_config: &ToolConfig) -> PathBuf {
+            std::env::set_var("XDG_CONFIG_HOME", xdg_config_home);
+            let paths = FilePaths::new(tool_config);
+            std::env::remove_var("XDG_CONFIG_HOME");
+            paths.unwrap().config_dir
+        }
+        
+        // The only test that changes XDG_CONFIG_HOME, so it can't race another
+        // one; it also covers ToolConfig::config_file
+        #[test]
+        fn config_dir_follows_absolute_xdg_config_home() {
+            let home = home_dir().unwrap();
+            let default = ToolConfig::default();
+            
+            assert_eq!(config_dir_with("/tmp/xdg-config", &default), PathBuf::from("/tmp/xdg-config"));
+            assert_eq!(config_dir_with("xdg-config", &default), home.join(".config"));
+            assert_eq!(config_dir_with("", &default), home.join(".config"));
+            
+            let explicit = ToolConfig { config_dir: "~/dotconfig".to_string(), ..ToolConfig::default() };
+            assert_eq!(config_dir_with("/tmp/xdg-config", &explicit), home.join("dotconfig"));
+            
+            for (xdg_config_home, expected) in [("/tmp/xdg-config", PathBuf::from("/tmp/xdg-config")), ("", home.join(".config"))] {
+                std::env::set_var("XDG_CONFIG_HOME", xdg_config_home);
+                let config_file = ToolConfig::config_file();
+                std::env::remove_var("XDG_CONFIG_HOME");
+                assert_eq!(config_file.unwrap(), expected.join("dotfiles-rs").join("config.toml"));
+            }
+        }
+    }
+}
+
+mod distribution {
+    use anyhow::{Context, Result};
+    use indexmap::IndexMap;
+    use serde::{Deserialize, Serialize};
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::io::{self, Read};
+    use std::path::{Component, Path, PathBuf};
+    use std::str::FromStr;
+    use crate::DotfilesError;
+    use crate::DotfilesArchive;
+    use crate::util::{self, RetryPolicy};
+    use super::{EnvExpander, FilePaths, SectionDirs};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct Distribution {
+        #[serde(rename = "_settings", default, skip_serializing_if = "Option::is_none")]
+        pub settings: Option<DistributionSettings>,
+        
+        // Other distribution files whose sections are merged into this one
+        #[serde(rename = "_includes", default, skip_serializing_if = "Option::is_none")]
+        pub includes: Option<Includes>,
+        
+        // Settings only read by build.rs when embedding files
+        #[serde(rename = "_build", default, skip_serializing_if = "Option::is_none")]
+        pub build: Option<BuildSettings>,
+        
+        // Named groups of tools selected with --profile
+        #[serde(rename = "_profiles", default, skip_serializing_if = "Option::is_none")]
+        pub profiles: Option<IndexMap<String, Vec<String>>>,
+        
+        // IndexMap keeps sections in file order so writes don't reshuffle them
+        #[serde(flatten)]
+        pub sections: IndexMap<String, Section>,
+    }
+    
+    // The reserved [_includes] section; paths are relative to the including file
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Includes {
+        #[serde(default)]
+        pub files: Vec<String>,
+    }
+    
+    // The reserved [_build] section. `large_files` lists "tool/file" entries
+    // embedded regardless of BUILD_MAX_EMBED_BYTES
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct BuildSettings {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub large_files: Vec<String>,
+    }
+    
+    pub const MAX_INCLUDE_DEPTH: usize = 5;
+    
+    // Resolve `..` and `.` without touching the file system, as embedded
+    // includes have no file system to canonicalize against
+    fn normalize_path(path: &Path) -> PathBuf {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::ParentDir => {
+                    normalized.pop();
+                },
+                Component::CurDir => {},
+                component => normalized.push(component),
+            }
+        }
+        normalized
+    }
+    
+    // Repository-wide settings from the reserved [_settings] section. Keys
+    // that aren't in the file stay None so they don't override config.toml
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct DistributionSettings {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub symlink: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub preserve_permissions: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub backup_before_install: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub conflict_strategy: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub auto_sort: Option<bool>,
+    }
+    
+    impl DistributionSettings {
+        pub const KNOWN_KEYS: &'static [&'static str] = &[
+            "symlink",
+            "preserve_permissions",
+            "backup_before_install",
+            "conflict_strategy",
+            "auto_sort",
+        ];
+        
+        // Check field values, returning a message for each invalid one
+        pub fn validate(&self) -> Vec<String> {
+            let mut problems = Vec::new();
             
-            let repo_dir = home.join("repos").join("dotfiles");
-            let config_dir = home.join(".config");
-            let distribution_file = repo_dir.join("distribution.toml");
-            let dotignore_file = repo_dir.join(".dotignore");
+            if let Some(conflict_strategy) = &self.conflict_strategy {
+                if !["overwrite", "skip", "ask", "error"].contains(&conflict_strategy.as_str()) {
+                    problems.push(format!(
+                        "Invalid conflict_strategy: {} (expected overwrite, skip, ask or error)",
+                        conflict_strategy
+                    ));
+                }
+            }
+            
+            problems
+        }
+        
+        // Find keys in the raw [_settings] table that aren't recognized
+        pub fn unknown_keys(content: &str, format: DistributionFormat) -> Vec<String> {
+            #[derive(Deserialize)]
+            struct RawSettings {
+                #[serde(rename = "_settings", default)]
+                settings: IndexMap<String, serde::de::IgnoredAny>,
+            }
+            
+            let raw: Option<RawSettings> = match format {
+                DistributionFormat::Toml => toml::from_str(content).ok(),
+                DistributionFormat::Yaml => serde_yaml::from_str(content).ok(),
+                DistributionFormat::Json => serde_json::from_str(content).ok(),
+            };
+            
+            match raw {
+                Some(raw) => raw.settings.keys()
+                    .filter(|key| !Self::KNOWN_KEYS.contains(&key.as_str()))
+                    .cloned()
+                    .collect(),
+                None => Vec::new(),
+            }
+        }
+    }
+    
+    // The base directory a tool's files live under
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum DirType {
+        // config_dir from the dotfiles-rs config, ~/.config by default
+        #[default]
+        Config,
+        // $XDG_DATA_HOME, ~/.local/share by default
+        Data,
+        // $XDG_STATE_HOME, ~/.local/state by default
+        State,
+        // $XDG_CACHE_HOME, ~/.cache by default
+        Cache,
+        // ~/Library/Application Support on macOS, config_dir elsewhere
+        AppSupport,
+        // ~/Library/Preferences on macOS, config_dir elsewhere
+        Preferences,
+    }
+    
+    impl DirType {
+        pub fn name(self) -> &'static str {
+            match self {
+                DirType::Config => "config",
+                DirType::Data => "data",
+                DirType::State => "state",
+                DirType::Cache => "cache",
+                DirType::AppSupport => "app_support",
+                DirType::Preferences => "preferences",
+            }
+        }
+        
+        pub fn macos_only(self) -> bool {
+            matches!(self, DirType::AppSupport | DirType::Preferences)
+        }
+    }
+    
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct Section {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub description: Option<String>,
+        
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub url: Option<String>,
+        
+        #[serde(default)]
+        pub files: Vec<FileEntry>,
+        
+        // Disabled sections stay in the file but are skipped by sync/install/status
+        #[serde(default = "default_true", skip_serializing_if = "is_true")]
+        pub enabled: bool,
+        
+        // Lower numbers are installed, synced and checked first
+        #[serde(default = "default_priority", skip_serializing_if = "is_default_priority")]
+        pub priority: u32,
+        
+        // Files are stored in the repo as <file>.gpg, encrypted for gpg_key_id
+        // (or the default GPG key when unset)
+        #[serde(default, skip_serializing_if = "is_false")]
+        pub encrypted: bool,
+        
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub gpg_key_id: Option<String>,
+        
+        // Files are minijinja templates, rendered on install with the
+        // variables in config/<tool>/variables.toml
+        #[serde(default, skip_serializing_if = "is_false")]
+        pub template: bool,
+        
+        // Labels for selecting groups of tools with --tag
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub tags: Vec<String>,
+        
+        // Only process the tool on these operating systems, named as in
+        // std::env::consts::OS (e.g. linux or macos), and on these hosts.
+        // Empty lists match everywhere
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub os: Vec<String>,
+        
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub machines: Vec<String>,
+        
+        // Local directory to sync from instead of <config_dir>/<tool>, e.g. ~/.vim.
+        // May contain ~, $HOME and $XDG_CONFIG_HOME
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub source_dir: Option<String>,
+        
+        // Local directory to install into, when it differs from source_dir
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub target_dir: Option<String>,
+        
+        // Which XDG base directory holds <tool>/ when source_dir isn't set
+        #[serde(default, skip_serializing_if = "is_config_dir_type")]
+        pub dir_type: DirType,
+        
+        // Shell command run in config/<tool>/ in the repo after sync copies
+        // files of the tool, e.g. to strip machine-specific settings
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub post_sync: Option<String>,
+    }
+    
+    impl Section {
+        // The file name under config/<tool>/ in the repository, including the
+        // .gpg extension of encrypted sections
+        pub fn repo_file_name(&self, entry: &FileEntry) -> String {
+            if self.encrypted {
+                format!("{}.gpg", entry.source())
+            } else {
+                entry.source().to_string()
+            }
+        }
+        
+        // Whether `os` and `machines` include this machine
+        pub fn applies_here(&self) -> bool {
+            let os_matches = self.os.is_empty() || self.os.iter().any(|os| os == std::env::consts::OS);
+            os_matches && (self.machines.is_empty() || hostname::get().is_ok_and(|name| {
+                self.machines.iter().any(|machine| machine.as_str() == name.to_string_lossy())
+            }))
+        }
+    }
+    
+    pub const DEFAULT_PRIORITY: u32 = 100;
+    
+    fn default_priority() -> u32 {
+        DEFAULT_PRIORITY
+    }
+    
+    fn is_default_priority(priority: &u32) -> bool {
+        *priority == DEFAULT_PRIORITY
+    }
+    
+    // A files entry is either a plain file name or an inline table that installs
+    // a repo file under a different local name
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(untagged)]
+    pub enum FileEntry {
+        Simple(String),
+        Rename { from: String, to: String },
+    }
+    
+    impl FileEntry {
+        // The file name under config/<tool>/ in the repository
+        pub fn source(&self) -> &str {
+            match self {
+                FileEntry::Simple(file) => file,
+                FileEntry::Rename { from, .. } => from,
+            }
+        }
+        
+        // The file name in the local config directory
+        pub fn destination(&self) -> &str {
+            match self {
+                FileEntry::Simple(file) => file,
+                FileEntry::Rename { to, .. } => to,
+            }
+        }
+    }
+    
+    fn default_true() -> bool {
+        true
+    }
+    
+    fn is_true(value: &bool) -> bool {
+        *value
+    }
+    
+    fn is_false(value: &bool) -> bool {
+        !*value
+    }
+    
+    fn is_config_dir_type(dir_type: &DirType) -> bool {
+        *dir_type == DirType::Config
+    }
+    
+    // Metadata written by add_file when it creates a new section
+    #[derive(Debug, Default)]
+    pub struct SectionMetadata {
+        pub description: Option<String>,
+        pub url: Option<String>,
+        pub tags: Vec<String>,
+    }
+    
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Severity {
+        Error,
+        Warning,
+        Info,
+    }
+    
+    // A single problem found by DistributionParser::validate
+    #[derive(Debug)]
+    pub struct ValidationWarning {
+        pub severity: Severity,
+        pub tool: String,
+        pub file: Option<String>,
+        pub message: String,
+    }
+    
+    impl ValidationWarning {
+        fn new(severity: Severity, tool: &str, file: Option<&str>, message: &str) -> Self {
+            Self {
+                severity,
+                tool: tool.to_string(),
+                file: file.map(|f| f.to_string()),
+                message: message.to_string(),
+            }
+        }
+    }
+    
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum DistributionFormat {
+        Toml,
+        Yaml,
+        Json,
+    }
+    
+    impl DistributionFormat {
+        // Pick the format from a file extension, defaulting to TOML
+        pub fn from_path(path: &Path) -> Self {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("yaml") | Some("yml") => DistributionFormat::Yaml,
+                Some("json") => DistributionFormat::Json,
+                _ => DistributionFormat::Toml,
+            }
+        }
+        
+        pub fn name(&self) -> &'static str {
+            match self {
+                DistributionFormat::Toml => "TOML",
+                DistributionFormat::Yaml => "YAML",
+                DistributionFormat::Json => "JSON",
+            }
+        }
+    }
+    
+    impl FromStr for DistributionFormat {
+        type Err = String;
+        
+        fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+            match name.to_lowercase().as_str() {
+                "toml" => Ok(DistributionFormat::Toml),
+                "yaml" | "yml" => Ok(DistributionFormat::Yaml),
+                "json" => Ok(DistributionFormat::Json),
+                _ => Err(format!("unknown format: {} (expected toml, yaml or json)", name)),
+            }
+        }
+    }
+    
+    // Where a section's `files = [...]` array sits in the distribution text
+    struct FilesArray {
+        // Offsets of the opening and closing brackets
+        open: usize,
+        close: usize,
+        // Start and end offsets of each element
+        elements: Vec<(usize, usize)>,
+        // Whether the last element is followed by a comma
+        trailing_comma: bool,
+    }
+    
+    #[derive(Clone)]
+    pub enum DistributionSource {
+        File(PathBuf),
+        Embedded,
+        // Read once from standard input with --from-stdin; can't be edited
+        Stdin(String),
+    }
+    
+    #[derive(Clone)]
+    pub struct DistributionParser {
+        pub source: DistributionSource,
+        pub format: DistributionFormat,
+        pub retry_policy: RetryPolicy,
+        // Expands variables in file entries; None leaves them as written
+        pub env: Option<EnvExpander>,
+    }
+    
+    impl DistributionParser {
+        pub fn new(path: PathBuf) -> Self {
+            let format = DistributionFormat::from_path(&path);
+            Self { source: DistributionSource::File(path), format, retry_policy: RetryPolicy::default(), env: None }
+        }
+        
+        pub fn from_embedded() -> Self {
+            Self { source: DistributionSource::Embedded, format: DistributionFormat::Toml, retry_policy: RetryPolicy::default(), env: None }
+        }
+        
+        // Read the whole distribution from standard input, as TOML unless
+        // with_format says otherwise. Included files are relative to the
+        // current directory
+        pub fn from_stdin() -> Result<Self> {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content).context("Failed to read distribution from stdin")?;
+            Ok(Self { source: DistributionSource::Stdin(content), format: DistributionFormat::Toml, retry_policy: RetryPolicy::default(), env: None })
+        }
+        
+        pub fn reads_stdin(&self) -> bool {
+            matches!(self.source, DistributionSource::Stdin(_))
+        }
+        
+        // Override the format detected from the file extension
+        pub fn with_format(mut self, format: DistributionFormat) -> Self {
+            self.format = format;
+            self
+        }
+        
+        pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+            self.retry_policy = retry_policy;
+            self
+        }
+        
+        // Expand $VAR and ${VAR} in the file entries get_files and
+        // get_file_entries return. Writes keep the entries as written
+        pub fn with_env(mut self, env: EnvExpander) -> Self {
+            self.env = Some(env);
+            self
+        }
+        
+        // The distribution with the sections of all included files merged in
+        pub fn read_distribution(&self) -> Result<Distribution> {
+            let mut distribution = self.read_without_includes()?;
+            for (_, included) in self.read_includes(&distribution)? {
+                for (tool, section) in included.sections {
+                    match distribution.sections.get_mut(&tool) {
+                        Some(existing) => {
+                            for entry in section.files {
+                                if !existing.files.iter().any(|e| e.destination() == entry.destination()) {
+                                    existing.files.push(entry);
+                                }
+                            }
+                        },
+                        None => {
+                            distribution.sections.insert(tool, section);
+                        },
+                    }
+                }
+            }
+            distribution.includes = None;
+            Ok(distribution)
+        }
+        
+        // Every file pulled in through the [_includes] of `distribution`,
+        // nested ones right after the file including them, each parsed
+        // without its own includes merged
+        pub fn read_includes(&self, distribution: &Distribution) -> Result<Vec<(PathBuf, Distribution)>> {
+            let base = match &self.source {
+                DistributionSource::File(path) => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+                DistributionSource::Embedded | DistributionSource::Stdin(_) => PathBuf::new(),
+            };
+            
+            // Seed with this file so an include cycle back to it is skipped
+            let mut visited = match &self.source {
+                DistributionSource::File(path) => path.canonicalize().into_iter().collect(),
+                DistributionSource::Embedded => vec![PathBuf::from("distribution.toml")],
+                DistributionSource::Stdin(_) => Vec::new(),
+            };
+            let mut included = Vec::new();
+            if let Some(includes) = &distribution.includes {
+                self.collect_includes(includes, &base, &mut visited, 0, &mut included)?;
+            }
+            Ok(included)
+        }
+        
+        // The distribution file on its own, as edits must write it back
+        pub fn read_without_includes(&self) -> Result<Distribution> {
+            self.parse(&self.read_content()?)
+        }
+        
+        // The unparsed text of the distribution file
+        pub fn read_content(&self) -> Result<String> {
+            match &self.source {
+                DistributionSource::File(path) => fs::read_to_string(path)
+                    .context("Failed to read distribution file"),
+                DistributionSource::Embedded => DotfilesArchive::get_distribution(),
+                DistributionSource::Stdin(content) => Ok(content.clone()),
+            }
+        }
+        
+        // Add each file in `includes` to `included`, followed by the files it
+        // includes. A file that was already included (including through a
+        // cycle) is skipped
+        fn collect_includes(&self, includes: &Includes, base: &Path, visited: &mut Vec<PathBuf>, depth: usize,
+            included: &mut Vec<(PathBuf, Distribution)>) -> Result<()> {
+            if depth >= MAX_INCLUDE_DEPTH {
+                return Err(DotfilesError::DistributionParseError(format!(
+                    "Includes are nested more than {} levels deep", MAX_INCLUDE_DEPTH)).into());
+            }
+            
+            for file in &includes.files {
+                let path = normalize_path(&base.join(file));
+                let include_error = |e: &dyn std::fmt::Display| DotfilesError::DistributionParseError(
+                    format!("Included file {}: {}", path.display(), e));
+                
+                let key = match &self.source {
+                    DistributionSource::File(_) | DistributionSource::Stdin(_) => path.canonicalize().map_err(|e| include_error(&e))?,
+                    DistributionSource::Embedded => path.clone(),
+                };
+                if visited.contains(&key) {
+                    continue;
+                }
+                visited.push(key);
+                
+                let content = match &self.source {
+                    DistributionSource::File(_) | DistributionSource::Stdin(_) => fs::read_to_string(&path).map_err(|e| include_error(&e))?,
+                    DistributionSource::Embedded => DotfilesArchive::get_include(&path.to_string_lossy())?,
+                };
+                let distribution = Self::parse_as(&content, DistributionFormat::from_path(&path))
+                    .map_err(|e| include_error(&e))?;
+                
+                let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                let nested = distribution.includes.clone();
+                included.push((path, distribution));
+                if let Some(nested) = nested {
+                    self.collect_includes(&nested, &parent, visited, depth + 1, included)?;
+                }
+            }
+            
+            Ok(())
+        }
+        
+        pub fn parse(&self, content: &str) -> Result<Distribution> {
+            Self::parse_as(content, self.format)
+        }
+        
+        fn parse_as(content: &str, format: DistributionFormat) -> Result<Distribution> {
+            match format {
+                DistributionFormat::Toml => toml::from_str(content)
+                    .map_err(|e| DotfilesError::DistributionParseError(e.to_string()).into()),
+                DistributionFormat::Yaml => Self::from_yaml(content),
+                DistributionFormat::Json => Self::from_json(content),
+            }
+        }
+        
+        pub fn from_yaml(content: &str) -> Result<Distribution> {
+            serde_yaml::from_str(content)
+                .map_err(|e| DotfilesError::DistributionParseError(e.to_string()).into())
+        }
+        
+        pub fn to_yaml(&self) -> Result<String> {
+            let distribution = self.read_distribution()?;
+            Self::serialize_as(&distribution, DistributionFormat::Yaml)
+        }
+        
+        pub fn from_json(content: &str) -> Result<Distribution> {
+            serde_json::from_str(content)
+                .map_err(|e| DotfilesError::DistributionParseError(e.to_string()).into())
+        }
+        
+        pub fn to_json(&self) -> Result<String> {
+            let distribution = self.read_distribution()?;
+            Self::serialize_as(&distribution, DistributionFormat::Json)
+        }
+        
+        pub fn serialize_as(distribution: &Distribution, format: DistributionFormat) -> Result<String> {
+            let content = match format {
+                DistributionFormat::Toml => toml::to_string(distribution)
+                    .map_err(|e| DotfilesError::DistributionParseError(format!("Failed to serialize: {}", e)))?,
+                DistributionFormat::Yaml => serde_yaml::to_string(distribution)
+                    .map_err(|e| DotfilesError::DistributionParseError(format!("Failed to serialize: {}", e)))?,
+                DistributionFormat::Json => serde_json::to_string_pretty(distribution)
+                    .map_err(|e| DotfilesError::DistributionParseError(format!("Failed to serialize: {}", e)))? + "\n",
+            };
+            
+            Ok(content)
+        }
+        
+        // Serialize to TOML in a fixed layout so rewrites produce clean diffs:
+        // sections and files sorted, one file per line, a blank line between sections
+        pub fn write_pretty(&self, distribution: &Distribution) -> Result<String> {
+            let serialize_error = |e: toml::ser::Error| DotfilesError::DistributionParseError(format!("Failed to serialize: {}", e));
+            let mut content = String::new();
+            
+            if let Some(settings) = &distribution.settings {
+                content.push_str("[_settings]\n");
+                content.push_str(&toml::to_string(settings).map_err(serialize_error)?);
+            }
+            
+            if let Some(includes) = &distribution.includes {
+                if !content.is_empty() {
+                    content.push('\n');
+                }
+                content.push_str("[_includes]\n");
+                content.push_str(&toml::to_string(includes).map_err(serialize_error)?);
+            }
+            
+            if let Some(build) = &distribution.build {
+                if !content.is_empty() {
+                    content.push('\n');
+                }
+                content.push_str("[_build]\n");
+                content.push_str(&toml::to_string(build).map_err(serialize_error)?);
+            }
+            
+            if let Some(profiles) = &distribution.profiles {
+                if !content.is_empty() {
+                    content.push('\n');
+                }
+                content.push_str("[_profiles]\n");
+                for (name, tools) in profiles {
+                    let tools: Vec<String> = tools.iter().map(|tool| toml::Value::String(tool.clone()).to_string()).collect();
+                    content.push_str(&format!("{} = [{}]\n", Self::toml_key(name), tools.join(", ")));
+                }
+            }
+            
+            let mut tools: Vec<&String> = distribution.sections.keys().collect();
+            tools.sort();
+            
+            for tool in tools {
+                let section = &distribution.sections[tool];
+                if !content.is_empty() {
+                    content.push('\n');
+                }
+                content.push_str(&format!("[{}]\n", Self::toml_key(tool)));
+                
+                if let Some(description) = &section.description {
+                    content.push_str(&format!("description = {}\n", toml::Value::String(description.clone())));
+                }
+                if let Some(url) = &section.url {
+                    content.push_str(&format!("url = {}\n", toml::Value::String(url.clone())));
+                }
+                for (key, values) in [("tags", &section.tags), ("os", &section.os), ("machines", &section.machines)] {
+                    if !values.is_empty() {
+                        let values: Vec<String> = values.iter().map(|value| toml::Value::String(value.clone()).to_string()).collect();
+                        content.push_str(&format!("{} = [{}]\n", key, values.join(", ")));
+                    }
+                }
+                if let Some(source_dir) = &section.source_dir {
+                    content.push_str(&format!("source_dir = {}\n", toml::Value::String(source_dir.clone())));
+                }
+                if let Some(target_dir) = &section.target_dir {
+                    content.push_str(&format!("target_dir = {}\n", toml::Value::String(target_dir.clone())));
+                }
+                if section.dir_type != DirType::Config {
+                    content.push_str(&format!("dir_type = \"{}\"\n", section.dir_type.name()));
+                }
+                if let Some(post_sync) = &section.post_sync {
+                    content.push_str(&format!("post_sync = {}\n", toml::Value::String(post_sync.clone())));
+                }
+                
+                let mut files: Vec<&FileEntry> = section.files.iter().collect();
+                files.sort_by(|a, b| a.destination().cmp(b.destination()));
+                if files.is_empty() {
+                    content.push_str("files = []\n");
+                } else {
+                    content.push_str("files = [\n");
+                    for entry in files {
+                        let value = match entry {
+                            FileEntry::Simple(file) => toml::Value::String(file.clone()).to_string(),
+                            FileEntry::Rename { from, to } => format!("{{ from = {}, to = {} }}",
+                                toml::Value::String(from.clone()), toml::Value::String(to.clone())),
+                        };
+                        content.push_str(&format!("  {},\n", value));
+                    }
+                    content.push_str("]\n");
+                }
+                
+                if !section.enabled {
+                    content.push_str("enabled = false\n");
+                }
+                if section.priority != DEFAULT_PRIORITY {
+                    content.push_str(&format!("priority = {}\n", section.priority));
+                }
+                if section.encrypted {
+                    content.push_str("encrypted = true\n");
+                }
+                if let Some(key_id) = &section.gpg_key_id {
+                    content.push_str(&format!("gpg_key_id = {}\n", toml::Value::String(key_id.clone())));
+                }
+                if section.template {
+                    content.push_str("template = true\n");
+                }
+            }
+            
+            Ok(content)
+        }
+        
+        // Style problems in the distribution file itself, without includes.
+        // Only files listed in several sections and values with trailing
+        // whitespace are errors
+        pub fn lint(&self) -> Result<Vec<ValidationWarning>> {
+            if self.format != DistributionFormat::Toml {
+                return Err(DotfilesError::InvalidCommand(format!(
+                    "Only TOML distribution files can be linted, not {}", self.format.name())).into());
+            }
+            let content = self.read_content()?;
+            let distribution = self.parse(&content)?;
+            let table: toml::Table = toml::from_str(&content)
+                .map_err(|e| DotfilesError::DistributionParseError(e.to_string()))?;
+            let mut warnings = Vec::new();
+            
+            let tools: Vec<&String> = distribution.sections.keys().collect();
+            for pair in tools.windows(2) {
+                if pair[1] < pair[0] {
+                    warnings.push(ValidationWarning::new(
+                        Severity::Warning, pair[1], None,
+                        &format!("Section is not in alphabetical order (comes after {})", pair[0])));
+                }
+            }
+            
+            let mut owners: HashMap<&str, &str> = HashMap::new();
+            for (tool, section) in &distribution.sections {
+                for pair in section.files.windows(2) {
+                    if pair[1].destination() < pair[0].destination() {
+                        warnings.push(ValidationWarning::new(
+                            Severity::Warning, tool, Some(pair[1].destination()),
+                            &format!("File is not in alphabetical order (comes after {})", pair[0].destination())));
+                    }
+                }
+                
+                if section.description.is_none() {
+                    warnings.push(ValidationWarning::new(Severity::Warning, tool, None, "Section has no description"));
+                }
+                
+                let explicitly_enabled = table.get(tool.as_str())
+                    .and_then(|section| section.get("enabled"))
+                    .and_then(toml::Value::as_bool) == Some(true);
+                if explicitly_enabled {
+                    warnings.push(ValidationWarning::new(
+                        Severity::Warning, tool, None, "enabled = true is the default and can be removed"));
+                }
+                
+                for file in section.files.iter().map(FileEntry::destination) {
+                    match owners.get(file) {
+                        Some(other) if other != tool => warnings.push(ValidationWarning::new(
+                            Severity::Error, tool, Some(file), &format!("Also listed under {}", other))),
+                        Some(_) => {},
+                        None => {
+                            owners.insert(file, tool);
+                        },
+                    }
+                }
+            }
+            
+            for (key, value) in &table {
+                Self::lint_whitespace(key, key, value, &mut warnings);
+            }
+            
+            Ok(warnings)
+        }
+        
+        // Report strings under `value` that end in whitespace. `path` is the
+        // dotted key of `value` within the section `tool`
+        fn lint_whitespace(tool: &str, path: &str, value: &toml::Value, warnings: &mut Vec<ValidationWarning>) {
+            match value {
+                toml::Value::String(text) if text.trim_end() != text => warnings.push(ValidationWarning::new(
+                    Severity::Error, tool, None, &format!("{} has trailing whitespace: {:?}", path, text))),
+                toml::Value::Array(values) => {
+                    for value in values {
+                        Self::lint_whitespace(tool, path, value, warnings);
+                    }
+                },
+                toml::Value::Table(table) => {
+                    for (key, value) in table {
+                        let path = if path == tool { key.clone() } else { format!("{}.{}", path, key) };
+                        Self::lint_whitespace(tool, &path, value, warnings);
+                    }
+                },
+                _ => {},
+            }
+        }
+        
+        // Rewrite the distribution file in the `write_pretty` layout; returns
+        // false if it was already formatted
+        pub fn format_file(&self) -> Result<bool> {
+            if self.format != DistributionFormat::Toml {
+                return Err(DotfilesError::InvalidCommand(format!(
+                    "Only TOML distribution files can be formatted, not {}", self.format.name())).into());
+            }
+            
+            let current = match &self.source {
+                DistributionSource::File(path) => fs::read_to_string(path)
+                    .context("Failed to read distribution file")?,
+                DistributionSource::Embedded => return Err(DotfilesError::InvalidCommand(
+                    "Cannot modify distribution file in embedded mode".to_string()).into()),
+                DistributionSource::Stdin(_) => return Err(DotfilesError::InvalidCommand(
+                    "Cannot modify a distribution read from stdin".to_string()).into()),
+            };
+            let formatted = self.write_pretty(&self.parse(&current)?)?;
+            
+            if formatted == current {
+                return Ok(false);
+            }
+            self.write_content(&formatted)?;
+            Ok(true)
+        }
+        
+        // Section names that aren't bare TOML keys (e.g. "~home") need quoting
+        fn toml_key(name: &str) -> String {
+            let bare = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if bare {
+                name.to_string()
+            } else {
+                toml::Value::String(name.to_string()).to_string()
+            }
+        }
+        
+        // TOML files are written in the `write_pretty` layout; other formats as serde emits them
+        fn serialize_for_write(&self, distribution: &Distribution) -> Result<String> {
+            match self.format {
+                DistributionFormat::Toml => self.write_pretty(distribution),
+                format => Self::serialize_as(distribution, format),
+            }
+        }
+        
+        // Check section contents beyond TOML syntax; repo_config_dir is used to
+        // find files that are listed but missing from the repository
+        pub fn validate(&self, repo_config_dir: &Path) -> Result<Vec<ValidationWarning>> {
+            let distribution = self.read_distribution()?;
+            let mut warnings = Vec::new();
+            let mut owners: HashMap<&str, &str> = HashMap::new();
+            
+            let mut tools: Vec<&String> = distribution.sections.keys().collect();
+            tools.sort();
+            
+            let mut priorities: HashMap<u32, &str> = HashMap::new();
+            for tool in tools.iter().copied() {
+                let priority = distribution.sections[tool].priority;
+                if priority == 0 || priority == DEFAULT_PRIORITY {
+                    continue;
+                }
+                if let Some(other) = priorities.insert(priority, tool) {
+                    warnings.push(ValidationWarning::new(
+                        Severity::Warning, tool, None,
+                        &format!("Same priority ({}) as {}", priority, other)));
+                }
+            }
+            
+            for tool in tools {
+                let section = &distribution.sections[tool];
+                
+                if section.files.is_empty() {
+                    warnings.push(ValidationWarning::new(
+                        Severity::Warning, tool, None, "Section has no files (+gc removes it)"));
+                }
+                
+                if section.encrypted && section.gpg_key_id.is_none() {
+                    warnings.push(ValidationWarning::new(
+                        Severity::Warning, tool, None, "Encrypted section has no gpg_key_id, the default GPG key is used"));
+                }
+                
+                if section.dir_type.macos_only() && !FilePaths::is_macos() && section.source_dir.is_none() {
+                    warnings.push(ValidationWarning::new(
+                        Severity::Warning, tool, None,
+                        &format!("dir_type \"{}\" only applies on macOS, config_dir is used instead", section.dir_type.name())));
+                }
+                
+                let mut seen = Vec::new();
+                for entry in &section.files {
+                    let file = entry.destination();
+                    let invalid = [entry.source(), file].iter().any(|name| {
+                        let path = Path::new(name);
+                        name.is_empty() || path.is_absolute()
+                            || path.components().any(|c| matches!(c, Component::ParentDir))
+                    });
+                    if invalid {
+                        warnings.push(ValidationWarning::new(
+                            Severity::Error, tool, Some(file),
+                            "Path must be relative and must not contain '..'"));
+                        continue;
+                    }
+                    
+                    if entry.source().contains('\\') || file.contains('\\') {
+                        warnings.push(ValidationWarning::new(
+                            Severity::Warning, tool, Some(file), "Path uses '\\' instead of '/'"));
+                    }
+                    
+                    if seen.contains(&file) {
+                        warnings.push(ValidationWarning::new(
+                            Severity::Warning, tool, Some(file), "Duplicate entry in section"));
+                        continue;
+                    }
+                    seen.push(file);
+                    
+                    if let Some(other) = owners.insert(file, tool) {
+                        warnings.push(ValidationWarning::new(
+                            Severity::Info, tool, Some(file),
+                            &format!("Also listed under {}", other)));
+                    }
+                    
+                    if !repo_config_dir.join(tool).join(section.repo_file_name(entry)).exists() {
+                        warnings.push(ValidationWarning::new(
+                            Severity::Warning, tool, Some(file), "Missing from repository"));
+                    }
+                }
+            }
+            
+            Ok(warnings)
+        }
+        
+        // Encrypted tools and the GPG key each one is encrypted for
+        pub fn encrypted_tools(&self) -> Result<HashMap<String, Option<String>>> {
+            let distribution = self.read_distribution()?;
+            Ok(distribution.sections.into_iter()
+                .filter(|(_, section)| section.encrypted)
+                .map(|(tool, section)| (tool, section.gpg_key_id))
+                .collect())
+        }
+        
+        // The post_sync command of each tool that has one
+        pub fn post_sync_commands(&self) -> Result<HashMap<String, String>> {
+            let distribution = self.read_distribution()?;
+            Ok(distribution.sections.into_iter()
+                .filter_map(|(tool, section)| section.post_sync.map(|command| (tool, command)))
+                .collect())
+        }
+        
+        // Tools whose files are rendered as templates
+        pub fn template_tools(&self) -> Result<HashSet<String>> {
+            let distribution = self.read_distribution()?;
+            Ok(distribution.sections.into_iter()
+                .filter(|(_, section)| section.template)
+                .map(|(tool, _)| tool)
+                .collect())
+        }
+        
+        // The source_dir, target_dir and dir_type of the tools that set any,
+        // as written in the distribution file
+        pub fn section_dirs(&self) -> Result<HashMap<String, SectionDirs>> {
+            let distribution = self.read_distribution()?;
+            Ok(distribution.sections.into_iter()
+                .filter(|(_, section)| section.source_dir.is_some() || section.target_dir.is_some() || section.dir_type != DirType::Config)
+                .map(|(tool, section)| (tool, SectionDirs {
+                    source: section.source_dir,
+                    target: section.target_dir,
+                    dir_type: section.dir_type,
+                }))
+                .collect())
+        }
+        
+        // Whether a tool has any of `tags`; every tool matches an empty list
+        pub fn has_any_tag(&self, tool: &str, tags: &[String]) -> Result<bool> {
+            if tags.is_empty() {
+                return Ok(true);
+            }
+            let distribution = self.read_distribution()?;
+            Ok(distribution.sections.get(tool)
+                .is_some_and(|section| section.tags.iter().any(|tag| tags.contains(tag))))
+        }
+        
+        // Every tag in use and how many tools carry it, sorted by tag
+        pub fn tag_counts(&self) -> Result<Vec<(String, usize)>> {
+            let distribution = self.read_distribution()?;
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for section in distribution.sections.values() {
+                let mut tags = section.tags.clone();
+                tags.sort();
+                tags.dedup();
+                for tag in tags {
+                    *counts.entry(tag).or_default() += 1;
+                }
+            }
+            
+            let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+            counts.sort();
+            Ok(counts)
+        }
+        
+        // Every profile and the tools it lists, in file order
+        pub fn profiles(&self) -> Result<IndexMap<String, Vec<String>>> {
+            Ok(self.read_distribution()?.profiles.unwrap_or_default())
+        }
+        
+        pub fn profile_tools(&self, profile: &str) -> Result<Vec<String>> {
+            self.profiles()?.shift_remove(profile)
+                .ok_or_else(|| DotfilesError::InvalidCommand(format!("Profile '{}' not found", profile)).into())
+        }
+        
+        // Add a tool to a profile, creating the profile if needed. Returns
+        // false if the profile already lists it
+        pub fn add_profile_tool(&self, profile: &str, tool: &str) -> Result<bool> {
+            let tools = self.get_tools()?;
+            if !tools.iter().any(|name| name == tool) {
+                return Err(DotfilesError::tool_not_found(tool, &tools).into());
+            }
+            
+            let mut distribution = self.read_without_includes()?;
+            let tools = distribution.profiles.get_or_insert_with(IndexMap::new)
+                .entry(profile.to_string())
+                .or_default();
+            if tools.iter().any(|name| name == tool) {
+                return Ok(false);
+            }
+            tools.push(tool.to_string());
+            
+            let content = self.serialize_for_write(&distribution)?;
+            self.write_content(&content)?;
+            Ok(true)
+        }
+        
+        // Remove a tool from a profile. Returns false if the profile doesn't list it
+        pub fn remove_profile_tool(&self, profile: &str, tool: &str) -> Result<bool> {
+            let mut distribution = self.read_without_includes()?;
+            let tools = match distribution.profiles.as_mut().and_then(|profiles| profiles.get_mut(profile)) {
+                Some(tools) => tools,
+                None => return Err(DotfilesError::InvalidCommand(format!("Profile '{}' not found", profile)).into()),
+            };
+            
+            let before = tools.len();
+            tools.retain(|name| name != tool);
+            if tools.len() == before {
+                return Ok(false);
+            }
+            
+            let content = self.serialize_for_write(&distribution)?;
+            self.write_content(&content)?;
+            Ok(true)
+        }
+        
+        pub fn get_tools(&self) -> Result<Vec<String>> {
+            let distribution = self.read_distribution()?;
+            Ok(distribution.sections.keys().cloned().collect())
+        }
+        
+        // Tools ordered by priority; tools with the same priority keep their file order
+        pub fn get_tools_by_priority(&self) -> Result<Vec<String>> {
+            let distribution = self.read_distribution()?;
+            let mut sections: Vec<(&String, &Section)> = distribution.sections.iter().collect();
+            sections.sort_by_key(|(_, section)| section.priority);
+            Ok(sections.into_iter().map(|(tool, _)| tool.clone()).collect())
+        }
+        
+        // Local file names for a tool, with renames resolved to their destination
+        pub fn get_files(&self, tool: &str) -> Result<Vec<String>> {
+            Ok(self.get_file_entries(tool)?.iter()
+                .map(|entry| entry.destination().to_string())
+                .collect())
+        }
+        
+        pub fn get_file_entries(&self, tool: &str) -> Result<Vec<FileEntry>> {
+            let distribution = self.read_distribution()?;
+            
+            match distribution.sections.get(tool) {
+                Some(section_data) => section_data.files.iter().map(|entry| self.expand_entry(entry)).collect(),
+                None => Ok(Vec::new()),
+            }
+        }
+        
+        fn expand_entry(&self, entry: &FileEntry) -> Result<FileEntry> {
+            let Some(env) = &self.env else {
+                return Ok(entry.clone());
+            };
+            Ok(match entry {
+                FileEntry::Simple(file) => FileEntry::Simple(env.expand(file)?),
+                FileEntry::Rename { from, to } => FileEntry::Rename { from: env.expand(from)?, to: env.expand(to)? },
+            })
+        }
+        
+        // Sections that aren't listed count as enabled. Sections limited to
+        // other operating systems or machines count as disabled
+        pub fn is_enabled(&self, tool: &str) -> Result<bool> {
+            let distribution = self.read_distribution()?;
+            Ok(distribution.sections.get(tool).is_none_or(|section| section.enabled && section.applies_here()))
+        }
+        
+        pub fn remove_sections(&self, tools: &[String]) -> Result<()> {
+            let mut distribution = self.read_without_includes()?;
+            
+            for tool in tools {
+                distribution.sections.shift_remove(tool);
+            }
+            
+            let content = Self::serialize_as(&distribution, self.format)?;
+            self.write_content(&content)
+        }
+        
+        pub fn set_enabled(&self, tool: &str, enabled: bool) -> Result<()> {
+            let mut distribution = self.read_without_includes()?;
+            
+            if !distribution.sections.contains_key(tool) {
+                return Err(DotfilesError::tool_not_found(tool, distribution.sections.keys()).into());
+            }
+            distribution.sections[tool].enabled = enabled;
+            
+            let content = Self::serialize_as(&distribution, self.format)?;
+            self.write_content(&content)
+        }
+        
+        // A section with no files and default settings
+        fn new_section(metadata: &SectionMetadata) -> Section {
+            Section {
+                description: metadata.description.clone(),
+                url: metadata.url.clone(),
+                files: Vec::new(),
+                enabled: true,
+                priority: DEFAULT_PRIORITY,
+                encrypted: false,
+                gpg_key_id: None,
+                template: false,
+                tags: metadata.tags.clone(),
+                os: Vec::new(),
+                machines: Vec::new(),
+                source_dir: None,
+                target_dir: None,
+                dir_type: DirType::Config,
+                post_sync: None,
+            }
+        }
+        
+        // Add an empty section for a new tool
+        pub fn add_section(&self, tool: &str, metadata: &SectionMetadata) -> Result<()> {
+            let mut distribution = self.read_without_includes()?;
+            if distribution.sections.contains_key(tool) {
+                return Err(DotfilesError::InvalidCommand(format!("Tool '{}' already exists", tool)).into());
+            }
+            distribution.sections.insert(tool.to_string(), Self::new_section(metadata));
+            
+            let content = self.serialize_for_write(&distribution)?;
+            self.write_content(&content)
+        }
+        
+        // The parser of the first included file with a [tool] section that
+        // lists `file`, or that exists at all when `file` is None, so edits go
+        // to the file that defines the tool
+        fn included_owner(&self, distribution: &Distribution, tool: &str, file: Option<&str>) -> Result<Option<Self>> {
+            if !matches!(self.source, DistributionSource::File(_)) {
+                return Ok(None);
+            }
+            for (path, included) in self.read_includes(distribution)? {
+                let owns = included.sections.get(tool).is_some_and(|section| {
+                    file.is_none_or(|file| section.files.iter().any(|entry| entry.destination() == file))
+                });
+                if owns {
+                    return Ok(Some(Self::new(path).with_retry_policy(self.retry_policy)));
+                }
+            }
+            Ok(None)
+        }
+        
+        // Add `file` to the section of `tool`, in the included file that
+        // defines the tool when this file doesn't
+        pub fn add_file(&self, tool: &str, file: &str, metadata: &SectionMetadata) -> Result<()> {
+            if let Ok(distribution) = self.read_without_includes() {
+                if !distribution.sections.contains_key(tool) {
+                    if let Some(owner) = self.included_owner(&distribution, tool, None)? {
+                        return owner.add_file(tool, file, metadata);
+                    }
+                }
+            }
+            
+            // Edit TOML files as text so comments, blank lines and the order of
+            // sections are kept
+            if let (DistributionFormat::Toml, DistributionSource::File(path)) = (self.format, &self.source) {
+                let content = match fs::read_to_string(path) {
+                    Ok(content) => Some(content),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Some(String::new()),
+                    Err(_) => None,
+                };
+                if let Some(content) = content {
+                    if let Ok(mut distribution) = self.parse(&content) {
+                        let section = distribution.sections.entry(tool.to_string())
+                            .or_insert_with(|| Self::new_section(metadata));
+                        if section.files.iter().any(|entry| entry.destination() == file) {
+                            return Ok(());
+                        }
+                        section.files.push(FileEntry::Simple(file.to_string()));
+                        
+                        if let Some(patched) = self.patch_add_file(&content, tool, file, metadata) {
+                            if self.same_distribution(&patched, &distribution) {
+                                return self.write_content(&patched);
+                            }
+                        }
+                    }
+                }
+            }
+            
+            let mut distribution = self.read_without_includes().unwrap_or_else(|_| Distribution {
+                settings: None,
+                includes: None,
+                build: None,
+                profiles: None,
+                sections: IndexMap::new(),
+            });
+            
+            // Create tool section if it doesn't exist
+            let section_entry = distribution.sections.entry(tool.to_string())
+                .or_insert_with(|| Self::new_section(metadata));
+            
+            // Add file if it doesn't already exist
+            if !section_entry.files.iter().any(|entry| entry.destination() == file) {
+                section_entry.files.push(FileEntry::Simple(file.to_string()));
+            }
+            
+            // Clean up any duplicates left over from older edits
+            Self::remove_duplicates(&mut distribution, false);
+            
+            // Write back to file
+            let content = self.serialize_for_write(&distribution)?;
+            self.write_content(&content)
+        }
+        
+        // Insert `file` into the files array of `tool` in the raw TOML, appending
+        // a new section when there is none. None when the section is written in
+        // a way a line-based edit can't handle, e.g. with dotted keys
+        fn patch_add_file(&self, content: &str, tool: &str, file: &str, metadata: &SectionMetadata) -> Option<String> {
+            let entry = toml::Value::String(file.to_string()).to_string();
+            let mut patched = content.to_string();
+            
+            let Some(body) = Self::find_section(content, tool) else {
+                let mut sections = IndexMap::new();
+                let mut section = Self::new_section(metadata);
+                section.files.push(FileEntry::Simple(file.to_string()));
+                sections.insert(tool.to_string(), section);
+                let new_section = self.write_pretty(&Distribution { settings: None, includes: None, build: None, profiles: None, sections }).ok()?;
+                
+                if !patched.is_empty() && !patched.ends_with('\n') {
+                    patched.push('\n');
+                }
+                if !patched.trim().is_empty() {
+                    patched.push('\n');
+                }
+                patched.push_str(&new_section);
+                return Some(patched);
+            };
+            
+            let Some(array) = Self::find_files_array(content, body.clone()) else {
+                // A section without a files key gets one after its last line
+                let end = Self::last_line_end(content, body.clone());
+                let line = format!("files = [{}]
+", entry);
+                if end == content.len() && !content.ends_with('\n') {
+                    patched.push('\n');
+                    patched.push_str(&line);
+                } else {
+                    patched.insert_str(end, &line);
+                }
+                return Some(patched);
+            };
+            
+            let close_line = content[..array.close].rfind('\n').map_or(0, |i| i + 1);
+            let last_value_end = array.elements.last().map(|&(_, end)| end);
+            let multi_line = close_line > array.open && content[close_line..array.close].trim().is_empty();
+            if multi_line {
+                let indent = match last_value_end {
+                    Some(end) => {
+                        let line = content[..end].rfind('\n').map_or(0, |i| i + 1);
+                        content[line..].chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+                    },
+                    None => format!("{}  ", &content[close_line..array.close]),
+                };
+                patched.insert_str(close_line, &format!("{}{},\n", indent, entry));
+                if let Some(end) = last_value_end.filter(|_| !array.trailing_comma) {
+                    patched.insert(end, ',');
+                }
+            } else {
+                match last_value_end {
+                    Some(end) => patched.insert_str(end, &format!(", {}", entry)),
+                    None => patched.insert_str(array.close, &entry),
+                }
+            }
+            
+            Some(patched)
+        }
+        
+        // Whether `content` parses to the same distribution as `expected`
+        fn same_distribution(&self, content: &str, expected: &Distribution) -> bool {
+            match self.parse(content) {
+                Ok(parsed) => toml::to_string(&parsed).ok() == toml::to_string(expected).ok(),
+                Err(_) => false,
+            }
+        }
+        
+        // Byte range of the lines between the `[tool]` header and the next header
+        fn find_section(content: &str, tool: &str) -> Option<std::ops::Range<usize>> {
+            let key = Self::toml_key(tool);
+            let mut start = None;
+            let mut offset = 0;
+            
+            for line in content.split_inclusive('\n') {
+                let trimmed = line.trim_start();
+                if let Some(header) = trimmed.strip_prefix('[') {
+                    if let Some(start) = start {
+                        return Some(start..offset);
+                    }
+                    let name = header.split(']').next().unwrap_or_default().trim();
+                    if !header.starts_with('[') && (name == key || name == tool) {
+                        start = Some(offset + line.len());
+                    }
+                }
+                offset += line.len();
+            }
+            
+            start.map(|start| start..content.len())
+        }
+        
+        // End of the last line in `body` holding a key, or its start if it has none
+        fn last_line_end(content: &str, body: std::ops::Range<usize>) -> usize {
+            let mut end = body.start;
+            let mut offset = body.start;
+            for line in content[body].split_inclusive('\n') {
+                offset += line.len();
+                let trimmed = line.trim();
+                if !trimmed.is_empty() && !trimmed.starts_with('#') {
+                    end = offset;
+                }
+            }
+            end
+        }
+        
+        // Locate the `files = [...]` array in a section body, skipping over
+        // strings and comments that may contain brackets
+        fn find_files_array(content: &str, body: std::ops::Range<usize>) -> Option<FilesArray> {
+            let mut offset = body.start;
+            let mut open = None;
+            for line in content[body.clone()].split_inclusive('\n') {
+                let trimmed = line.trim_start();
+                if let Some(rest) = trimmed.strip_prefix("files") {
+                    if let Some(value) = rest.trim_start().strip_prefix('=') {
+                        let value = value.trim_start();
+                        if value.starts_with('[') {
+                            open = Some(offset + line.len() - value.len());
+                        }
+                        break;
+                    }
+                }
+                offset += line.len();
+            }
+            let open = open?;
+            
+            let mut depth = 0;
+            let mut quote: Option<char> = None;
+            let mut escaped = false;
+            let mut comment = false;
+            let mut elements = Vec::new();
+            let mut element_start = None;
+            let mut trailing_comma = false;
+            for (i, c) in content[open..body.end].char_indices() {
+                let i = open + i;
+                if comment {
+                    comment = c != '\n';
+                    continue;
+                }
+                if let Some(q) = quote {
+                    if escaped {
+                        escaped = false;
+                    } else if c == '\\' && q == '"' {
+                        escaped = true;
+                    } else if c == q {
+                        quote = None;
+                        if depth == 1 {
+                            elements.extend(element_start.take().map(|start| (start, i + 1)));
+                            trailing_comma = false;
+                        }
+                    }
+                    continue;
+                }
+                if depth == 1 && matches!(c, '"' | '\'' | '[' | '{') {
+                    element_start = Some(i);
+                }
+                match c {
+                    '#' => comment = true,
+                    '"' | '\'' => quote = Some(c),
+                    '[' | '{' => depth += 1,
+                    ']' | '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(FilesArray { open, close: i, elements, trailing_comma });
+                        }
+                        if depth == 1 {
+                            elements.extend(element_start.take().map(|start| (start, i + 1)));
+                            trailing_comma = false;
+                        }
+                    },
+                    ',' if depth == 1 => trailing_comma = true,
+                    _ => {},
+                }
+            }
+            
+            None
+        }
+        
+        // Remove the first entry for `file` from the section of `tool`, editing
+        // the included file that lists it when this file doesn't. Returns how
+        // many more entries for it are left, which only happens when the file
+        // is listed more than once
+        pub fn remove_file(&self, tool: &str, file: &str) -> Result<usize> {
+            let mut distribution = self.read_without_includes()?;
+            
+            let listed = distribution.sections.get(tool)
+                .is_some_and(|section| section.files.iter().any(|entry| entry.destination() == file));
+            if !listed {
+                let owner = match self.included_owner(&distribution, tool, Some(file))? {
+                    Some(owner) => Some(owner),
+                    None if !distribution.sections.contains_key(tool) => self.included_owner(&distribution, tool, None)?,
+                    None => None,
+                };
+                if let Some(owner) = owner {
+                    return owner.remove_file(tool, file);
+                }
+            }
+            
+            // Check if tool section exists
+            if !distribution.sections.contains_key(tool) {
+                return Err(DotfilesError::tool_not_found(tool, distribution.sections.keys()).into());
+            }
+            let section_data = &mut distribution.sections[tool];
+            let Some(index) = section_data.files.iter().position(|entry| entry.destination() == file) else {
+                return Ok(0);
+            };
+            section_data.files.remove(index);
+            let remaining = section_data.files.iter().filter(|entry| entry.destination() == file).count();
+            
+            // Edit TOML files as text so comments, blank lines and the order of
+            // sections are kept
+            if let (DistributionFormat::Toml, DistributionSource::File(path)) = (self.format, &self.source) {
+                let content = fs::read_to_string(path).context("Failed to read distribution file")?;
+                if let Some(patched) = Self::patch_remove_file(&content, tool, file) {
+                    if self.same_distribution(&patched, &distribution) {
+                        self.write_content(&patched)?;
+                        return Ok(remaining);
+                    }
+                }
+            }
             
-            Ok(Self {
-                repo_dir,
-                config_dir,
-                distribution_file,
-                dotignore_file,
-            })
+            // Write back to file
+            let content = self.serialize_for_write(&distribution)?;
+            self.write_content(&content)?;
+            Ok(remaining)
         }
         
-        pub fn repo_config_dir(&self, section: &str) -> PathBuf {
-            self.repo_dir.join("config").join(section)
+        // Delete the first entry for `file` from the files array of `tool` in the
+        // raw TOML. An entry on a line of its own is removed with its line;
+        // otherwise it is cut out together with the comma that separates it
+        fn patch_remove_file(content: &str, tool: &str, file: &str) -> Option<String> {
+            let array = Self::find_files_array(content, Self::find_section(content, tool)?)?;
+            let index = array.elements.iter().position(|&(start, end)| {
+                let entry: Option<toml::Table> = toml::from_str(&format!("entry = {}", &content[start..end])).ok();
+                entry.and_then(|entry| entry.get("entry").cloned())
+                    .and_then(|entry| entry.try_into::<FileEntry>().ok())
+                    .is_some_and(|entry| entry.destination() == file)
+            })?;
+            let (start, end) = array.elements[index];
+            
+            let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = content[end..].find('\n').map_or(content.len(), |i| end + i + 1);
+            let rest = content[end..line_end].trim_start();
+            let rest = rest.strip_prefix(',').unwrap_or(rest).trim();
+            let own_line = line_start > array.open && line_end <= array.close
+                && content[line_start..start].trim().is_empty()
+                && (rest.is_empty() || rest.starts_with('#'));
+            
+            let removed = if own_line {
+                line_start..line_end
+            } else if let Some(&(next_start, _)) = array.elements.get(index + 1) {
+                start..next_start
+            } else if let Some(&(_, previous_end)) = index.checked_sub(1).and_then(|i| array.elements.get(i)) {
+                previous_end..end
+            } else {
+                let after = content[end..array.close].trim_start();
+                match after.strip_prefix(',') {
+                    Some(after) => start..array.close - after.len(),
+                    None => start..end,
+                }
+            };
+            
+            let mut patched = content.to_string();
+            patched.replace_range(removed, "");
+            Some(patched)
         }
         
-        pub fn config_section_dir(&self, section: &str) -> PathBuf {
-            self.config_dir.join(section)
+        // Move a file entry to another tool, creating its section if needed, in a
+        // single write of the distribution file
+        pub fn move_file(&self, from_tool: &str, file: &str, to_tool: &str, new_file: &str) -> Result<()> {
+            let mut distribution = self.read_without_includes()?;
+            
+            let tracked = distribution.sections.get(from_tool)
+                .is_some_and(|section| section.files.iter().any(|entry| entry.destination() == file));
+            if !tracked {
+                return Err(DotfilesError::InvalidCommand(format!("{}/{} is not tracked", from_tool, file)).into());
+            }
+            let taken = distribution.sections.get(to_tool)
+                .is_some_and(|section| section.files.iter().any(|entry| entry.destination() == new_file));
+            if taken {
+                return Err(DotfilesError::InvalidCommand(format!("{}/{} is already tracked", to_tool, new_file)).into());
+            }
+            
+            if let Some(section) = distribution.sections.get_mut(from_tool) {
+                section.files.retain(|entry| entry.destination() != file);
+            }
+            distribution.sections.entry(to_tool.to_string())
+                .or_insert_with(|| Self::new_section(&SectionMetadata::default()))
+                .files.push(FileEntry::Simple(new_file.to_string()));
+            
+            let content = self.serialize_for_write(&distribution)?;
+            self.write_content(&content)
         }
         
-        pub fn repo_file_path(&self, section: &str, file: &str) -> PathBuf {
-            self.repo_config_dir(section).join(file)
+        // Remove repeated file entries within each section, keeping the first
+        // occurrence, and return how many were removed
+        pub fn deduplicate(&self, case_insensitive: bool) -> Result<usize> {
+            let mut distribution = self.read_without_includes()?;
+            let removed = Self::remove_duplicates(&mut distribution, case_insensitive);
+            
+            if removed > 0 {
+                let content = Self::serialize_as(&distribution, self.format)?;
+                self.write_content(&content)?;
+            }
+            
+            Ok(removed)
         }
         
-        pub fn config_file_path(&self, section: &str, file: &str) -> PathBuf {
-            self.config_section_dir(section).join(file)
-        }
-    }
-}
-
-mod distribution {
-    use anyhow::{Context, Result};
-    use serde::{Deserialize, Serialize};
-    use std::collections::HashMap;
-    use std::fs;
-    use std::path::PathBuf;
-    use crate::DotfilesError;
-    use crate::DotfilesArchive;
-
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct Distribution {
-        #[serde(flatten)]
-        pub sections: HashMap<String, Section>,
-    }
-    
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct Section {
-        #[serde(default)]
-        pub files: Vec<String>,
-    }
-    
-    pub enum DistributionSource {
-        File(PathBuf),
-        Embedded,
-    }
-    
-    pub struct DistributionParser {
-        pub source: DistributionSource,
-    }
-    
-    impl DistributionParser {
-        pub fn new(path: PathBuf) -> Self {
-            Self { source: DistributionSource::File(path) }
+        // Find (tool, file) entries that only differ by case from an earlier entry
+        pub fn case_insensitive_duplicates(&self) -> Result<Vec<(String, String)>> {
+            let distribution = self.read_without_includes()?;
+            let mut duplicates = Vec::new();
+            
+            for (tool, section) in &distribution.sections {
+                let mut seen: Vec<&str> = Vec::new();
+                for file in section.files.iter().map(FileEntry::destination) {
+                    if seen.iter().any(|f| *f != file && f.to_lowercase() == file.to_lowercase()) {
+                        duplicates.push((tool.clone(), file.to_string()));
+                    } else {
+                        seen.push(file);
+                    }
+                }
+            }
+            
+            Ok(duplicates)
         }
         
-        pub fn from_embedded() -> Self {
-            Self { source: DistributionSource::Embedded }
+        fn remove_duplicates(distribution: &mut Distribution, case_insensitive: bool) -> usize {
+            let mut removed = 0;
+            
+            for section in distribution.sections.values_mut() {
+                let mut seen = Vec::new();
+                let before = section.files.len();
+                section.files.retain(|entry| {
+                    let file = entry.destination();
+                    let key = if case_insensitive { file.to_lowercase() } else { file.to_string() };
+                    if seen.contains(&key) {
+                        false
+                    } else {
+                        seen.push(key);
+                        true
+                    }
+                });
+                removed += before - section.files.len();
+            }
+            
+            removed
         }
         
-        pub fn read_distribution(&self) -> Result<Distribution> {
-            let content = match &self.source {
-                DistributionSource::File(path) => fs::read_to_string(path)
-                    .context("Failed to read distribution file")?,
-                DistributionSource::Embedded => DotfilesArchive::get_distribution()?,
-            };
+        // Combine this distribution with another one. Sections found in both get
+        // their files concatenated without duplicates; for everything else
+        // (description, url, enabled, os, machines, settings) this distribution's
+        // values win
+        pub fn merge(&self, other: &DistributionParser) -> Result<Distribution> {
+            let mut merged = self.read_without_includes()?;
+            let other = other.read_distribution()?;
             
-            let distribution: Distribution = toml::from_str(&content)
-                .map_err(|e| DotfilesError::DistributionParseError(e.to_string()))?;
+            if merged.settings.is_none() {
+                merged.settings = other.settings;
+            }
             
-            Ok(distribution)
+            for (tool, other_section) in other.sections {
+                match merged.sections.get_mut(&tool) {
+                    Some(section) => {
+                        section.files.extend(other_section.files);
+                        if section.description.is_none() {
+                            section.description = other_section.description;
+                        }
+                        if section.url.is_none() {
+                            section.url = other_section.url;
+                        }
+                    },
+                    None => {
+                        merged.sections.insert(tool, other_section);
+                    },
+                }
+            }
+            
+            Self::remove_duplicates(&mut merged, false);
+            Ok(merged)
         }
         
-        pub fn get_tools(&self) -> Result<Vec<String>> {
-            let distribution = self.read_distribution()?;
-            Ok(distribution.sections.keys().cloned().collect())
+        // Replace the distribution file with the given distribution
+        pub fn write_distribution(&self, distribution: &Distribution) -> Result<()> {
+            let content = Self::serialize_as(distribution, self.format)?;
+            self.write_content(&content)
         }
         
-        pub fn get_files(&self, tool: &str) -> Result<Vec<String>> {
-            let distribution = self.read_distribution()?;
+        // Sort section names and each section's files alphabetically
+        pub fn sort(&self) -> Result<()> {
+            let mut distribution = self.read_without_includes()?;
             
-            match distribution.sections.get(tool) {
-                Some(section_data) => Ok(section_data.files.clone()),
-                None => Ok(Vec::new()),
+            distribution.sections.sort_keys();
+            for section in distribution.sections.values_mut() {
+                section.files.sort_by(|a, b| a.destination().cmp(b.destination()));
             }
+            
+            let content = Self::serialize_as(&distribution, self.format)?;
+            self.write_content(&content)
         }
         
-        pub fn add_file(&self, tool: &str, file: &str) -> Result<()> {
-            let mut distribution = self.read_distribution().unwrap_or_else(|_| Distribution {
-                sections: HashMap::new(),
-            });
+        // Whether sections and their files are already in the order `sort` produces
+        pub fn is_sorted(&self) -> Result<bool> {
+            let distribution = self.read_without_includes()?;
             
-            // Create tool section if it doesn't exist
-            let section_entry = distribution.sections.entry(tool.to_string())
-                .or_insert_with(|| Section { files: Vec::new() });
+            let tools: Vec<&String> = distribution.sections.keys().collect();
+            Ok(tools.is_sorted() && distribution.sections.values().all(|section| {
+                section.files.is_sorted_by(|a, b| a.destination() <= b.destination())
+            }))
+        }
+        
+        // Replace Windows-style '\' separators in file entries with '/' and
+        // return how many entries changed
+        pub fn normalize_separators(&self) -> Result<usize> {
+            let mut distribution = self.read_without_includes()?;
+            let mut changed = 0;
             
-            // Add file if it doesn't already exist
-            if !section_entry.files.contains(&file.to_string()) {
-                section_entry.files.push(file.to_string());
+            for section in distribution.sections.values_mut() {
+                for entry in section.files.iter_mut() {
+                    let names = match entry {
+                        FileEntry::Simple(file) => vec![file],
+                        FileEntry::Rename { from, to } => vec![from, to],
+                    };
+                    
+                    let mut entry_changed = false;
+                    for name in names {
+                        if name.contains('\\') {
+                            *name = name.replace('\\', "/");
+                            entry_changed = true;
+                        }
+                    }
+                    if entry_changed {
+                        changed += 1;
+                    }
+                }
             }
             
-            // Write back to file
-            let toml_content = toml::to_string(&distribution)
-                .map_err(|e| DotfilesError::DistributionParseError(format!("Failed to serialize: {}", e)))?;
+            if changed > 0 {
+                let content = Self::serialize_as(&distribution, self.format)?;
+                self.write_content(&content)?;
+            }
             
-            match &self.source {
-                DistributionSource::File(path) => fs::write(path, toml_content)?,
+            Ok(changed)
+        }
+        
+        // Write through a temporary file and rename it so the file is never left half-written
+        fn write_content(&self, content: &str) -> Result<()> {
+            let path = match &self.source {
+                DistributionSource::File(path) => path,
                 DistributionSource::Embedded => return Err(DotfilesError::InvalidCommand(
                     "Cannot modify distribution file in embedded mode".to_string()).into()),
-            }
+                DistributionSource::Stdin(_) => return Err(DotfilesError::InvalidCommand(
+                    "Cannot modify a distribution read from stdin".to_string()).into()),
+            };
+            
+            let tmp_path = path.with_extension("toml.tmp");
+            util::retry(self.retry_policy.max_attempts, self.retry_policy.delay, || {
+                fs::write(&tmp_path, content)?;
+                Ok(())
+            })?;
+            fs::rename(&tmp_path, path)?;
             
             Ok(())
         }
+    }
+    
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use tempfile::TempDir;
+        
+        // A repository directory holding distribution.toml with `content`
+        fn distribution_file(content: &str) -> (TempDir, DistributionParser) {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("distribution.toml");
+            fs::write(&path, content).unwrap();
+            (dir, DistributionParser::new(path))
+        }
+        
+        fn read(dir: &TempDir, name: &str) -> String {
+            fs::read_to_string(dir.path().join(name)).unwrap()
+        }
+        
+        const MAIN_WITH_INCLUDE: &str = "[_includes]\nfiles = [\"extra.toml\"]\n\n[zsh]\nfiles = [\".zshrc\"]\n";
         
-        pub fn remove_file(&self, tool: &str, file: &str) -> Result<()> {
-            let mut distribution = self.read_distribution()?;
+        #[test]
+        fn add_file_edits_the_included_file_defining_the_tool() {
+            let (dir, parser) = distribution_file(MAIN_WITH_INCLUDE);
+            fs::write(dir.path().join("extra.toml"), "[git]\nfiles = [\"config\"]\n").unwrap();
             
-            // Check if tool section exists
-            if let Some(section_data) = distribution.sections.get_mut(tool) {
-                // Remove file if it exists
-                section_data.files.retain(|f| f != file);
-                
-                // Write back to file
-                let toml_content = toml::to_string(&distribution)
-                    .map_err(|e| DotfilesError::DistributionParseError(format!("Failed to serialize: {}", e)))?;
-                
-                match &self.source {
-                    DistributionSource::File(path) => fs::write(path, toml_content)?,
-                    DistributionSource::Embedded => return Err(DotfilesError::InvalidCommand(
-                        "Cannot modify distribution file in embedded mode".to_string()).into()),
-                }
-                
-                Ok(())
-            } else {
-                Err(DotfilesError::InvalidCommand(format!("Tool '{}' not found", tool)).into())
-            }
+            parser.add_file("git", "ignore", &SectionMetadata::default()).unwrap();
+            
+            assert_eq!(read(&dir, "distribution.toml"), MAIN_WITH_INCLUDE);
+            assert_eq!(read(&dir, "extra.toml"), "[git]\nfiles = [\"config\", \"ignore\"]\n");
+        }
+        
+        #[test]
+        fn add_file_creates_new_tools_in_the_main_file() {
+            let (dir, parser) = distribution_file(MAIN_WITH_INCLUDE);
+            fs::write(dir.path().join("extra.toml"), "[git]\nfiles = [\"config\"]\n").unwrap();
+            
+            parser.add_file("nvim", "init.lua", &SectionMetadata::default()).unwrap();
+            
+            assert!(read(&dir, "distribution.toml").contains("[nvim]"));
+            assert!(!read(&dir, "extra.toml").contains("nvim"));
+        }
+        
+        #[test]
+        fn remove_file_edits_the_included_file_listing_it() {
+            let (dir, parser) = distribution_file(MAIN_WITH_INCLUDE);
+            fs::write(dir.path().join("extra.toml"), "[git]\nfiles = [\"config\", \"ignore\"]\n").unwrap();
+            
+            assert_eq!(parser.remove_file("git", "config").unwrap(), 0);
+            
+            assert_eq!(read(&dir, "distribution.toml"), MAIN_WITH_INCLUDE);
+            assert_eq!(read(&dir, "extra.toml"), "[git]\nfiles = [\"ignore\"]\n");
+            assert!(parser.remove_file("hg", "config").is_err());
+        }
+        
+        // Add a file by patching `content`, checking that the result parses to
+        // the same distribution as adding it to the parsed content would
+        fn patch_add(content: &str, tool: &str, file: &str) -> String {
+            let parser = DistributionParser::new(PathBuf::from("distribution.toml"));
+            let patched = parser.patch_add_file(content, tool, file, &SectionMetadata::default()).unwrap();
+            
+            let mut expected = parser.parse(content).unwrap();
+            expected.sections.entry(tool.to_string())
+                .or_insert_with(|| DistributionParser::new_section(&SectionMetadata::default()))
+                .files.push(FileEntry::Simple(file.to_string()));
+            assert!(parser.same_distribution(&patched, &expected), "unexpected result:\n{}", patched);
+            patched
+        }
+        
+        #[test]
+        fn patch_add_file_appends_to_inline_array() {
+            let content = "# Shell\n[zsh]\nfiles = [\"a\", \"b\"] # keep\n\n[git]\nfiles = [\"config\"]\n";
+            assert_eq!(patch_add(content, "zsh", "c"),
+                "# Shell\n[zsh]\nfiles = [\"a\", \"b\", \"c\"] # keep\n\n[git]\nfiles = [\"config\"]\n");
+        }
+        
+        #[test]
+        fn patch_add_file_appends_to_multi_line_array_without_trailing_comma() {
+            let content = "[zsh]\nfiles = [\n    \"a\", # first\n    \"b\"\n]\n";
+            assert_eq!(patch_add(content, "zsh", "c"),
+                "[zsh]\nfiles = [\n    \"a\", # first\n    \"b\",\n    \"c\",\n]\n");
+        }
+        
+        #[test]
+        fn patch_add_file_adds_files_key_to_section_without_one() {
+            let content = "[zsh]\ndescription = \"Z shell\"\n# no files yet\n\n[git]\nfiles = [\"config\"]\n";
+            assert_eq!(patch_add(content, "zsh", "c"),
+                "[zsh]\ndescription = \"Z shell\"\nfiles = [\"c\"]\n# no files yet\n\n[git]\nfiles = [\"config\"]\n");
+        }
+        
+        #[test]
+        fn patch_add_file_appends_new_section_after_existing_content() {
+            let content = "# My dotfiles\n[zsh]\nfiles = [\"a\"] # shell\n";
+            let patched = patch_add(content, "nvim", "init.lua");
+            assert!(patched.starts_with(content));
+            assert!(patched[content.len()..].starts_with("\n[nvim]\n"));
+        }
+        
+        #[test]
+        fn patch_add_file_skips_brackets_in_strings_and_comments() {
+            let content = "[zsh]\nfiles = [\n    \"a]b\", # [not] the end]\n    'c[d',\n    { from = \"x\", to = \"y]\" },\n]\n";
+            assert_eq!(patch_add(content, "zsh", "e"),
+                "[zsh]\nfiles = [\n    \"a]b\", # [not] the end]\n    'c[d',\n    { from = \"x\", to = \"y]\" },\n    \"e\",\n]\n");
+        }
+        
+        #[test]
+        fn merge_combines_overlapping_and_separate_sections() {
+            let (_dir, primary) = distribution_file("[zsh]\nfiles = [\".zshrc\", \".zshenv\"]\nos = [\"linux\"]\n\n[git]\nfiles = [\"config\"]\n");
+            let (_other_dir, other) = distribution_file("[nvim]\nfiles = [\"init.lua\"]\n\n[zsh]\ndescription = \"Z shell\"\nfiles = [\".zshenv\", \".zprofile\"]\nenabled = false\nos = [\"macos\"]\nmachines = [\"work\"]\n");
+            let merged = primary.merge(&other).unwrap();
+            
+            assert_eq!(merged.sections.keys().collect::<Vec<_>>(), ["zsh", "git", "nvim"]);
+            let zsh = &merged.sections["zsh"];
+            let files: Vec<&str> = zsh.files.iter().map(FileEntry::destination).collect();
+            assert_eq!(files, [".zshrc", ".zshenv", ".zprofile"]);
+            assert_eq!(zsh.description.as_deref(), Some("Z shell"));
+            assert!(zsh.enabled);
+            assert_eq!(zsh.os, ["linux"]);
+            assert!(zsh.machines.is_empty());
+            assert_eq!(merged.sections["git"].files, [FileEntry::Simple("config".to_string())]);
+            assert_eq!(merged.sections["nvim"].files, [FileEntry::Simple("init.lua".to_string())]);
+        }
+        
+        const DUPLICATES: &str = "[zsh]\nfiles = [\".zshrc\", \".zshenv\", \".zshrc\", \".ZSHRC\"]\n\n[git]\nfiles = [\"config\", { from = \"work\", to = \"config\" }, \"ignore\"]\n";
+        
+        #[test]
+        fn deduplicate_keeps_first_occurrences() {
+            let (dir, parser) = distribution_file(DUPLICATES);
+            assert_eq!(parser.case_insensitive_duplicates().unwrap(), [("zsh".to_string(), ".ZSHRC".to_string())]);
+            assert_eq!(parser.deduplicate(false).unwrap(), 2);
+            
+            let distribution = parser.parse(&read(&dir, "distribution.toml")).unwrap();
+            let zsh: Vec<&str> = distribution.sections["zsh"].files.iter().map(FileEntry::destination).collect();
+            assert_eq!(zsh, [".zshrc", ".zshenv", ".ZSHRC"]);
+            assert_eq!(distribution.sections["git"].files,
+                [FileEntry::Simple("config".to_string()), FileEntry::Simple("ignore".to_string())]);
+            assert_eq!(parser.deduplicate(false).unwrap(), 0);
+        }
+        
+        #[test]
+        fn deduplicate_case_insensitive_removes_case_variants() {
+            let (_dir, parser) = distribution_file(DUPLICATES);
+            assert_eq!(parser.deduplicate(true).unwrap(), 3);
+            assert!(parser.case_insensitive_duplicates().unwrap().is_empty());
+        }
+        
+        #[test]
+        fn writing_back_keeps_section_order() {
+            let (dir, parser) = distribution_file("[zsh]\nfiles = [\".zshrc\"]\n\n[alacritty]\nfiles = []\n\n[git]\nfiles = [\"config\"]\n");
+            let mut distribution = parser.read_distribution().unwrap();
+            distribution.sections.insert("bat".to_string(), DistributionParser::new_section(&SectionMetadata::default()));
+            parser.write_distribution(&distribution).unwrap();
+            
+            let written = parser.parse(&read(&dir, "distribution.toml")).unwrap();
+            assert_eq!(written.sections.keys().collect::<Vec<_>>(), ["zsh", "alacritty", "git", "bat"]);
+            
+            parser.add_file("nvim", "init.lua", &SectionMetadata::default()).unwrap();
+            let written = parser.read_distribution().unwrap();
+            assert_eq!(written.sections.keys().collect::<Vec<_>>(), ["zsh", "alacritty", "git", "bat", "nvim"]);
+        }
+        
+        #[test]
+        fn sort_orders_sections_and_files() {
+            let (dir, parser) = distribution_file("[zsh]\nfiles = [\".zshrc\"]\n\n[git]\nfiles = [\"config\"]\n");
+            parser.add_file("zsh", ".zprofile", &SectionMetadata::default()).unwrap();
+            parser.add_file("alacritty", "alacritty.toml", &SectionMetadata::default()).unwrap();
+            parser.add_file("zsh", ".zlogin", &SectionMetadata::default()).unwrap();
+            assert!(!parser.is_sorted().unwrap());
+            
+            parser.sort().unwrap();
+            assert!(parser.is_sorted().unwrap());
+            let sorted = read(&dir, "distribution.toml");
+            let distribution = parser.parse(&sorted).unwrap();
+            assert_eq!(distribution.sections.keys().collect::<Vec<_>>(), ["alacritty", "git", "zsh"]);
+            let files: Vec<&str> = distribution.sections["zsh"].files.iter().map(FileEntry::destination).collect();
+            assert_eq!(files, [".zlogin", ".zprofile", ".zshrc"]);
+            assert!(sorted.find("[alacritty]").unwrap() < sorted.find("[git]").unwrap());
+            assert!(sorted.find("[git]").unwrap() < sorted.find("[zsh]").unwrap());
+        }
+        
+        const ALL_OPTIONAL_FIELDS: &str = "[zsh]\ndescription = \"Z shell\"\nurl = \"https://zsh.org\"\nfiles = [\".zshrc\", { from = \"env\", to = \".zshenv\" }]\nenabled = false\nos = [\"linux\", \"macos\"]\nmachines = [\"work\"]\n\n[git]\nfiles = [\"config\"]\n";
+        
+        #[test]
+        fn toml_to_json_to_toml_keeps_optional_fields() {
+            let distribution: Distribution = toml::from_str(ALL_OPTIONAL_FIELDS).unwrap();
+            let json = DistributionParser::serialize_as(&distribution, DistributionFormat::Json).unwrap();
+            let restored = DistributionParser::from_json(&json).unwrap();
+            
+            let zsh = &restored.sections["zsh"];
+            assert_eq!(zsh.description.as_deref(), Some("Z shell"));
+            assert_eq!(zsh.url.as_deref(), Some("https://zsh.org"));
+            assert!(!zsh.enabled);
+            assert_eq!(zsh.os, ["linux", "macos"]);
+            assert_eq!(zsh.machines, ["work"]);
+            assert!(restored.sections["git"].enabled);
+            assert_eq!(DistributionParser::serialize_as(&restored, DistributionFormat::Toml).unwrap(),
+                DistributionParser::serialize_as(&distribution, DistributionFormat::Toml).unwrap());
+        }
+        
+        #[test]
+        fn json_to_toml_to_json_keeps_optional_fields() {
+            let distribution: Distribution = toml::from_str(ALL_OPTIONAL_FIELDS).unwrap();
+            let json = DistributionParser::serialize_as(&distribution, DistributionFormat::Json).unwrap();
+            
+            let from_json = DistributionParser::from_json(&json).unwrap();
+            let toml = DistributionParser::serialize_as(&from_json, DistributionFormat::Toml).unwrap();
+            let restored: Distribution = toml::from_str(&toml).unwrap();
+            assert_eq!(DistributionParser::serialize_as(&restored, DistributionFormat::Json).unwrap(), json);
+            
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(value["zsh"]["enabled"], false);
+            assert_eq!(value["zsh"]["os"], serde_json::json!(["linux", "macos"]));
+            assert!(value["git"].get("enabled").is_none());
+        }
+        
+        #[test]
+        fn sections_for_other_systems_count_as_disabled() {
+            let content = format!("[zsh]\nfiles = []\nos = [\"{}\"]\n\n[karabiner]\nfiles = []\nos = [\"plan9\"]\n\n[git]\nfiles = []\nmachines = [\"no-such-host.invalid\"]\n", std::env::consts::OS);
+            let (_dir, parser) = distribution_file(&content);
+            assert!(parser.is_enabled("zsh").unwrap());
+            assert!(!parser.is_enabled("karabiner").unwrap());
+            assert!(!parser.is_enabled("git").unwrap());
+        }
+        
+        fn patch_remove(content: &str, tool: &str, file: &str) -> String {
+            let parser = DistributionParser::new(PathBuf::from("distribution.toml"));
+            let patched = DistributionParser::patch_remove_file(content, tool, file).unwrap();
+            
+            let mut expected = parser.parse(content).unwrap();
+            let files = &mut expected.sections[tool].files;
+            let index = files.iter().position(|entry| entry.destination() == file).unwrap();
+            files.remove(index);
+            assert!(parser.same_distribution(&patched, &expected), "unexpected result:\n{}", patched);
+            patched
+        }
+        
+        #[test]
+        fn patch_remove_file_from_inline_array() {
+            let content = "# Shell\n[zsh]\nfiles = [\"a\", \"b\", \"c\"] # keep\n\n[git]\nfiles = [\"config\"]\n";
+            assert_eq!(patch_remove(content, "zsh", "b"),
+                "# Shell\n[zsh]\nfiles = [\"a\", \"c\"] # keep\n\n[git]\nfiles = [\"config\"]\n");
+        }
+        
+        #[test]
+        fn patch_remove_file_from_multi_line_array() {
+            let content = "[zsh]\nfiles = [\n    \"a\", # first\n    \"b\", # second\n    \"c\"\n]\n";
+            assert_eq!(patch_remove(content, "zsh", "b"),
+                "[zsh]\nfiles = [\n    \"a\", # first\n    \"c\"\n]\n");
+        }
+        
+        #[test]
+        fn patch_remove_file_matches_renamed_entry_by_destination() {
+            let content = "[zsh]\nfiles = [\n    \"a\",\n    { from = \"zshrc\", to = \".zshrc\" },\n]\n";
+            assert_eq!(patch_remove(content, "zsh", ".zshrc"), "[zsh]\nfiles = [\n    \"a\",\n]\n");
+            assert!(DistributionParser::patch_remove_file(content, "zsh", "zshrc").is_none());
+        }
+        
+        #[test]
+        fn patch_remove_file_removes_only_first_duplicate() {
+            let content = "[zsh]\nfiles = [\"a\", \"b\", \"a\"]\n";
+            assert_eq!(patch_remove(content, "zsh", "a"), "[zsh]\nfiles = [\"b\", \"a\"]\n");
+        }
+        
+        #[test]
+        fn patch_remove_file_last_element() {
+            assert_eq!(patch_remove("[zsh]\nfiles = [\"a\", \"b\"]\n", "zsh", "b"), "[zsh]\nfiles = [\"a\"]\n");
+            assert_eq!(patch_remove("[zsh]\nfiles = [\n    \"a\",\n    \"b\", # last\n]\n", "zsh", "b"),
+                "[zsh]\nfiles = [\n    \"a\",\n]\n");
+        }
+        
+        #[test]
+        fn patch_remove_file_keeps_emptied_array_and_section() {
+            let content = "# Shell\n[zsh]\nfiles = [\"a\"] # only one\n\n[git]\nfiles = [\"config\"]\n";
+            assert_eq!(patch_remove(content, "zsh", "a"),
+                "# Shell\n[zsh]\nfiles = [] # only one\n\n[git]\nfiles = [\"config\"]\n");
+            assert_eq!(patch_remove("[zsh]\nfiles = [\n    \"a\",\n]\n", "zsh", "a"), "[zsh]\nfiles = [\n]\n");
+        }
+        
+        #[test]
+        fn remove_file_keeps_comments_and_reports_remaining_duplicates() {
+            let content = "# Shell\n[zsh]\nfiles = [\n    \"a\", # keep me\n    \"b\",\n    \"a\",\n]\n\n# Editor\n[nvim]\nfiles = [\"init.lua\"]\n";
+            let (dir, parser) = distribution_file(content);
+            assert_eq!(parser.remove_file("zsh", "a").unwrap(), 1);
+            assert_eq!(read(&dir, "distribution.toml"),
+                "# Shell\n[zsh]\nfiles = [\n    \"b\",\n    \"a\",\n]\n\n# Editor\n[nvim]\nfiles = [\"init.lua\"]\n");
         }
     }
 }
@@ -170,32 +2649,70 @@ mod distribution {
 mod ignore {
     use anyhow::Result;
     use glob::Pattern;
-    use std::fs::{self, File};
+    use std::fs::{self, File, OpenOptions};
     use std::io::Write;
     use std::path::{Path, PathBuf};
     use crate::DotfilesArchive;
+    use crate::DotfilesError;
     
     pub enum DotIgnoreSource {
         File(PathBuf),
         Embedded,
     }
     
+    // A single .dotignore line; negated rules (starting with `!`) un-ignore files
+    pub struct IgnoreRule {
+        pub raw: String,
+        pub negate: bool,
+        pub pattern: Pattern,
+        // Read from the global ~/.dotignore rather than the repo's file
+        pub global: bool,
+    }
+    
     pub struct DotIgnore {
-        pub patterns: Vec<Pattern>,
+        pub patterns: Vec<IgnoreRule>,
     }
     
     impl DotIgnore {
+        pub const TOOLIGNORE_FILE: &'static str = ".toolignore";
+        
         pub fn new(path: &Path) -> Result<Self> {
             Self::from_source(DotIgnoreSource::File(path.to_path_buf()))
         }
         
+        // Global patterns first, then the repo's, so a local `!` rule can
+        // un-ignore a file the global file ignores. A missing global file
+        // adds nothing; a missing local file means the default patterns
+        pub fn from_global_and_local(global: &Path, local: &Path) -> Result<Self> {
+            let mut patterns = Vec::new();
+            if global != local && global.exists() {
+                let content = fs::read_to_string(global)?;
+                for mut rule in Self::parse_patterns(&content)? {
+                    rule.global = true;
+                    patterns.push(rule);
+                }
+            }
+            
+            patterns.extend(Self::new(local)?.patterns);
+            Ok(Self { patterns })
+        }
+        
         pub fn from_embedded() -> Result<Self> {
             Self::from_source(DotIgnoreSource::Embedded)
         }
         
-        pub fn from_source(source: DotIgnoreSource) -> Result<Self> {
-            let mut patterns = Vec::new();
+        // Load the .toolignore for a single tool; a missing file means no patterns
+        pub fn for_tool(tool_dir: &Path) -> Result<Self> {
+            let path = tool_dir.join(Self::TOOLIGNORE_FILE);
+            if !path.exists() {
+                return Ok(Self { patterns: Vec::new() });
+            }
             
+            let content = fs::read_to_string(&path)?;
+            Ok(Self { patterns: Self::parse_patterns(&content)? })
+        }
+        
+        pub fn from_source(source: DotIgnoreSource) -> Result<Self> {
             let content = match source {
                 DotIgnoreSource::File(path) => {
                     if path.exists() {
@@ -209,19 +2726,48 @@ mod ignore {
                 }
             };
             
-            for line in content.lines() {
-                let line = line.trim();
-                if !line.is_empty() && !line.starts_with('#') {
-                    patterns.push(Pattern::new(line)?);
-                }
+            Ok(Self { patterns: Self::parse_patterns(&content)? })
+        }
+        
+        // Build an instance from patterns in .dotignore syntax, without
+        // reading any file
+        pub fn from_patterns(patterns: &[&str]) -> Result<Self> {
+            let mut dotignore = Self { patterns: Vec::new() };
+            dotignore.extend_from_patterns(patterns)?;
+            Ok(dotignore)
+        }
+        
+        // Append patterns after the existing ones, so they take precedence
+        pub fn extend_from_patterns(&mut self, patterns: &[&str]) -> Result<()> {
+            for pattern in patterns {
+                self.patterns.push(Self::parse_rule(pattern.trim())?);
             }
+            Ok(())
+        }
+        
+        fn parse_patterns(content: &str) -> Result<Vec<IgnoreRule>> {
+            content.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(Self::parse_rule)
+                .collect()
+        }
+        
+        fn parse_rule(line: &str) -> Result<IgnoreRule> {
+            let (negate, glob) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let pattern = Pattern::new(glob.strip_prefix('/').unwrap_or(glob))
+                .map_err(|e| DotfilesError::InvalidCommand(format!("Invalid pattern '{}': {}", line, e)))?;
             
-            Ok(Self { patterns })
+            Ok(IgnoreRule { raw: line.to_string(), negate, pattern, global: false })
         }
         
         pub fn default_content() -> &'static str {
             r#"# Add files to ignore when syncing
 # Each line is a glob pattern matched against the basename of files
+# Lines starting with ! un-ignore files matched by an earlier pattern
 *history
 *_history
 *id_rsa*
@@ -250,17 +2796,134 @@ mod ignore {
             Ok(())
         }
         
+        // Append a pattern to the ignore file at `path` and reload the patterns from it.
+        // A missing file starts out with the default patterns
+        pub fn add_pattern_to_file(&mut self, path: &Path, pattern: &str) -> Result<()> {
+            let pattern = pattern.trim();
+            Self::parse_rule(pattern)?;
+            
+            Self::create_default(path)?;
+            let content = fs::read_to_string(path)?;
+            
+            let mut file = OpenOptions::new().append(true).open(path)?;
+            if !content.is_empty() && !content.ends_with('\n') {
+                writeln!(file)?;
+            }
+            writeln!(file, "{}", pattern)?;
+            
+            self.reload(path)
+        }
+        
+        // Remove every line that is exactly `pattern`, keeping comments and blank
+        // lines, and reload the patterns. Returns whether the pattern was found
+        pub fn remove_pattern_from_file(&mut self, path: &Path, pattern: &str) -> Result<bool> {
+            if !path.exists() {
+                return Ok(false);
+            }
+            
+            let pattern = pattern.trim();
+            let content = fs::read_to_string(path)?;
+            let kept: Vec<&str> = content.lines().filter(|line| line.trim() != pattern).collect();
+            if kept.len() == content.lines().count() {
+                return Ok(false);
+            }
+            
+            let mut new_content = kept.join("\n");
+            if !new_content.is_empty() {
+                new_content.push('\n');
+            }
+            fs::write(path, new_content)?;
+            
+            self.reload(path)?;
+            Ok(true)
+        }
+        
+        // Re-read the local patterns from `path`, keeping the global ones
+        fn reload(&mut self, path: &Path) -> Result<()> {
+            let content = fs::read_to_string(path)?;
+            self.patterns.retain(|rule| rule.global);
+            self.patterns.extend(Self::parse_patterns(&content)?);
+            Ok(())
+        }
+        
+        // A file is ignored when the last rule matching it is not a negation
         pub fn is_ignored(&self, filename: &str) -> bool {
+            self.last_match(filename).is_some_and(|rule| !rule.negate)
+        }
+        
+        // Return the final rule that matches the file, if any
+        pub fn explain(&self, filename: &str) -> Option<&str> {
+            self.last_match(filename).map(|rule| rule.raw.as_str())
+        }
+        
+        // Patterns containing a `/` match the whole path, e.g. `lua/secret.lua`
+        // or `/secret.lua` (only the top-level file) in a .toolignore; all
+        // others match the basename
+        fn last_match(&self, filename: &str) -> Option<&IgnoreRule> {
             let basename = Path::new(filename).file_name()
                 .and_then(|os_str| os_str.to_str())
                 .unwrap_or("");
-                
-            self.patterns.iter().any(|pattern| pattern.matches(basename))
+            
+            self.patterns.iter()
+                .rev()
+                .find(|rule| match rule.raw.contains('/') {
+                    true => rule.pattern.matches(filename),
+                    false => rule.pattern.matches(basename),
+                })
+        }
+    }
+    
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        
+        #[test]
+        fn explain_returns_the_matching_pattern() {
+            let dotignore = DotIgnore::from_patterns(&["*.key", "id_*", "?.bak", "cache[0-9]", "lua/*.tmp"]).unwrap();
+            assert_eq!(dotignore.explain("ssh/server.key"), Some("*.key"));
+            assert_eq!(dotignore.explain("id_ed25519"), Some("id_*"));
+            assert_eq!(dotignore.explain("a.bak"), Some("?.bak"));
+            assert_eq!(dotignore.explain("ab.bak"), None);
+            assert_eq!(dotignore.explain("cache7"), Some("cache[0-9]"));
+            assert_eq!(dotignore.explain("lua/init.tmp"), Some("lua/*.tmp"));
+            assert_eq!(dotignore.explain("init.lua"), None);
+        }
+        
+        #[test]
+        fn last_matching_rule_decides_with_negations() {
+            let dotignore = DotIgnore::from_patterns(&["*.log", "!important*.log", "important-old.log"]).unwrap();
+            assert!(dotignore.is_ignored("debug.log"));
+            assert_eq!(dotignore.explain("debug.log"), Some("*.log"));
+            assert!(!dotignore.is_ignored("important.log"));
+            assert_eq!(dotignore.explain("important.log"), Some("!important*.log"));
+            assert!(dotignore.is_ignored("important-old.log"));
+            assert_eq!(dotignore.explain("important-old.log"), Some("important-old.log"));
+            
+            let dotignore = DotIgnore::from_patterns(&["!important.log", "*.log"]).unwrap();
+            assert!(dotignore.is_ignored("important.log"));
+            assert_eq!(dotignore.explain("important.log"), Some("*.log"));
+        }
+        
+        #[test]
+        fn basename_patterns_match_in_any_directory() {
+            let dotignore = DotIgnore::from_patterns(&["*.key"]).unwrap();
+            assert!(dotignore.is_ignored("ssh/id.key"));
+            assert!(dotignore.is_ignored("id.key"));
+        }
+        
+        #[test]
+        fn patterns_with_a_slash_match_the_whole_path() {
+            let dotignore = DotIgnore::from_patterns(&["/secret.lua", "/lua/p.lua"]).unwrap();
+            assert!(dotignore.is_ignored("secret.lua"));
+            assert!(!dotignore.is_ignored("lua/secret.lua"));
+            assert!(dotignore.is_ignored("lua/p.lua"));
+            assert!(!dotignore.is_ignored("p.lua"));
         }
     }
 }
 
-// Re-exports for use in main.rs
-pub use filepaths::FilePaths;
-pub use distribution::{Distribution, DistributionParser};
+// Re-exports for use in lib.rs
+pub use toolconfig::ToolConfig;
+pub use filepaths::{EnvExpander, FilePaths, SectionDirs};
+pub use distribution::{DirType, Distribution, DistributionParser, DistributionSettings, FileEntry, Section, SectionMetadata, Severity, DistributionFormat, ValidationWarning};
 pub use ignore::DotIgnore;
\ No newline at end of file
@@ -0,0 +1,56 @@
+use anyhow::Result;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use crate::DotfilesError;
+
+// Encrypt `content` for `key_id`, or for the default GPG key when there is none
+pub fn encrypt(content: &[u8], key_id: Option<&str>) -> Result<Vec<u8>> {
+    let mut args = vec!["--batch", "--yes", "--encrypt"];
+    match key_id {
+        Some(key_id) => args.extend(["--recipient", key_id]),
+        None => args.push("--default-recipient-self"),
+    }
+    
+    run_gpg(&args, content)
+}
+
+// Decrypt `content`; gpg-agent asks for the passphrase if needed
+pub fn decrypt(content: &[u8]) -> Result<Vec<u8>> {
+    run_gpg(&["--quiet", "--decrypt"], content)
+}
+
+// Pipe `content` through gpg and return its output
+fn run_gpg(args: &[&str], content: &[u8]) -> Result<Vec<u8>> {
+    let mut child = Command::new("gpg")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => DotfilesError::EncryptionFailed("gpg not found in PATH".to_string()),
+            _ => DotfilesError::EncryptionFailed(format!("failed to run gpg: {}", e)),
+        })?;
+    
+    // Write from another thread so a full stdout pipe can't block gpg
+    let mut stdin = child.stdin.take()
+        .ok_or_else(|| DotfilesError::EncryptionFailed("failed to open gpg stdin".to_string()))?;
+    let input = content.to_vec();
+    let writer = thread::spawn(move || stdin.write_all(&input));
+    
+    let output = child.wait_with_output()
+        .map_err(|e| DotfilesError::EncryptionFailed(format!("failed to run gpg: {}", e)))?;
+    let written = writer.join();
+    
+    // A gpg failure explains a broken pipe, so report it first
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DotfilesError::EncryptionFailed(format!("gpg exited with {}: {}", output.status, stderr.trim())).into());
+    }
+    written
+        .map_err(|_| DotfilesError::EncryptionFailed("failed to write to gpg".to_string()))?
+        .map_err(|e| DotfilesError::EncryptionFailed(format!("failed to write to gpg: {}", e)))?;
+    
+    Ok(output.stdout)
+}
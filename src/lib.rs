@@ -0,0 +1,7541 @@
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use glob::Pattern;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, DiffTag, TextDiff};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{self, create_dir_all, File, OpenOptions};
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use termcolor::{Buffer, Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use unicode_width::UnicodeWidthStr;
+use walkdir::WalkDir;
+use thiserror::Error;
+
+// Import configuration module
+mod config;
+mod gpg;
+mod oplog;
+mod tree;
+mod util;
+use oplog::{OperationLog, Snapshot, UndoCache};
+use tree::Tree;
+use util::{sha256_hex, RetryPolicy, SshHost};
+pub use util::hash_file;
+use config::{Distribution, DistributionParser, DistributionSettings, FileEntry, Section, SectionMetadata, Severity, ValidationWarning};
+pub use config::{DirType, DotIgnore, FilePaths, DistributionFormat, ToolConfig};
+
+// Include the generated file with embedded content
+// This is generated by build.rs
+include!(concat!(env!("OUT_DIR"), "/embedded_files.rs"));
+include!(concat!(env!("OUT_DIR"), "/embedded_checksums.rs"));
+
+// Custom error types
+#[derive(Error, Debug)]
+enum DotfilesError {
+    #[error("Repository not found: {0}")]
+    RepoNotFound(String),
+    
+    #[error("Distribution file not found: {0}")]
+    DistributionNotFound(String),
+    
+    #[error("Failed to parse distribution file: {0}")]
+    DistributionParseError(String),
+    
+    #[error("File not found: {0}")]
+    FileNotFound(String),
+    
+    #[error("Invalid command format: {0}")]
+    InvalidCommand(String),
+    
+    #[error("File not found in archive: {0}")]
+    ArchiveFileNotFound(String),
+    
+    #[error("Failed to read file from archive: {0}")]
+    ArchiveReadError(String),
+    
+    #[error("Operation aborted: {0}")]
+    Aborted(String),
+    
+    #[error("Encryption failed: {0}")]
+    EncryptionFailed(String),
+    
+    #[error("Tool '{name}' not found{}", did_you_mean(.similar))]
+    ToolNotFound {
+        name: String,
+        similar: Vec<String>,
+    },
+    
+    #[error("Conflict: {tool}/{file} differs from the repository (repo modified {}, local modified {})",
+        format_mtime(*.repo_mtime), format_mtime(*.config_mtime))]
+    ConflictDetected {
+        tool: String,
+        file: String,
+        repo_mtime: SystemTime,
+        config_mtime: SystemTime,
+    },
+}
+
+impl DotfilesError {
+    // A ToolNotFound suggesting the known tools within edit distance 2 of `name`
+    fn tool_not_found<'a>(name: &str, known: impl IntoIterator<Item = &'a String>) -> Self {
+        let similar = known.into_iter()
+            .filter(|tool| strsim::levenshtein(name, tool) <= 2)
+            .cloned()
+            .collect();
+        DotfilesError::ToolNotFound { name: name.to_string(), similar }
+    }
+}
+
+fn did_you_mean(similar: &[String]) -> String {
+    if similar.is_empty() {
+        return String::new();
+    }
+    let names: Vec<String> = similar.iter().map(|name| format!("'{}'", name)).collect();
+    format!(". Did you mean: {}?", names.join(", "))
+}
+
+// A modification time as UTC, to the second
+fn format_mtime(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+// Status symbols
+const CHECK_MARK: &str = "✓";
+const CROSS_MARK: &str = "✗";
+const WARNING_MARK: &str = "⚠";
+const INFO_MARK: &str = "ℹ";
+const ARROW_MARK: &str = "→";
+
+// Variables for the templates of a tool, next to its files in the repo
+const TEMPLATE_VARIABLES_FILE: &str = "variables.toml";
+
+// The file embedding is handled by build.rs and the embedded_files.rs file
+
+// Command line arguments
+#[derive(Parser)]
+#[clap(
+    name = "dotfiles-rs",
+    about = "Dotfiles management done right.",
+    version = env!("CARGO_PKG_VERSION"),
+    override_usage = "dotfiles-rs +<action> [flags]",
+    help_template = "The dotfiles-rs tool is a self-contained binary used to deploy your environment configuration.\n\nUsage: dotfiles-rs +<action> [flags]\n\nActions:\n{subcommands}\n\nOptions:\n{options}\n\nBuild identity: {version}\n",
+)]
+pub struct Cli {
+    /// Enable verbose output with detailed information (repeat for more detail)
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Show all files including identical ones when checking status
+    #[clap(short, long, global = true)]
+    all: bool,
+    
+    /// Only print errors, headers and final summaries
+    #[clap(short, long, global = true)]
+    quiet: bool,
+    
+    /// Also write output to this file with timestamps
+    #[clap(long, global = true)]
+    log_file: Option<PathBuf>,
+    
+    /// Truncate the log file instead of appending to it
+    #[clap(long, global = true)]
+    rotate_log: bool,
+    
+    /// Prefix every output line with the UTC time
+    #[clap(long, global = true)]
+    timestamp: bool,
+    
+    /// When to use colors: always, auto or never
+    #[clap(long, global = true, value_name = "WHEN")]
+    color: Option<String>,
+    
+    /// Only process the tools in this profile from [_profiles]
+    #[clap(long, global = true)]
+    profile: Option<String>,
+    
+    /// Use this dotfiles repository instead of the configured repo_dir
+    #[clap(long, global = true)]
+    repo_dir: Option<PathBuf>,
+    
+    /// Leave undefined $VAR references in the distribution file as written
+    #[clap(long, global = true)]
+    allow_undefined_vars: bool,
+    
+    /// Action to run
+    #[clap(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+#[command(help_template = "Manages dotfiles between system configuration directories and git repository\n\nUsage: dotfiles-rs +<action> [flags]\n\nActions:\n{subcommands}\n\nOptions:\n{options}\n")]
+#[command(subcommand_help_heading = "Actions")]
+pub enum Commands {
+    #[command(name = "+bootstrap")]
+    /// Set up a fresh machine: check the distribution, create directories and install every tool
+    Bootstrap {
+        /// Don't ask for confirmation
+        #[clap(long)]
+        yes: bool,
+        
+        /// Show what would be created and installed without changing anything
+        #[clap(long)]
+        dry_run: bool,
+    },
+    
+    #[command(name = "+sync")]
+    /// Sync files from $HOME/.config to repository
+    Sync {
+        /// Stop at the first error instead of reporting all errors at the end
+        #[clap(long)]
+        fail_fast: bool,
+        
+        /// Only sync files modified on or after this date (YYYY-MM-DD)
+        #[clap(long)]
+        since: Option<chrono::NaiveDate>,
+        
+        /// Only include this tool
+        #[clap(long)]
+        tool: Option<String>,
+        
+        /// Only include tools with any of these tags
+        #[clap(long)]
+        tag: Vec<String>,
+        
+        /// How many times to attempt each copy before giving up
+        #[clap(long)]
+        retries: Option<u32>,
+        
+        /// Delay before the first retry in milliseconds, doubled after each attempt
+        #[clap(long)]
+        retry_delay_ms: Option<u64>,
+        
+        /// Confirm each file before it is synced
+        #[clap(short, long)]
+        interactive: bool,
+        
+        /// Override a template variable from variables.toml
+        #[clap(long = "variables", value_name = "KEY=VALUE")]
+        variables: Vec<String>,
+        
+        /// Don't run the post_sync commands of the synced tools
+        #[clap(long)]
+        skip_post_sync: bool,
+    },
+    
+    #[command(name = "+status")]
+    /// Show status of files in distribution.toml
+    Status {
+        /// Stop at the first error instead of reporting all errors at the end
+        #[clap(long)]
+        fail_fast: bool,
+        
+        /// Print the status counts as JSON
+        #[clap(long)]
+        json: bool,
+        
+        /// Only include this tool
+        #[clap(long)]
+        tool: Option<String>,
+        
+        /// Only include tools with any of these tags
+        #[clap(long)]
+        tag: Vec<String>,
+        
+        /// Read the distribution from standard input instead of distribution.toml
+        #[clap(long)]
+        from_stdin: bool,
+    },
+    
+    #[command(name = "+template")]
+    /// Print a file of a template section rendered with its variables
+    Template {
+        /// The tool name
+        tool: String,
+        
+        /// The tracked file name
+        file: String,
+        
+        /// Override a template variable from variables.toml
+        #[clap(long = "variables", value_name = "KEY=VALUE")]
+        variables: Vec<String>,
+    },
+    
+    #[command(name = "+check")]
+    /// Verify that every tracked file is installed and matches the repository
+    Check {
+        /// Also fail on files that are not installed
+        #[clap(long)]
+        strict: bool,
+    },
+    
+    #[command(name = "+install")]
+    /// Install files from repository to $HOME/.config
+    Install {
+        /// How to handle local files that would be overwritten
+        #[clap(long, value_enum)]
+        conflict: Option<ConflictStrategy>,
+        
+        /// Overwrite local files even when they are newer (destructive)
+        #[clap(long)]
+        force: bool,
+        
+        /// Only include this tool
+        #[clap(long)]
+        tool: Option<String>,
+        
+        /// Only include tools with any of these tags
+        #[clap(long)]
+        tag: Vec<String>,
+        
+        /// Stop at the first error instead of reporting all errors at the end
+        #[clap(long)]
+        fail_fast: bool,
+        
+        /// With --conflict error, list every conflict at the end instead of stopping at the first
+        #[clap(long)]
+        accumulate_errors: bool,
+        
+        /// How many times to attempt each copy before giving up
+        #[clap(long)]
+        retries: Option<u32>,
+        
+        /// Delay before the first retry in milliseconds, doubled after each attempt
+        #[clap(long)]
+        retry_delay_ms: Option<u64>,
+        
+        /// Replace local files that are symlinks instead of skipping them
+        #[clap(long)]
+        overwrite_symlinks: bool,
+        
+        /// Install files that are symlinks in the repository instead of skipping them
+        #[clap(long)]
+        allow_symlinks_in_repo: bool,
+        
+        /// Confirm each file before it is installed
+        #[clap(short, long)]
+        interactive: bool,
+        
+        /// Skip files whose parent directory is missing instead of creating it
+        #[clap(long)]
+        no_create_dirs: bool,
+        
+        /// Read the distribution from standard input instead of distribution.toml
+        #[clap(long)]
+        from_stdin: bool,
+        
+        /// Override a template variable from variables.toml
+        #[clap(long = "variables", value_name = "KEY=VALUE")]
+        variables: Vec<String>,
+    },
+    
+    #[command(name = "+diff")]
+    /// Show line differences between repository and local files
+    Diff {
+        /// Only diff this tool
+        tool: Option<String>,
+        
+        /// Only diff this file within the tool
+        file: Option<String>,
+        
+        /// Only include tools with any of these tags
+        #[clap(long)]
+        tag: Vec<String>,
+        
+        /// Show a table of added and removed line counts instead of the changed lines
+        #[clap(long)]
+        stat: bool,
+        
+        /// Compare the installed files with a stash or other backup directory instead of the repository
+        #[clap(long)]
+        from_backup: Option<PathBuf>,
+    },
+    
+    #[command(name = "+update")]
+    /// Find files in tool config directories that are not tracked yet
+    Update {
+        /// Only scan this tool
+        tool: Option<String>,
+        
+        /// Add every untracked file
+        #[clap(long)]
+        add: bool,
+        
+        /// Ask before adding each untracked file
+        #[clap(long)]
+        interactive: bool,
+        
+        /// Also scan subdirectories
+        #[clap(long)]
+        recursive: bool,
+    },
+    
+    #[command(name = "+stash")]
+    /// Save installed config files under the repo stash directory
+    Stash,
+    
+    #[command(name = "+unstash")]
+    /// List stashes, or restore one to the config directory
+    Unstash {
+        /// The stash to restore
+        stash_id: Option<String>,
+        
+        /// List stashes with their timestamps and file counts
+        #[clap(long)]
+        list: bool,
+        
+        /// Delete this stash
+        #[clap(long)]
+        drop: Option<String>,
+    },
+    
+    #[command(name = "+snapshot")]
+    /// Save a named copy of the repo's config directory and distribution.toml
+    Snapshot {
+        /// Name of the snapshot, e.g. before-nvim-upgrade
+        name: String,
+    },
+    
+    #[command(name = "+snapshot-list")]
+    /// List snapshots with their creation time and size
+    SnapshotList,
+    
+    #[command(name = "+snapshot-restore")]
+    /// Replace the repo's config directory and distribution.toml with a snapshot
+    SnapshotRestore {
+        /// The snapshot to restore
+        name: String,
+    },
+    
+    #[command(name = "+snapshot-delete")]
+    /// Delete a snapshot
+    SnapshotDelete {
+        /// The snapshot to delete
+        name: String,
+        
+        /// Don't ask for confirmation
+        #[clap(long)]
+        yes: bool,
+    },
+    
+    #[command(name = "+add")]
+    /// Add a file to distribution.toml and copy to repo
+    Add {
+        /// The tool name (directory under .config)
+        tool: String,
+        
+        /// The file name to add
+        file: String,
+        
+        /// Description written when creating a new tool section
+        #[clap(long)]
+        description: Option<String>,
+        
+        /// Upstream URL written when creating a new tool section
+        #[clap(long)]
+        url: Option<String>,
+        
+        /// Tags written when creating a new tool section
+        #[clap(long)]
+        tag: Vec<String>,
+        
+        /// Sort distribution.toml after adding the file
+        #[clap(long)]
+        sort: bool,
+        
+        /// How many times to attempt each copy before giving up
+        #[clap(long)]
+        retries: Option<u32>,
+        
+        /// Delay before the first retry in milliseconds, doubled after each attempt
+        #[clap(long)]
+        retry_delay_ms: Option<u64>,
+    },
+    
+    #[command(name = "+new")]
+    /// Create the directories and an empty distribution.toml section for a new tool
+    New {
+        /// The tool name (directory under .config)
+        tool: String,
+        
+        /// Description of the tool
+        #[clap(long)]
+        description: Option<String>,
+        
+        /// Tags for the tool
+        #[clap(long)]
+        tag: Vec<String>,
+        
+        /// Don't create the local config directory
+        #[clap(long)]
+        no_config_dir: bool,
+    },
+    
+    #[command(name = "+remove")]
+    /// Remove a file from distribution.toml
+    Remove {
+        /// The tool name (directory under .config)
+        tool: String,
+        
+        /// The file name to remove
+        file: String,
+    },
+    
+    #[command(name = "+mv")]
+    /// Move a tracked file to another tool, optionally renaming it
+    Mv {
+        /// The tool the file is tracked under
+        from_tool: String,
+        
+        /// The tracked file name
+        file: String,
+        
+        /// The tool to move it to
+        to_tool: String,
+        
+        /// A new name for the file; defaults to its current name
+        new_file: Option<String>,
+        
+        /// Add the destination tool to distribution.toml if it doesn't exist
+        #[clap(long)]
+        create_section: bool,
+    },
+    
+    #[command(name = "+cp")]
+    /// Copy a tracked file's repository version to any path, without tracking it
+    Cp {
+        /// The tool name
+        tool: String,
+        
+        /// The tracked file name
+        file: String,
+        
+        /// Where to write the copy
+        destination: PathBuf,
+        
+        /// Overwrite the destination if it exists
+        #[clap(long)]
+        force: bool,
+    },
+    
+    #[command(name = "+list")]
+    /// List tools in distribution.toml
+    List {
+        /// Show each tool's description and URL
+        #[clap(long)]
+        describe: bool,
+        
+        /// Output layout
+        #[clap(long, value_enum)]
+        format: Option<ListFormat>,
+        
+        /// List tools in the order install and sync process them
+        #[clap(long)]
+        priority_order: bool,
+        
+        /// Only include tools with any of these tags
+        #[clap(long)]
+        tag: Vec<String>,
+    },
+    
+    #[command(name = "+log")]
+    /// Show the history of operations recorded in operations.log
+    Log {
+        /// Only show the most recent entries
+        #[clap(long = "last")]
+        last_n: Option<usize>,
+        
+        /// Only show entries for this command, e.g. sync
+        #[clap(long = "command")]
+        command_filter: Option<String>,
+        
+        /// Only show entries for this tool
+        #[clap(long = "tool")]
+        tool_filter: Option<String>,
+        
+        /// Empty the log after asking for confirmation
+        #[clap(long)]
+        clear: bool,
+    },
+    
+    #[command(name = "+undo")]
+    /// Reverse the most recent operations recorded in operations.log
+    Undo {
+        /// How many operations to undo (default 1)
+        steps: Option<usize>,
+    },
+    
+    #[command(name = "+doctor")]
+    /// Check the repository, distribution file, config directories and required programs
+    Doctor,
+    
+    #[command(name = "+repair")]
+    /// Re-point dangling symlinks of tracked files at their repository copies
+    Repair {
+        /// Only list the dangling symlinks
+        #[clap(long)]
+        dry_run: bool,
+        
+        /// Remove dangling symlinks instead of re-pointing them
+        #[clap(long)]
+        remove_dangling: bool,
+    },
+    
+    #[command(name = "+link")]
+    /// Replace a tracked file's config path with a symlink to its repository copy
+    Link {
+        /// Tool name
+        tool: String,
+        
+        /// File name
+        file: String,
+        
+        /// Replace a symlink that points somewhere else
+        #[clap(long)]
+        relink: bool,
+    },
+    
+    #[command(name = "+unlink")]
+    /// Replace a symlink made by +link with a copy of the repository file
+    Unlink {
+        /// Tool name
+        tool: String,
+        
+        /// File name
+        file: String,
+    },
+    
+    #[command(name = "+tags")]
+    /// List the tags used in distribution.toml and how many tools have each
+    Tags,
+    
+    #[command(name = "+info")]
+    /// Show the settings of a tool and the status of each of its files
+    Info {
+        /// Tool name
+        tool: String,
+    },
+    
+    #[command(name = "+fetch")]
+    /// Copy the tracked files from another machine over SSH with scp
+    Fetch {
+        /// Host to copy from, as scp accepts it
+        host: String,
+        
+        /// Only include this tool
+        #[clap(long)]
+        tool: Option<String>,
+        
+        /// User to log in as
+        #[clap(long)]
+        ssh_user: Option<String>,
+        
+        /// SSH port of the host
+        #[clap(long)]
+        ssh_port: Option<u16>,
+        
+        /// Put the files into the repository instead of the config directory
+        #[clap(long)]
+        to_repo: bool,
+    },
+    
+    #[command(name = "+push")]
+    /// Copy the tracked files from the repository to another machine over SSH with scp
+    Push {
+        /// Host to copy to, as scp accepts it
+        host: String,
+        
+        /// Only include this tool
+        #[clap(long)]
+        tool: Option<String>,
+        
+        /// User to log in as
+        #[clap(long)]
+        ssh_user: Option<String>,
+        
+        /// SSH port of the host
+        #[clap(long)]
+        ssh_port: Option<u16>,
+        
+        /// Show what would be pushed without copying anything
+        #[clap(long)]
+        dry_run: bool,
+        
+        /// Show how each file on the host differs before pushing it
+        #[clap(long)]
+        diff_first: bool,
+    },
+    
+    #[command(name = "+where")]
+    /// Show the repository and config paths of a tracked file
+    Where {
+        /// Tool name
+        tool: String,
+        
+        /// File name
+        file: String,
+        
+        /// Open the file in $EDITOR
+        #[clap(long)]
+        open: bool,
+        
+        /// Only print the repository path
+        #[clap(long, conflicts_with = "config")]
+        repo: bool,
+        
+        /// Only print the config path
+        #[clap(long)]
+        config: bool,
+    },
+    
+    #[command(name = "+touch")]
+    /// Set the modification time of a tracked file to now, e.g. so sync --since picks it up
+    Touch {
+        /// Tool name
+        tool: String,
+        
+        /// File name
+        file: String,
+        
+        /// Which copy of the file to touch
+        #[clap(long, value_enum, default_value = "config")]
+        side: TouchSide,
+    },
+    
+    #[command(name = "+stats")]
+    /// Show file counts, sizes and the largest and newest files in the repository
+    Stats {
+        /// Print the statistics as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    
+    #[command(name = "+tree")]
+    /// Show tracked files as a directory tree
+    Tree {
+        /// Only show this many levels below the repository
+        #[clap(long)]
+        depth: Option<usize>,
+        
+        /// Show each file's status next to it
+        #[clap(long)]
+        status: bool,
+        
+        /// Draw the tree with ASCII characters instead of box-drawing ones
+        #[clap(long)]
+        ascii: bool,
+    },
+    
+    #[command(name = "+profile")]
+    /// Manage the named groups of tools in [_profiles]
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+    
+    #[command(name = "+lint")]
+    /// Check distribution.toml for style problems without changing it
+    Lint {
+        /// Fail on warnings too, not just errors
+        #[clap(long)]
+        strict: bool,
+    },
+    
+    #[command(name = "+fmt")]
+    /// Rewrite distribution.toml in a consistent, sorted layout
+    Fmt,
+    
+    #[command(name = "+verify")]
+    /// Check the files embedded in the binary against their build-time checksums
+    Verify {
+        /// Verify the embedded archive
+        #[clap(long)]
+        embedded: bool,
+    },
+    
+    #[command(name = "+deduplicate")]
+    /// Remove duplicate file entries from distribution.toml
+    Deduplicate {
+        /// Also remove entries that only differ by case
+        #[clap(long)]
+        case_insensitive: bool,
+    },
+    
+    #[command(name = "+audit")]
+    /// Check tracked files for names and content that look sensitive
+    Audit {
+        /// Add flagged files to .dotignore
+        #[clap(long)]
+        fix: bool,
+    },
+    
+    #[command(name = "+clean")]
+    /// Find repo files that are no longer listed in distribution.toml
+    Clean {
+        /// Remove the orphaned files and empty directories
+        #[clap(long)]
+        delete: bool,
+        
+        /// Only list what would be removed
+        #[clap(long)]
+        dry_run: bool,
+        
+        /// Confirm deleting more than 10 files
+        #[clap(long)]
+        yes: bool,
+    },
+    
+    #[command(name = "+gc")]
+    /// Remove tool sections that have no files
+    Gc {
+        /// Only list the sections that would be removed
+        #[clap(long)]
+        dry_run: bool,
+        
+        /// Don't ask before removing more than one section
+        #[clap(long)]
+        yes: bool,
+        
+        /// Also remove the repo config directories of removed sections when they are empty
+        #[clap(long)]
+        remove_empty_dirs: bool,
+        
+        /// Also remove disabled sections
+        #[clap(long)]
+        strict: bool,
+    },
+    
+    #[command(name = "+enable")]
+    /// Resume syncing a disabled tool
+    Enable {
+        /// The tool name
+        tool: String,
+    },
+    
+    #[command(name = "+disable")]
+    /// Stop syncing a tool without removing it from distribution.toml
+    Disable {
+        /// The tool name
+        tool: String,
+    },
+    
+    #[command(name = "+ignore")]
+    /// Manage .dotignore patterns
+    Ignore {
+        #[command(subcommand)]
+        action: IgnoreAction,
+    },
+    
+    #[command(name = "+gitignore")]
+    /// Write a .gitignore for the repository's local-only and sensitive files
+    GitIgnore {
+        /// Only add entries missing from an existing .gitignore
+        #[clap(long)]
+        append: bool,
+        
+        /// Show the entries that would be written without writing them
+        #[clap(long)]
+        dry_run: bool,
+    },
+    
+    #[command(name = "+sort")]
+    /// Sort tools and files in distribution.toml alphabetically
+    Sort,
+    
+    #[command(name = "+precheck")]
+    /// Check that distribution.toml exists and has valid syntax
+    Precheck {
+        /// Parse the distribution file as this format instead of using its extension
+        #[clap(long)]
+        format: Option<DistributionFormat>,
+        
+        /// Compare the files under the repo config/ directory with the distribution file
+        #[clap(long)]
+        check_repos: bool,
+        
+        /// Repair duplicate entries, unsorted sections, empty sections and '\\' separators
+        #[clap(long)]
+        fix: bool,
+        
+        /// Only print the resolved XDG base directories
+        #[clap(long)]
+        xdg: bool,
+        
+        /// Only check that every $VAR in the distribution file is defined
+        #[clap(long)]
+        check_vars: bool,
+        
+        /// Only print the ignore patterns from ~/.dotignore and the repo's .dotignore
+        #[clap(long)]
+        ignore_info: bool,
+        
+        /// Read the distribution from standard input instead of distribution.toml
+        #[clap(long)]
+        from_stdin: bool,
+    },
+    
+    #[command(name = "+merge")]
+    /// Merge another distribution file into this one
+    Merge {
+        /// The distribution file to merge in
+        other_file: PathBuf,
+        
+        /// Where to write the result (defaults to distribution.toml)
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+    
+    #[command(name = "+convert")]
+    /// Convert a distribution file to another format, chosen by file extension
+    Convert {
+        /// The distribution file to read (.toml, .yaml or .json)
+        from: PathBuf,
+        
+        /// The file to write (.toml, .yaml or .json)
+        to: PathBuf,
+    },
+    
+    #[command(name = "+usage")]
+    /// Show usage information
+    Usage,
+    
+    #[command(name = "+config")]
+    /// Manage dotfiles-rs settings
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    
+    #[command(name = "+version")]
+    /// Show version and build information
+    Version,
+    
+    #[command(name = "+help")]
+    /// Show this help information
+    Help,
+}
+
+impl Commands {
+    /// The command's name as given on the command line, without the leading `+`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Commands::Bootstrap { .. } => "bootstrap",
+            Commands::Sync { .. } => "sync",
+            Commands::Status { .. } => "status",
+            Commands::Template { .. } => "template",
+            Commands::Check { .. } => "check",
+            Commands::Install { .. } => "install",
+            Commands::Diff { .. } => "diff",
+            Commands::Update { .. } => "update",
+            Commands::Stash => "stash",
+            Commands::Unstash { .. } => "unstash",
+            Commands::Snapshot { .. } => "snapshot",
+            Commands::SnapshotList => "snapshot-list",
+            Commands::SnapshotRestore { .. } => "snapshot-restore",
+            Commands::SnapshotDelete { .. } => "snapshot-delete",
+            Commands::Add { .. } => "add",
+            Commands::New { .. } => "new",
+            Commands::Remove { .. } => "remove",
+            Commands::Mv { .. } => "mv",
+            Commands::Cp { .. } => "cp",
+            Commands::List { .. } => "list",
+            Commands::Log { .. } => "log",
+            Commands::Undo { .. } => "undo",
+            Commands::Doctor => "doctor",
+            Commands::Repair { .. } => "repair",
+            Commands::Link { .. } => "link",
+            Commands::Unlink { .. } => "unlink",
+            Commands::Tags => "tags",
+            Commands::Info { .. } => "info",
+            Commands::Fetch { .. } => "fetch",
+            Commands::Push { .. } => "push",
+            Commands::Where { .. } => "where",
+            Commands::Touch { .. } => "touch",
+            Commands::Stats { .. } => "stats",
+            Commands::Tree { .. } => "tree",
+            Commands::Profile { .. } => "profile",
+            Commands::Lint { .. } => "lint",
+            Commands::Fmt => "fmt",
+            Commands::Verify { .. } => "verify",
+            Commands::Deduplicate { .. } => "deduplicate",
+            Commands::Audit { .. } => "audit",
+            Commands::Clean { .. } => "clean",
+            Commands::Gc { .. } => "gc",
+            Commands::Enable { .. } => "enable",
+            Commands::Disable { .. } => "disable",
+            Commands::Ignore { .. } => "ignore",
+            Commands::GitIgnore { .. } => "gitignore",
+            Commands::Sort => "sort",
+            Commands::Precheck { .. } => "precheck",
+            Commands::Merge { .. } => "merge",
+            Commands::Convert { .. } => "convert",
+            Commands::Usage => "usage",
+            Commands::Config { .. } => "config",
+            Commands::Version => "version",
+            Commands::Help => "help",
+        }
+    }
+}
+
+// How install handles a local file that already exists
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ConflictStrategy {
+    /// Always replace the local file with the repo version
+    Overwrite,
+    /// Only replace the local file if it is older than the repo version
+    Skip,
+    /// Prompt for each local file that differs from the repo version
+    Ask,
+    /// Fail on each local file that differs from the repo version
+    Error,
+}
+
+// How list prints tools
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ListFormat {
+    /// One tool per line
+    Plain,
+    /// Aligned columns with each tool's file status
+    Table,
+}
+
+// Which copies of a file +touch updates
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum TouchSide {
+    /// The file in the config directory
+    Config,
+    /// The copy in the repository
+    Repo,
+    /// Both copies
+    Both,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Write the default config file
+    Init,
+    
+    /// Show the current settings
+    Show,
+    
+    /// Update a single setting
+    Set {
+        /// The setting name
+        key: String,
+        
+        /// The new value
+        value: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum IgnoreAction {
+    /// Append a pattern to .dotignore
+    Add {
+        /// The glob pattern, optionally starting with !
+        pattern: String,
+    },
+    
+    /// Remove a pattern from .dotignore
+    Remove {
+        /// The exact pattern line
+        pattern: String,
+    },
+    
+    /// Show the patterns in .dotignore with line numbers
+    List,
+    
+    /// Show whether a file would be ignored and which pattern decides it
+    Test {
+        /// The file name to check
+        filename: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProfileAction {
+    /// List profiles and how many tools each has
+    List,
+    
+    /// Show the tools in a profile
+    Show {
+        /// The profile name
+        name: String,
+    },
+    
+    /// Add a tool to a profile, creating the profile if needed
+    AddTool {
+        /// The profile name
+        profile: String,
+        
+        /// The tool name
+        tool: String,
+    },
+    
+    /// Remove a tool from a profile
+    RemoveTool {
+        /// The profile name
+        profile: String,
+        
+        /// The tool name
+        tool: String,
+    },
+}
+
+// In-memory output for a Formatter, shared with whoever inspects it. Colors are dropped
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().map_err(|_| std::io::Error::other("output buffer poisoned"))?.write(buf)
+    }
+    
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl WriteColor for SharedBuffer {
+    fn supports_color(&self) -> bool {
+        false
+    }
+    
+    fn set_color(&mut self, _spec: &ColorSpec) -> std::io::Result<()> {
+        Ok(())
+    }
+    
+    fn reset(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// Progress bar for install and sync, drawn on stdout below the Formatter
+// output, e.g. `[===>  ] 23/150 Syncing nvim/init.lua`
+pub struct ProgressReporter {
+    bar: ProgressBar,
+}
+
+impl ProgressReporter {
+    pub fn new(total: u64) -> Self {
+        let bar = ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::stdout());
+        let style = ProgressStyle::with_template("[{bar:30}] {pos}/{len} {msg}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("=> ");
+        bar.set_style(style);
+        Self { bar }
+    }
+    
+    // Name the file being worked on
+    pub fn set_message(&self, message: String) {
+        self.bar.set_message(message);
+    }
+    
+    // Count a file as done
+    pub fn inc(&self) {
+        self.bar.inc(1);
+    }
+    
+    // Hide the bar while `f` writes, then draw it again
+    fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.bar.suspend(f)
+    }
+}
+
+impl Drop for ProgressReporter {
+    // Errors can end a run early, so the bar is cleared however it ends
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+// Output formatter helper
+pub struct Formatter {
+    stdout: Box<dyn WriteColor + Send>,
+    progress: Option<ProgressReporter>,
+    // While a progress bar is shown, the current line is built here and
+    // written above the bar once it is complete
+    pending_line: Option<Buffer>,
+    verbose: u8,
+    quiet: bool,
+    log_file: Option<BufWriter<File>>,
+    log_line: String,
+    log_level: &'static str,
+    timestamp: bool,
+    // Whether anything has been printed since the last newline
+    line_started: bool,
+    // Warnings reported so far, including those --quiet hides
+    warnings: u32,
+}
+
+impl Formatter {
+    pub fn new(verbose: u8) -> Self {
+        let mut formatter = Self::new_with_choice(ColorChoice::Auto);
+        formatter.verbose = verbose;
+        formatter
+    }
+    
+    fn new_with_choice(choice: ColorChoice) -> Self {
+        Self::new_with_sink(Box::new(StandardStream::stdout(choice)))
+    }
+    
+    // Map --color, or color_output from the config when it isn't given, to a
+    // color choice. A non-empty NO_COLOR turns colors off unless --color is given
+    fn color_choice(flag: Option<&str>, configured: &str) -> ColorChoice {
+        let value = match flag {
+            Some(value) => value,
+            None if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) => "never",
+            None => configured,
+        };
+        match value {
+            "always" => ColorChoice::Always,
+            "never" => ColorChoice::Never,
+            _ => ColorChoice::Auto,
+        }
+    }
+    
+    // Write output to `sink` instead of stdout
+    pub fn new_with_sink(sink: Box<dyn WriteColor + Send>) -> Self {
+        Self {
+            stdout: sink,
+            progress: None,
+            pending_line: None,
+            verbose: 0,
+            quiet: false,
+            log_file: None,
+            log_line: String::new(),
+            log_level: "INFO",
+            timestamp: false,
+            line_started: false,
+            warnings: 0,
+        }
+    }
+    
+    // Capture output in memory, e.g. to check messages without spawning the binary
+    pub fn new_test_sink() -> (Self, Arc<Mutex<Vec<u8>>>) {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let formatter = Self::new_with_sink(Box::new(SharedBuffer(Arc::clone(&buffer))));
+        (formatter, buffer)
+    }
+    
+    // Also write every output line to `path`, appending unless `rotate` is set.
+    // A log file that can't be opened only produces a warning
+    pub fn set_log_file(&mut self, path: &Path, rotate: bool) {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(!rotate)
+            .truncate(rotate)
+            .open(path);
+        
+        match file {
+            Ok(file) => self.log_file = Some(BufWriter::new(file)),
+            Err(e) => eprintln!("Warning: cannot open log file {}: {}", path.display(), e),
+        }
+    }
+    
+    // End the current line, writing it to the log file with a timestamp and level
+    fn newline(&mut self) -> Result<()> {
+        match (self.pending_line.take(), &self.progress) {
+            (Some(line), Some(progress)) => {
+                let stdout = &mut self.stdout;
+                progress.suspend(|| writeln!(stdout, "{}", String::from_utf8_lossy(line.as_slice())))?;
+            },
+            (Some(line), None) => writeln!(self.stdout, "{}", String::from_utf8_lossy(line.as_slice()))?,
+            (None, _) => writeln!(self.stdout)?,
+        }
+        
+        if let Some(log_file) = self.log_file.as_mut() {
+            if !self.log_line.trim().is_empty() {
+                let timestamp = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+                writeln!(log_file, "{} [{}] {}", timestamp, self.log_level, self.log_line.trim())?;
+            }
+        }
+        self.log_line.clear();
+        self.log_level = "INFO";
+        self.line_started = false;
+        
+        Ok(())
+    }
+    
+    // Prefix each output line with the current UTC time
+    pub fn set_timestamp(&mut self, timestamp: bool) {
+        self.timestamp = timestamp;
+    }
+    
+    // In quiet mode only errors, headers and final summaries are printed
+    fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+    
+    // Show a progress bar over `total` files until finish_progress. It is
+    // left out in quiet mode and when stdout is not a terminal
+    pub fn start_progress(&mut self, total: usize) {
+        if !self.quiet && std::io::stdout().is_terminal() {
+            self.progress = Some(ProgressReporter::new(total as u64));
+        }
+    }
+    
+    pub fn progress_message(&self, message: String) {
+        if let Some(progress) = &self.progress {
+            progress.set_message(message);
+        }
+    }
+    
+    pub fn progress_inc(&self) {
+        if let Some(progress) = &self.progress {
+            progress.inc();
+        }
+    }
+    
+    pub fn finish_progress(&mut self) {
+        self.progress = None;
+    }
+    
+    // Where the current line goes: straight to stdout, or into a buffer
+    // while the progress bar is shown
+    fn out(&mut self) -> &mut dyn WriteColor {
+        if self.progress.is_some() && self.pending_line.is_none() {
+            self.pending_line = Some(if self.stdout.supports_color() { Buffer::ansi() } else { Buffer::no_color() });
+        }
+        match self.pending_line.as_mut() {
+            Some(line) => line,
+            None => &mut *self.stdout,
+        }
+    }
+    
+    fn print(&mut self, message: &str, color: Option<Color>, bold_italic: bool) -> Result<()> {
+        self.start_line()?;
+        
+        let mut color_spec = ColorSpec::new();
+        if let Some(c) = color {
+            color_spec.set_fg(Some(c));
+        }
+        color_spec.set_bold(bold_italic);
+        color_spec.set_italic(bold_italic);
+        self.write_styled(message, &color_spec)
+    }
+    
+    // Print secondary text, such as the path after a status, in a faint style
+    fn dim(&mut self, message: &str) -> Result<()> {
+        self.start_line()?;
+        
+        let mut color_spec = ColorSpec::new();
+        color_spec.set_dimmed(true);
+        self.write_styled(message, &color_spec)
+    }
+    
+    // A line is made of several print calls, so only its first one gets the timestamp
+    fn start_line(&mut self) -> Result<()> {
+        if self.timestamp && !self.line_started {
+            let mut timestamp_spec = ColorSpec::new();
+            timestamp_spec.set_fg(Some(Color::White)).set_dimmed(true);
+            let out = self.out();
+            out.set_color(&timestamp_spec)?;
+            write!(out, "[{}] ", chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"))?;
+            out.reset()?;
+        }
+        self.line_started = true;
+        Ok(())
+    }
+    
+    fn write_styled(&mut self, message: &str, color_spec: &ColorSpec) -> Result<()> {
+        let out = self.out();
+        out.set_color(color_spec)?;
+        write!(out, "{}", message)?;
+        out.reset()?;
+        
+        if self.log_file.is_some() {
+            self.log_line.push_str(message);
+        }
+        Ok(())
+    }
+    
+    fn warning(&mut self, message: &str) -> Result<()> {
+        self.warnings += 1;
+        if self.quiet {
+            return Ok(());
+        }
+        
+        self.log_level = "WARN";
+        self.print(&format!("{} ", WARNING_MARK), Some(Color::Yellow), false)?;
+        
+        // Special case for "Not installed:" messages
+        if message.starts_with("Not installed:") {
+            // Redirect to not_installed formatter for consistent styling
+            return self.not_installed(message);
+        }
+        
+        // Special case for "File not found:" messages
+        if message.starts_with("File not found:") || message.starts_with("Local file not found:") {
+            if let Some(idx) = message.find(": ") {
+                let (status, path) = message.split_at(idx + 2);
+                self.print(status, Some(Color::Yellow), true)?; // Make the status part yellow and bold
+                self.print(path, None, false)?;
+            } else {
+                self.print(message, None, false)?;
+            }
+        } else {
+            // Default handling for other warnings with the part before the colon in bold
+            if let Some(idx) = message.find(": ") {
+                let (status, content) = message.split_at(idx + 2);
+                self.print(status, Some(Color::Yellow), true)?; // Make the status part yellow and bold
+                self.print(content, None, false)?;
+            } else {
+                self.print(message, None, false)?;
+            }
+        }
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn error(&mut self, message: &str) -> Result<()> {
+        self.log_level = "ERROR";
+        self.print(&format!("{} ", CROSS_MARK), Some(Color::Red), false)?;
+        
+        // Format error messages with status text in red and bold
+        if let Some(idx) = message.find(": ") {
+            let (status, content) = message.split_at(idx + 2);
+            self.print(status, Some(Color::Red), true)?; // Make the status part red and bold
+            self.print(content, None, false)?;
+        } else {
+            self.print(message, None, false)?;
+        }
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn info(&mut self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        
+        self.print(&format!("{} ", INFO_MARK), Some(Color::Blue), false)?;
+        
+        // Process tool names differently
+        if message.starts_with("Processing tool:") {
+            if let Some(idx) = message.find(": ") {
+                let (status, tool) = message.split_at(idx + 2);
+                self.print(status, Some(Color::Blue), true)?; // Make the status part blue and bold
+                self.print(tool, Some(Color::White), true)?;  // Make the tool name white and bold
+            } else {
+                self.print(message, None, false)?;
+            }
+        } else {
+            // Format other info messages with status text in blue and bold
+            if let Some(idx) = message.find(": ") {
+                let (status, content) = message.split_at(idx + 2);
+                self.print(status, Some(Color::Blue), true)?; // Make the status part blue and bold
+                self.print(content, None, false)?;
+            } else {
+                self.print(message, None, false)?;
+            }
+        }
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn modified(&mut self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        
+        self.print(&format!("{} ", ARROW_MARK), Some(Color::Magenta), false)?;
+        
+        // Format modified messages with status text in magenta and bold
+        if let Some(idx) = message.find(": ") {
+            let (status, content) = message.split_at(idx + 2);
+            self.print(status, Some(Color::Magenta), true)?; // Make the status part magenta and bold
+            self.dim(content)?;
+        } else {
+            self.print(message, None, false)?;
+        }
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn identical(&mut self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        
+        // Use green for icon, and make the text blue for better visibility
+        self.print(&format!("{} ", CHECK_MARK), Some(Color::Green), false)?;
+        
+        // Split the message into parts: "Identical: " and the actual path
+        if let Some(idx) = message.find(": ") {
+            let (status, content) = message.split_at(idx + 2);
+            self.print(status, Some(Color::Blue), true)?; // Make the "Identical: " part blue and bold
+            self.dim(content)?;
+        } else {
+            // Fallback if there's no ": " in the message
+            self.print(message, Some(Color::Blue), false)?;
+        }
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn not_installed(&mut self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        
+        // Keep the warning icon but use a distinct color for "Not installed: "
+        self.print(&format!("{} ", WARNING_MARK), Some(Color::Yellow), false)?;
+        
+        // Split the message into parts: "Not installed: " and the actual path
+        if let Some(idx) = message.find(": ") {
+            let (status, content) = message.split_at(idx + 2);
+            self.print(status, Some(Color::Cyan), true)?; // Make the "Not installed: " part cyan and bold
+            self.dim(content)?;
+        } else {
+            // Fallback if there's no ": " in the message
+            self.print(message, None, false)?;
+        }
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn installed(&mut self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        
+        // Use green checkmark with purple text for "Installed to local: "
+        self.print(&format!("{} ", CHECK_MARK), Some(Color::Green), false)?;
+        
+        // Split the message into parts: "Installed to local: " and the actual path
+        if let Some(idx) = message.find(": ") {
+            let (status, content) = message.split_at(idx + 2);
+            self.print(status, Some(Color::Magenta), true)?; // Make the "Installed to local: " part magenta and bold
+            self.dim(content)?;
+        } else {
+            // Fallback if there's no ": " in the message
+            self.print(message, None, false)?;
+        }
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn synced(&mut self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        
+        // Use green checkmark with green text for "Synced to repo: "
+        self.print(&format!("{} ", CHECK_MARK), Some(Color::Green), false)?;
+        
+        // Split the message into parts: "Synced to repo: " and the actual path
+        if let Some(idx) = message.find(": ") {
+            let (status, content) = message.split_at(idx + 2);
+            self.print(status, Some(Color::Green), true)?; // Make the "Synced to repo: " part green and bold
+            self.dim(content)?;
+        } else {
+            // Fallback if there's no ": " in the message
+            self.print(message, None, false)?;
+        }
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn tracking(&mut self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        
+        // Use green checkmark with blue text for "Added to tracking: "
+        self.print(&format!("{} ", CHECK_MARK), Some(Color::Green), false)?;
+        
+        // Split the message into parts: "Added to tracking: " and the actual path
+        if let Some(idx) = message.find(": ") {
+            let (status, content) = message.split_at(idx + 2);
+            self.print(status, Some(Color::Blue), true)?; // Make the "Added to tracking: " part blue and bold
+            self.print(content, None, false)?;
+        } else {
+            // Fallback if there's no ": " in the message
+            self.print(message, None, false)?;
+        }
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn validation(&mut self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        
+        // Use green checkmark with cyan text for validation messages
+        self.print(&format!("{} ", CHECK_MARK), Some(Color::Green), false)?;
+        
+        // Always make validation messages bold
+        self.print(message, Some(Color::Cyan), true)?;
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn success(&mut self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        
+        self.print(&format!("{} ", CHECK_MARK), Some(Color::Green), false)?;
+        self.print(message, Some(Color::Green), false)?;
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn action(&mut self, message: &str) -> Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+        
+        // Use cyan color for actions that modify the filesystem
+        self.print("+ ", Some(Color::Cyan), false)?;
+        
+        // Split the message into parts if it contains ": "
+        if let Some(idx) = message.find(": ") {
+            let (action, content) = message.split_at(idx + 2);
+            self.print(action, Some(Color::Cyan), true)?;  // Make the action part cyan and bold
+            self.print(content, None, false)?;
+        } else {
+            self.print(message, None, false)?;
+        }
+        
+        self.newline()?;
+        Ok(())
+    }
+    
+    // Final result line of a command, printed even in quiet mode
+    pub fn summary(&mut self, message: &str) -> Result<()> {
+        self.print(&format!("{} ", INFO_MARK), Some(Color::Blue), false)?;
+        self.print(message, None, false)?;
+        self.newline()?;
+        Ok(())
+    }
+    
+    fn header(&mut self, message: &str) -> Result<()> {
+        self.print(message, None, true)?;
+        self.newline()?;
+        Ok(())
+    }
+    
+    // Print rows in aligned columns; widths use display width so wide characters line up
+    fn table(&mut self, headers: &[&str], rows: &[Vec<String>], colors: &[Option<Color>]) -> Result<()> {
+        let mut widths: Vec<usize> = headers.iter().map(|h| h.width()).collect();
+        for row in rows {
+            for (i, cell) in row.iter().enumerate().take(widths.len()) {
+                widths[i] = widths[i].max(cell.width());
+            }
+        }
+        
+        for (i, header) in headers.iter().enumerate() {
+            self.print(&Self::pad_cell(header, widths[i], i + 1 == headers.len()), None, true)?;
+        }
+        self.newline()?;
+        
+        for row in rows {
+            for (i, cell) in row.iter().enumerate().take(widths.len()) {
+                let color = colors.get(i).copied().flatten();
+                self.print(&Self::pad_cell(cell, widths[i], i + 1 == headers.len()), color, false)?;
+            }
+            self.newline()?;
+        }
+        
+        Ok(())
+    }
+    
+    // Right-pad a cell to the column width plus a gap, except in the last column
+    fn pad_cell(cell: &str, width: usize, last: bool) -> String {
+        if last {
+            cell.to_string()
+        } else {
+            format!("{}{}", cell, " ".repeat(width - cell.width() + 2))
+        }
+    }
+    
+    fn verbosity(&self) -> u8 {
+        self.verbose
+    }
+    
+    // Only output in verbose mode
+    pub fn verbose(&mut self, message: &str) -> Result<()> {
+        if self.verbose > 0 {
+            // Special handling for example output display
+            if message.starts_with("EXAMPLE:") {
+                let example_content = message.trim_start_matches("EXAMPLE:");
+                write!(self.stdout, "{}", example_content)?;
+                return Ok(());
+            }
+            
+            self.log_level = "DEBUG";
+            self.print(&format!("{} ", INFO_MARK), Some(Color::White), false)?;
+            self.print("Verbose: ", Some(Color::White), true)?;
+            self.print(message, None, false)?;
+            self.newline()?;
+        }
+        Ok(())
+    }
+}
+
+// Paths helper is now in the config module as FilePaths
+
+// DotfilesArchive provides access to the embedded files
+struct DotfilesArchive;
+
+// An embedded file whose content no longer matches the checksum taken at build time
+struct IntegrityError {
+    path: String,
+    expected: String,
+    actual: String,
+}
+
+impl DotfilesArchive {
+    fn require_embedded_feature() -> Result<()> {
+        if !cfg!(feature = "embedded") {
+            return Err(DotfilesError::ArchiveReadError(
+                "dotfiles-rs was built without the `embedded` feature".to_string()).into());
+        }
+        Ok(())
+    }
+    
+    // Check if a file exists in the archive
+    fn file_exists(section: &str, file: &str) -> bool {
+        let path = format!("config/{}/{}", section, file);
+        EMBEDDED_FILES.get(&path).is_some()
+    }
+    
+    // Get a file from the archive
+    fn get_file(section: &str, file: &str) -> Result<Vec<u8>> {
+        let path = format!("config/{}/{}", section, file);
+        let content = EMBEDDED_FILES.get(&path)
+            .ok_or_else(|| DotfilesError::ArchiveFileNotFound(path.clone()))?;
+            
+        Ok(content.to_vec())
+    }
+    
+    // Get the embedded distribution.toml
+    fn get_distribution() -> Result<String> {
+        Self::require_embedded_feature()?;
+        let content = EMBEDDED_FILES.get("distribution.toml")
+            .ok_or_else(|| DotfilesError::ArchiveFileNotFound("distribution.toml".to_string()))?;
+            
+        String::from_utf8(content.to_vec())
+            .map_err(|e| DotfilesError::ArchiveReadError(e.to_string()).into())
+    }
+    
+    // Get a distribution file included from distribution.toml, keyed by its path in the repo
+    fn get_include(path: &str) -> Result<String> {
+        let content = EMBEDDED_FILES.get(path)
+            .ok_or_else(|| DotfilesError::ArchiveFileNotFound(path.to_string()))?;
+            
+        String::from_utf8(content.to_vec())
+            .map_err(|e| DotfilesError::ArchiveReadError(e.to_string()).into())
+    }
+    
+    // Re-hash every embedded file and compare it to the checksum computed by build.rs
+    fn verify_integrity() -> Result<Vec<IntegrityError>> {
+        Self::require_embedded_feature()?;
+        let mut errors = Vec::new();
+        
+        for (path, expected) in EMBEDDED_CHECKSUMS {
+            let actual = match EMBEDDED_FILES.get(*path) {
+                Some(content) => sha256_hex(content),
+                None => "missing".to_string(),
+            };
+            if actual != *expected {
+                errors.push(IntegrityError {
+                    path: path.to_string(),
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+        }
+        
+        Ok(errors)
+    }
+    
+    // Get the embedded .dotignore
+    fn get_dotignore() -> Result<String> {
+        Self::require_embedded_feature()?;
+        let content = EMBEDDED_FILES.get(".dotignore")
+            .ok_or_else(|| DotfilesError::ArchiveFileNotFound(".dotignore".to_string()))?;
+            
+        String::from_utf8(content.to_vec())
+            .map_err(|e| DotfilesError::ArchiveReadError(e.to_string()).into())
+    }
+}
+
+// DotIgnore parser is now in the config module
+
+enum FileSource {
+    Filesystem,
+    Embedded,
+}
+
+// FileManager handles file operations
+struct FileManager<'a> {
+    paths: &'a FilePaths,
+    formatter: &'a mut Formatter,
+    dotignore: &'a DotIgnore,
+    // The tool's .toolignore, for callers working on a single tool
+    tool_ignore: Option<&'a DotIgnore>,
+    source: FileSource,
+    show_all: bool,
+    // (section, local file name) -> repo file name for renamed entries
+    renames: HashMap<(String, String), String>,
+    // Encrypted sections and the GPG key to encrypt their files for
+    encrypted: HashMap<String, Option<String>>,
+    templates: Templates,
+    retry_policy: RetryPolicy,
+    // Where successful operations are recorded, if anywhere
+    operation_log: Option<OperationLog>,
+    // Sync skips local files last modified before this date
+    since: Option<chrono::NaiveDate>,
+    symlinks: SymlinkPolicy,
+    // Asks before each file is written with --interactive
+    prompt: Option<&'a mut InteractivePrompt>,
+    // Install creates missing parent directories unless --no-create-dirs
+    create_dirs: bool,
+    // Where install copies local files before overwriting them
+    backup_dir: Option<&'a Path>,
+}
+
+// Per-file confirmation for --interactive
+#[derive(Debug, Default)]
+struct InteractivePrompt {
+    // Set once the user answers `a`
+    confirm_all: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PromptResponse {
+    Yes,
+    No,
+    All,
+    Quit,
+}
+
+impl InteractivePrompt {
+    // Ask whether to go ahead with one operation. After `a` every later
+    // question is answered without prompting
+    fn ask(&mut self, formatter: &mut Formatter, message: &str) -> Result<PromptResponse> {
+        if self.confirm_all {
+            return Ok(PromptResponse::All);
+        }
+        
+        loop {
+            formatter.print(&format!("{} [y]es / [n]o / [a]ll / [q]uit: ", message), Some(Color::Cyan), true)?;
+            formatter.stdout.flush()?;
+            
+            // Treat end of input as no
+            let answer = match std::io::stdin().lines().next() {
+                Some(line) => line?,
+                None => "n".to_string(),
+            };
+            
+            match answer.trim().to_lowercase().as_str() {
+                "y" => return Ok(PromptResponse::Yes),
+                "n" => return Ok(PromptResponse::No),
+                "a" => {
+                    self.confirm_all = true;
+                    return Ok(PromptResponse::All);
+                },
+                "q" => return Ok(PromptResponse::Quit),
+                _ => {},
+            }
+        }
+    }
+}
+
+// Whether install may write through symlinks. Local symlinks are often
+// managed by another tool such as Stow, and symlinks in the repository may
+// not resolve on other machines, so both are skipped by default
+#[derive(Debug, Clone, Copy, Default)]
+struct SymlinkPolicy {
+    overwrite_local: bool,
+    allow_in_repo: bool,
+    // symlink_mode: link installed files to the repository instead of copying them
+    link_installs: bool,
+}
+
+impl<'a> FileManager<'a> {
+    fn new(paths: &'a FilePaths, formatter: &'a mut Formatter, dotignore: &'a DotIgnore, show_all: bool) -> Self {
+        Self {
+            paths,
+            formatter,
+            dotignore,
+            tool_ignore: None,
+            source: FileSource::Filesystem,
+            show_all,
+            renames: HashMap::new(),
+            encrypted: HashMap::new(),
+            templates: Templates::default(),
+            retry_policy: RetryPolicy::default(),
+            operation_log: None,
+            since: None,
+            symlinks: SymlinkPolicy::default(),
+            prompt: None,
+            create_dirs: true,
+            backup_dir: None,
+        }
+    }
+    
+    fn from_embedded(paths: &'a FilePaths, formatter: &'a mut Formatter, dotignore: &'a DotIgnore, show_all: bool) -> Self {
+        Self {
+            paths,
+            formatter,
+            dotignore,
+            tool_ignore: None,
+            source: FileSource::Embedded,
+            show_all,
+            renames: HashMap::new(),
+            encrypted: HashMap::new(),
+            templates: Templates::default(),
+            retry_policy: RetryPolicy::default(),
+            operation_log: None,
+            since: None,
+            symlinks: SymlinkPolicy::default(),
+            prompt: None,
+            create_dirs: true,
+            backup_dir: None,
+        }
+    }
+    
+    fn with_tool_ignore(mut self, tool_ignore: &'a DotIgnore) -> Self {
+        self.tool_ignore = Some(tool_ignore);
+        self
+    }
+    
+    fn with_renames(mut self, section: &str, entries: &[FileEntry]) -> Self {
+        self.add_renames(section, entries);
+        self
+    }
+    
+    fn with_encryption(mut self, encrypted: &HashMap<String, Option<String>>) -> Self {
+        self.encrypted = encrypted.clone();
+        self
+    }
+    
+    fn with_templates(mut self, templates: &Templates) -> Self {
+        self.templates = templates.clone();
+        self
+    }
+    
+    fn with_since(mut self, since: Option<chrono::NaiveDate>) -> Self {
+        self.since = since;
+        self
+    }
+    
+    fn with_symlinks(mut self, symlinks: SymlinkPolicy) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+    
+    fn with_prompt(mut self, prompt: Option<&'a mut InteractivePrompt>) -> Self {
+        self.prompt = prompt;
+        self
+    }
+    
+    fn with_create_dirs(mut self, create_dirs: bool) -> Self {
+        self.create_dirs = create_dirs;
+        self
+    }
+    
+    fn with_backup_dir(mut self, backup_dir: Option<&'a Path>) -> Self {
+        self.backup_dir = backup_dir;
+        self
+    }
+    
+    // With --interactive, ask before `verb`ing a file, showing how much it
+    // changes. Quitting stops the whole command
+    fn confirm(&mut self, verb: &str, section: &str, file: &str) -> Result<bool> {
+        if self.prompt.is_none() {
+            return Ok(true);
+        }
+        
+        let summary = match self.diff_file(section, file)? {
+            FileDiff::Different { hunks } => {
+                let lines: usize = hunks.iter().map(|hunk| hunk.old_lines.len() + hunk.new_lines.len()).sum();
+                format!("{} changed {}", lines, if lines == 1 { "line" } else { "lines" })
+            },
+            FileDiff::Identical => "unchanged".to_string(),
+            FileDiff::OnlyInRepo | FileDiff::OnlyInConfig => "new file".to_string(),
+        };
+        let message = format!("{} {} ({})?", verb, self.display_path(section, file), summary);
+        
+        let Some(prompt) = self.prompt.as_deref_mut() else {
+            return Ok(true);
+        };
+        match prompt.ask(self.formatter, &message)? {
+            PromptResponse::Yes | PromptResponse::All => Ok(true),
+            PromptResponse::No => Ok(false),
+            PromptResponse::Quit => Err(DotfilesError::Aborted(format!("{} stopped by user", verb.to_lowercase())).into()),
+        }
+    }
+    
+    // Whether `path` was last modified before the start of `date`, local time
+    fn modified_before(path: &Path, date: chrono::NaiveDate) -> Result<bool> {
+        let start = date.and_time(chrono::NaiveTime::MIN)
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .map(std::time::SystemTime::from)
+            .ok_or_else(|| anyhow!("Invalid date: {}", date))?;
+        
+        Ok(fs::metadata(path)?.modified()? < start)
+    }
+    
+    fn with_operation_log(mut self, operation_log: &OperationLog) -> Self {
+        self.operation_log = Some(operation_log.clone());
+        self
+    }
+    
+    // Save `path` to the undo cache before it is overwritten; None when
+    // operations aren't logged
+    fn snapshot(&self, path: &Path) -> Result<Option<Snapshot>> {
+        match &self.operation_log {
+            Some(operation_log) if operation_log.is_enabled() => Ok(Some(operation_log.undo_cache().save_before(path)?)),
+            _ => Ok(None),
+        }
+    }
+    
+    // A failure to write the log is reported but doesn't fail the operation
+    fn log_operation(&mut self, command: &str, section: &str, file: &str, result: &str, before: Option<&Snapshot>) -> Result<()> {
+        let Some(operation_log) = &self.operation_log else {
+            return Ok(());
+        };
+        if let Err(e) = operation_log.append(command, section, file, result, before) {
+            self.formatter.warning(&format!("Failed to write {}: {}", operation_log.path().display(), e))?;
+        }
+        Ok(())
+    }
+    
+    fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+    
+    // Run a copy or write into `path`, retrying with backoff on failure
+    fn retry_io(&mut self, path: &Path, mut op: impl FnMut() -> Result<()>) -> Result<()> {
+        let formatter = &mut *self.formatter;
+        let mut attempt = 0;
+        
+        util::retry(self.retry_policy.max_attempts, self.retry_policy.delay, || {
+            attempt += 1;
+            if attempt > 1 {
+                formatter.warning(&format!("Retrying (attempt {}): {}", attempt, path.display()))?;
+            }
+            op()
+        })
+    }
+    
+    fn add_renames(&mut self, section: &str, entries: &[FileEntry]) {
+        for entry in entries {
+            if let FileEntry::Rename { from, to } = entry {
+                self.renames.insert((section.to_string(), to.clone()), from.clone());
+            }
+        }
+    }
+    
+    // Files are addressed by their local name; renamed entries keep a different
+    // name in the repo and files of encrypted sections get a .gpg extension
+    fn repo_name(&self, section: &str, file: &str) -> String {
+        let name = self.renames.get(&(section.to_string(), file.to_string())).map(String::as_str).unwrap_or(file);
+        if self.encrypted.contains_key(section) {
+            format!("{}.gpg", name)
+        } else {
+            name.to_string()
+        }
+    }
+    
+    fn repo_file(&self, section: &str, file: &str) -> PathBuf {
+        self.paths.repo_file_path(section, &self.repo_name(section, file))
+    }
+    
+    // Whether the repo copy of a file is byte for byte what install writes,
+    // i.e. the section is neither encrypted nor a template
+    fn stored_verbatim(&self, section: &str) -> bool {
+        !self.encrypted.contains_key(section) && !self.templates.tools.contains(section)
+    }
+    
+    // Decrypt the repo copy of a file in an encrypted section
+    fn decode_from_repo(&self, section: &str, content: Vec<u8>) -> Result<Vec<u8>> {
+        if self.encrypted.contains_key(section) {
+            gpg::decrypt(&content)
+        } else {
+            Ok(content)
+        }
+    }
+    
+    // Encrypt local content for the repo copy of an encrypted section
+    fn encode_for_repo(&self, section: &str, content: Vec<u8>) -> Result<Vec<u8>> {
+        match self.encrypted.get(section) {
+            Some(key_id) => gpg::encrypt(&content, key_id.as_deref()),
+            None => Ok(content),
+        }
+    }
+    
+    // The tool's .toolignore is evaluated after the repo .dotignore, so a
+    // matching tool rule (including a negation) decides the outcome
+    fn is_file_ignored(&self, file: &str) -> bool {
+        self.is_ignored_with(self.tool_ignore, file)
+    }
+    
+    fn is_ignored_with(&self, tool_ignore: Option<&DotIgnore>, file: &str) -> bool {
+        match tool_ignore.filter(|tool_ignore| tool_ignore.explain(file).is_some()) {
+            Some(tool_ignore) => tool_ignore.is_ignored(file),
+            None => self.dotignore.is_ignored(file),
+        }
+    }
+    
+    fn ignored_message(&self, file: &str, display_path: &str) -> String {
+        self.ignored_message_with(self.tool_ignore, file, display_path)
+    }
+    
+    fn ignored_message_with(&self, tool_ignore: Option<&DotIgnore>, file: &str, display_path: &str) -> String {
+        match tool_ignore.and_then(|tool_ignore| tool_ignore.explain(file)) {
+            Some(pattern) => format!("Ignored by .toolignore: {} (matched pattern: `{}`)", display_path, pattern),
+            None => format!(
+                "Ignored by .dotignore: {} (matched pattern: `{}`)",
+                display_path,
+                self.dotignore.explain(file).unwrap_or_default()
+            ),
+        }
+    }
+    
+    // Short tool/file form by default; -v shows the absolute paths instead and
+    // -vv adds the size and checksum of each copy
+    fn display_path(&self, section: &str, file: &str) -> String {
+        let verbosity = self.formatter.verbosity();
+        if verbosity == 0 {
+            return format!("{}/{}", section, file);
+        }
+        
+        let config_file = self.paths.config_file_path(section, file);
+        let repo_file = match self.source {
+            FileSource::Filesystem => self.repo_file(section, file).display().to_string(),
+            FileSource::Embedded => format!("embedded:config/{}/{}", section, self.repo_name(section, file)),
+        };
+        
+        let mut display = format!("{} (repo: {})", config_file.display(), repo_file);
+        if verbosity >= 2 {
+            let local = fs::read(&config_file).ok();
+            let source = self.read_source(section, file).ok();
+            for (label, content) in [("local", local), ("repo", source)] {
+                if let Some(content) = content {
+                    display.push_str(&format!(" [{}: {} bytes, sha256 {}]", label, content.len(), sha256_hex(&content)));
+                }
+            }
+        }
+        
+        display
+    }
+    
+    // The content install writes: the repo or embedded copy, decrypted and
+    // rendered for template sections
+    fn read_source(&self, section: &str, file: &str) -> Result<Vec<u8>> {
+        let content = match self.source {
+            FileSource::Filesystem => fs::read(self.repo_file(section, file))?,
+            FileSource::Embedded => DotfilesArchive::get_file(section, &self.repo_name(section, file))?,
+        };
+        let content = self.decode_from_repo(section, content)?;
+        if self.templates.tools.contains(section) {
+            self.render_template(section, file, content)
+        } else {
+            Ok(content)
+        }
+    }
+    
+    // Render a template with the tool's variables.toml and the --variables
+    // overrides. Undefined variables are errors rather than empty strings
+    fn render_template(&self, section: &str, file: &str, content: Vec<u8>) -> Result<Vec<u8>> {
+        let template = String::from_utf8(content)
+            .map_err(|_| DotfilesError::InvalidCommand(format!("Template is not UTF-8: {}/{}", section, file)))?;
+        
+        let mut env = minijinja::Environment::new();
+        env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+        env.set_keep_trailing_newline(true);
+        let rendered = env.render_str(&template, self.template_variables(section)?)
+            .map_err(|e| DotfilesError::InvalidCommand(format!("Cannot render template {}/{}: {}", section, file, e)))?;
+        
+        Ok(rendered.into_bytes())
+    }
+    
+    fn template_variables(&self, section: &str) -> Result<toml::Table> {
+        let content = match self.source {
+            FileSource::Filesystem => {
+                let path = self.paths.repo_config_dir(section).join(TEMPLATE_VARIABLES_FILE);
+                if path.exists() { Some(fs::read(path)?) } else { None }
+            },
+            FileSource::Embedded if DotfilesArchive::file_exists(section, TEMPLATE_VARIABLES_FILE) => {
+                Some(DotfilesArchive::get_file(section, TEMPLATE_VARIABLES_FILE)?)
+            },
+            FileSource::Embedded => None,
+        };
+        
+        let mut variables = match content {
+            Some(content) => toml::from_str(&String::from_utf8_lossy(&content))
+                .map_err(|e| DotfilesError::InvalidCommand(format!("Invalid {}/{}: {}", section, TEMPLATE_VARIABLES_FILE, e)))?,
+            None => toml::Table::new(),
+        };
+        for (key, value) in &self.templates.overrides {
+            variables.insert(key.clone(), toml::Value::String(value.clone()));
+        }
+        
+        Ok(variables)
+    }
+    
+    // Decide whether an existing local file may be replaced during install
+    fn resolve_conflict(&mut self, section: &str, file: &str, conflict: ConflictStrategy) -> Result<bool> {
+        let config_file = self.paths.install_file_path(section, file);
+        let display_path = self.display_path(section, file);
+        
+        match conflict {
+            ConflictStrategy::Overwrite => Ok(true),
+            ConflictStrategy::Skip => {
+                let local_is_older = fs::metadata(&config_file)?.modified()? < self.source_mtime(section, file)?;
+                
+                if !local_is_older {
+                    self.formatter.verbose("Local file is not older than the source file")?;
+                    self.formatter.warning(&format!("Skipping (local is newer): {}", display_path))?;
+                }
+                Ok(local_is_older)
+            },
+            ConflictStrategy::Ask => {
+                let hunks = match self.diff_file(section, file)? {
+                    FileDiff::Different { hunks } => hunks,
+                    _ => return Ok(true),
+                };
+                
+                self.formatter.modified(&format!("Conflict: {}", display_path))?;
+                self.print_hunks(&hunks, Some(1))?;
+                
+                loop {
+                    self.formatter.print("[o]verwrite / [s]kip / [d]iff / [q]uit: ", Some(Color::Cyan), true)?;
+                    self.formatter.stdout.flush()?;
+                    
+                    // Treat end of input as skip
+                    let answer = match std::io::stdin().lines().next() {
+                        Some(line) => line?,
+                        None => "s".to_string(),
+                    };
+                    
+                    match answer.trim().to_lowercase().as_str() {
+                        "o" => return Ok(true),
+                        "s" => {
+                            self.formatter.warning(&format!("Skipped: {}", display_path))?;
+                            return Ok(false);
+                        },
+                        "d" => self.print_hunks(&hunks, None)?,
+                        "q" => return Err(DotfilesError::Aborted("install stopped by user".to_string()).into()),
+                        _ => {},
+                    }
+                }
+            },
+            ConflictStrategy::Error => Err(DotfilesError::ConflictDetected {
+                tool: section.to_string(),
+                file: file.to_string(),
+                repo_mtime: self.source_mtime(section, file)?,
+                config_mtime: fs::metadata(&config_file)?.modified()?,
+            }.into()),
+        }
+    }
+    
+    // When the source copy of a file last changed. Embedded files are as old
+    // as the binary that carries them
+    fn source_mtime(&self, section: &str, file: &str) -> Result<SystemTime> {
+        let path = match self.source {
+            FileSource::Filesystem => self.repo_file(section, file),
+            FileSource::Embedded => std::env::current_exe()?,
+        };
+        Ok(fs::metadata(path)?.modified()?)
+    }
+    
+    // Whether the repo, or the embedded archive, has the file
+    fn in_repo(&self, section: &str, file: &str) -> bool {
+        match self.source {
+            FileSource::Filesystem => self.repo_file(section, file).exists(),
+            FileSource::Embedded => DotfilesArchive::file_exists(section, &self.repo_name(section, file)),
+        }
+    }
+    
+    // Compare the repo (old) and local (new) copies of a file line by line
+    fn diff_file(&self, section: &str, file: &str) -> Result<FileDiff> {
+        let config_file = self.paths.config_file_path(section, file);
+        
+        match (self.in_repo(section, file), config_file.exists()) {
+            (false, false) => Err(DotfilesError::FileNotFound(format!("{}/{}", section, file)).into()),
+            (false, true) => Ok(FileDiff::OnlyInConfig),
+            (true, false) => Ok(FileDiff::OnlyInRepo),
+            (true, true) => {
+                let source_content = self.read_source(section, file)?;
+                let config_content = fs::read(&config_file)?;
+                if source_content == config_content {
+                    return Ok(FileDiff::Identical);
+                }
+                
+                Ok(FileDiff::Different { hunks: Self::hunks(&source_content, &config_content) })
+            },
+        }
+    }
+    
+    // The changes from `old` to `new`; each group of changes without context
+    // lines becomes one hunk
+    fn hunks(old: &[u8], new: &[u8]) -> Vec<DiffHunk> {
+        let old_text = String::from_utf8_lossy(old);
+        let new_text = String::from_utf8_lossy(new);
+        let diff = TextDiff::from_lines(old_text.as_ref(), new_text.as_ref());
+        
+        diff.grouped_ops(0).iter().map(|group| {
+            let mut hunk = DiffHunk {
+                old_start: group[0].old_range().start + 1,
+                new_start: group[0].new_range().start + 1,
+                old_lines: Vec::new(),
+                new_lines: Vec::new(),
+            };
+            for op in group {
+                for change in diff.iter_changes(op) {
+                    let line = change.value().trim_end_matches(['\r', '\n']).to_string();
+                    match change.tag() {
+                        ChangeTag::Delete => hunk.old_lines.push(line),
+                        ChangeTag::Insert => hunk.new_lines.push(line),
+                        ChangeTag::Equal => {},
+                    }
+                }
+            }
+            hunk
+        }).collect()
+    }
+    
+    // Copy the installed form of a file to the same place on `host`. With
+    // `diff_first` the copy on the host is shown as - lines against the
+    // pushed content as + lines, and an identical file isn't pushed.
+    // Returns whether the file was (or, with `dry_run`, would be) pushed
+    fn push_file(&mut self, host: &SshHost, section: &str, file: &str, dry_run: bool, diff_first: bool) -> Result<bool> {
+        let display_path = self.display_path(section, file);
+        if self.is_file_ignored(file) {
+            self.formatter.verbose(&format!("Skipping ignored file: {}", display_path))?;
+            return Ok(false);
+        }
+        if !self.in_repo(section, file) {
+            self.formatter.warning(&format!("Skipping (missing from repository): {}", display_path))?;
+            return Ok(false);
+        }
+        
+        let content = self.read_source(section, file)?;
+        let remote_path = self.paths.home_relative(&self.paths.install_file_path(section, file));
+        self.formatter.info(&format!("Pushing to {}: {}", host.host, display_path))?;
+        
+        if diff_first {
+            let output = host.ssh(&format!("cat {}", util::shell_quote(&remote_path.to_string_lossy()))).output()?;
+            if !output.status.success() {
+                self.formatter.not_installed(&format!("Not on host yet: {}", display_path))?;
+            } else if output.stdout == content {
+                self.formatter.identical(&format!("Identical on host: {}", display_path))?;
+                return Ok(false);
+            } else {
+                self.formatter.modified(&format!("Differs on host: {}", display_path))?;
+                self.print_hunks(&Self::hunks(&output.stdout, &content), None)?;
+            }
+        }
+        
+        if dry_run {
+            self.formatter.action(&format!("Would push: {}", display_path))?;
+            return Ok(true);
+        }
+        
+        // Plain repo copies are sent as they are with scp. Decrypted or
+        // rendered content is streamed into `cat` on the host so it never
+        // touches the local disk
+        let verbatim = matches!(self.source, FileSource::Filesystem) && self.stored_verbatim(section);
+        let output = if verbatim {
+            host.scp().arg(self.repo_file(section, file)).arg(host.remote(&remote_path)).output()?
+        } else {
+            let mut child = host.ssh(&format!("cat > {}", util::shell_quote(&remote_path.to_string_lossy())))
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .spawn()?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(&content)?;
+            }
+            child.wait_with_output()?
+        };
+        
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("{}", stderr.lines().last().map(str::to_string)
+                .unwrap_or_else(|| format!("{} exited with {}", if verbatim { "scp" } else { "ssh" }, output.status))));
+        }
+        self.formatter.success(&format!("Pushed: {}", display_path))?;
+        Ok(true)
+    }
+    
+    // Count the lines local (+) adds and removes relative to the repo without
+    // building hunks. None when the file is ignored, identical or only on one side
+    fn diff_stat(&self, section: &str, file: &str) -> Result<Option<DiffStat>> {
+        let config_file = self.paths.config_file_path(section, file);
+        if self.is_file_ignored(file) || !self.in_repo(section, file) || !config_file.exists() {
+            return Ok(None);
+        }
+        
+        let source_content = self.read_source(section, file)?;
+        let config_content = fs::read(&config_file)?;
+        if source_content == config_content {
+            return Ok(None);
+        }
+        Ok(Some(Self::line_stat(&source_content, &config_content)))
+    }
+    
+    // The lines `new` adds and removes relative to `old`
+    fn line_stat(old: &[u8], new: &[u8]) -> DiffStat {
+        let (Ok(old_text), Ok(new_text)) = (std::str::from_utf8(old), std::str::from_utf8(new)) else {
+            return DiffStat::Binary;
+        };
+        let (mut added, mut removed) = (0, 0);
+        for op in TextDiff::from_lines(old_text, new_text).ops() {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            match tag {
+                DiffTag::Delete => removed += old_range.len(),
+                DiffTag::Insert => added += new_range.len(),
+                DiffTag::Replace => {
+                    removed += old_range.len();
+                    added += new_range.len();
+                },
+                DiffTag::Equal => {},
+            }
+        }
+        
+        DiffStat::Lines { added, removed }
+    }
+    
+    // Print hunks as repo (-) / local (+) lines, up to `limit` hunks
+    fn print_hunks(&mut self, hunks: &[DiffHunk], limit: Option<usize>) -> Result<()> {
+        for hunk in hunks.iter().take(limit.unwrap_or(usize::MAX)) {
+            self.formatter.print(
+                &format!("  @@ -{},{} +{},{} @@", hunk.old_start, hunk.old_lines.len(), hunk.new_start, hunk.new_lines.len()),
+                Some(Color::Cyan),
+                false,
+            )?;
+            self.formatter.newline()?;
+            
+            for line in &hunk.old_lines {
+                self.formatter.print(&format!("  - {}", line), Some(Color::Red), false)?;
+                self.formatter.newline()?;
+            }
+            for line in &hunk.new_lines {
+                self.formatter.print(&format!("  + {}", line), Some(Color::Green), false)?;
+                self.formatter.newline()?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    fn show_diff(&mut self, section: &str, file: &str) -> Result<()> {
+        let display_path = self.display_path(section, file);
+        
+        if self.is_file_ignored(file) {
+            self.formatter.verbose(&format!("Skipping ignored file: {}", display_path))?;
+            return Ok(());
+        }
+        
+        match self.diff_file(section, file)? {
+            FileDiff::Identical => {
+                if self.show_all {
+                    self.formatter.identical(&format!("Identical: {}", display_path))?;
+                }
+            },
+            FileDiff::OnlyInRepo => self.formatter.not_installed(&format!("Not installed: {}", display_path))?,
+            FileDiff::OnlyInConfig => self.formatter.error(&format!("Missing in source: {}", display_path))?,
+            FileDiff::Different { hunks } => {
+                self.formatter.modified(&format!("Modified locally: {}", display_path))?;
+                self.print_hunks(&hunks, None)?;
+            },
+        }
+        
+        Ok(())
+    }
+    
+    // Returns whether the file was written
+    fn install_file(&mut self, section: &str, file: &str, conflict: ConflictStrategy) -> Result<bool> {
+        let config_file = self.paths.install_file_path(section, file);
+        let display_path = self.display_path(section, file);
+        
+        self.formatter.verbose(&format!("Processing file: {}", display_path))?;
+        self.formatter.verbose(&format!("Target path: {}", config_file.display()))?;
+        
+        if self.is_file_ignored(file) {
+            let message = self.ignored_message(file, &display_path);
+            self.formatter.verbose("File matched ignore pattern")?;
+            self.formatter.warning(&message)?;
+            return Ok(false);
+        }
+        
+        let file_exists = match self.source {
+            FileSource::Filesystem => {
+                let repo_file = self.repo_file(section, file);
+                self.formatter.verbose(&format!("Checking source file: {}", repo_file.display()))?;
+                repo_file.exists()
+            },
+            FileSource::Embedded => {
+                self.formatter.verbose(&format!("Checking embedded file: config/{}/{}", section, self.repo_name(section, file)))?;
+                DotfilesArchive::file_exists(section, &self.repo_name(section, file))
+            },
+        };
+        
+        if file_exists {
+            if matches!(self.source, FileSource::Filesystem) && !self.symlinks.allow_in_repo
+                && self.repo_file(section, file).is_symlink() {
+                self.formatter.warning(&format!("Skipping (symlink in repository, may not be portable): {}", display_path))?;
+                self.log_operation("install", section, file, "skipped-repo-symlink", None)?;
+                return Ok(false);
+            }
+            // Symlink mode links verbatim repo files; encrypted and template
+            // files differ from their repo copy, so they are still written
+            let link = self.symlinks.link_installs && matches!(self.source, FileSource::Filesystem) && self.stored_verbatim(section);
+            if config_file.is_symlink() && !self.symlinks.overwrite_local && !(link && self.links_to_repo(section, file)) {
+                self.formatter.warning(&format!("Skipping (is a symlink): {}", display_path))?;
+                self.log_operation("install", section, file, "skipped-symlink", None)?;
+                return Ok(false);
+            }
+            if !self.create_dirs && config_file.parent().is_some_and(|parent| !parent.is_dir()) {
+                self.formatter.warning(&format!("Parent directory missing, skipping: {}", display_path))?;
+                self.log_operation("install", section, file, "skipped-missing-dir", None)?;
+                return Ok(false);
+            }
+            
+            // An identical copy is still replaced by a link in symlink mode
+            let unchanged = config_file.exists() && fs::read(&config_file)? == self.read_source(section, file)?;
+            if unchanged && (!link || config_file.is_symlink()) {
+                if self.formatter.verbosity() > 0 {
+                    self.formatter.identical(&format!("Skipping unchanged file: {}", display_path))?;
+                }
+                return Ok(false);
+            }
+            
+            if config_file.exists() && !unchanged && !self.resolve_conflict(section, file, conflict)? {
+                return Ok(false);
+            }
+            
+            if !self.confirm("Install", section, file)? {
+                self.formatter.warning(&format!("Skipped: {}", display_path))?;
+                return Ok(false);
+            }
+            
+            if let Some(parent) = config_file.parent() {
+                self.formatter.verbose(&format!("Creating parent directory: {}", parent.display()))?;
+                create_dir_all(parent)?;
+            }
+            
+            // The live copy of an encrypted file is plaintext, which mustn't
+            // end up in the repository's undo cache
+            let before = match self.encrypted.contains_key(section) {
+                true => None,
+                false => self.snapshot(&config_file)?,
+            };
+            if config_file.exists() && !unchanged {
+                self.back_up(section, file, &config_file)?;
+            }
+            // Replace the link itself rather than writing through it
+            if config_file.is_symlink() {
+                self.formatter.verbose(&format!("Removing symlink: {}", config_file.display()))?;
+                fs::remove_file(&config_file)?;
+            }
+            match self.source {
+                FileSource::Filesystem if link => {
+                    let repo_file = self.repo_file(section, file);
+                    self.formatter.verbose(&format!("Linking to: {}", repo_file.display()))?;
+                    if config_file.exists() {
+                        fs::remove_file(&config_file)?;
+                    }
+                    util::symlink(&repo_file, &config_file)?;
+                },
+                FileSource::Filesystem if self.stored_verbatim(section) => {
+                    let repo_file = self.repo_file(section, file);
+                    self.formatter.verbose(&format!("Copying from: {} to: {}", repo_file.display(), config_file.display()))?;
+                    self.retry_io(&config_file, || {
+                        fs::copy(&repo_file, &config_file)?;
+                        Ok(())
+                    })?;
+                },
+                _ => {
+                    self.formatter.verbose(&format!("Extracting file to: {}", config_file.display()))?;
+                    let content = self.read_source(section, file)?;
+                    self.retry_io(&config_file, || {
+                        fs::write(&config_file, &content)?;
+                        Ok(())
+                    })?;
+                },
+            }
+            
+            self.formatter.installed(&format!("Installed to local: {}", display_path))?;
+            self.log_operation("install", section, file, "installed", before.as_ref())?;
+            Ok(true)
+        } else {
+            self.formatter.verbose("Source file does not exist")?;
+            self.formatter.warning(&format!("File not found: {}", display_path))?;
+            Ok(false)
+        }
+    }
+    
+    // Whether the local file is a symlink to its repo copy
+    fn links_to_repo(&self, section: &str, file: &str) -> bool {
+        let config_file = self.paths.install_file_path(section, file);
+        let repo_file = self.repo_file(section, file);
+        config_file.is_symlink() && config_file.canonicalize().ok() == repo_file.canonicalize().ok()
+    }
+    
+    // Copy a local file about to be overwritten to <backup_dir>/<section>/<file>
+    fn back_up(&mut self, section: &str, file: &str, config_file: &Path) -> Result<()> {
+        // Like the undo cache, backups mustn't hold the plaintext of encrypted files
+        let Some(backup_dir) = self.backup_dir.filter(|_| !self.encrypted.contains_key(section)) else {
+            return Ok(());
+        };
+        let backup = backup_dir.join(section).join(file);
+        if let Some(parent) = backup.parent() {
+            create_dir_all(parent)?;
+        }
+        fs::copy(config_file, &backup)?;
+        self.formatter.verbose(&format!("Backed up {} to {}", config_file.display(), backup.display()))?;
+        Ok(())
+    }
+    
+    // Install repo/<section>/<from> as config/<section>/<to>
+    fn install_renamed(&mut self, section: &str, from: &str, to: &str, conflict: ConflictStrategy) -> Result<bool> {
+        self.renames.insert((section.to_string(), to.to_string()), from.to_string());
+        self.install_file(section, to, conflict)
+    }
+    
+    // Sync the files of each tool back to the repo, loading each tool's
+    // .toolignore, and return what happened to every file. Each result is
+    // printed as it happens; failures are left to the caller to report. With
+    // `fail_fast` nothing after the first failure is synced
+    fn sync_all(&mut self, tools: &[(String, Vec<FileEntry>)], fail_fast: bool) -> Result<Vec<SyncResult>> {
+        let mut results = Vec::new();
+        
+        for (tool, entries) in tools {
+            self.formatter.info(&format!("Processing tool: {}", tool))?;
+            self.add_renames(tool, entries);
+            let tool_ignore = match DotIgnore::for_tool(&self.paths.repo_config_dir(tool)) {
+                Ok(tool_ignore) => tool_ignore,
+                Err(e) => {
+                    results.push(SyncResult::Failed { tool: tool.clone(), file: String::new(), error: e.to_string() });
+                    if fail_fast {
+                        return Ok(results);
+                    }
+                    continue;
+                },
+            };
+            
+            for entry in entries {
+                let file = entry.destination();
+                self.formatter.progress_message(format!("Syncing {}/{}", tool, file));
+                let synced = self.sync_entry(tool, file, &tool_ignore);
+                let (result, aborted) = match synced {
+                    Ok(result) => (result, false),
+                    Err(e) => {
+                        let aborted = matches!(e.downcast_ref::<DotfilesError>(), Some(DotfilesError::Aborted(_)));
+                        (SyncResult::Failed { tool: tool.clone(), file: file.to_string(), error: e.to_string() }, aborted)
+                    },
+                };
+                self.report_sync(&result)?;
+                self.formatter.progress_inc();
+                
+                let failed = result.is_failure();
+                results.push(result);
+                if aborted || (failed && fail_fast) {
+                    return Ok(results);
+                }
+            }
+        }
+        
+        Ok(results)
+    }
+    
+    // Print what sync did with one file, above the progress bar when it is up
+    fn report_sync(&mut self, result: &SyncResult) -> Result<()> {
+        let display_path = match result {
+            SyncResult::Synced { tool, file } | SyncResult::Skipped { tool, file, .. } => self.display_path(tool, file),
+            SyncResult::Failed { .. } => return Ok(()),
+        };
+        match result {
+            SyncResult::Synced { .. } => self.formatter.synced(&format!("Synced to repo: {}", display_path))?,
+            SyncResult::Skipped { reason: SkipReason::Ignored(message), .. } => self.formatter.warning(message)?,
+            SyncResult::Skipped { reason: SkipReason::NotInstalled, .. } => {
+                self.formatter.warning(&format!("Local file not found: {}", display_path))?
+            },
+            SyncResult::Skipped { reason: SkipReason::NotModifiedSince(since), .. } => {
+                self.formatter.info(&format!("Skipping (unchanged since {}): {}", since, display_path))?
+            },
+            SyncResult::Skipped { reason: SkipReason::Declined, .. } => {
+                self.formatter.warning(&format!("Skipped: {}", display_path))?
+            },
+            SyncResult::Skipped { reason: SkipReason::Template, .. } => {
+                self.formatter.warning(&format!("Skipping (rendered from a template, edit it in the repo): {}", display_path))?
+            },
+            SyncResult::Skipped { reason: SkipReason::Unchanged, .. } => {
+                if self.formatter.verbosity() > 0 {
+                    self.formatter.identical(&format!("Skipping unchanged file: {}", display_path))?;
+                }
+            },
+            SyncResult::Failed { .. } => {},
+        }
+        Ok(())
+    }
+    
+    // Copy a single local file to the repo unless it is ignored, unchanged or missing
+    fn sync_entry(&mut self, section: &str, file: &str, tool_ignore: &DotIgnore) -> Result<SyncResult> {
+        let repo_file = self.repo_file(section, file);
+        let config_file = self.paths.config_file_path(section, file);
+        let skipped = |reason| SyncResult::Skipped { tool: section.to_string(), file: file.to_string(), reason };
+        
+        self.formatter.verbose(&format!("Local path: {}", config_file.display()))?;
+        self.formatter.verbose(&format!("Repo path: {}", repo_file.display()))?;
+        
+        if self.is_ignored_with(Some(tool_ignore), file) {
+            let display_path = self.display_path(section, file);
+            return Ok(skipped(SkipReason::Ignored(self.ignored_message_with(Some(tool_ignore), file, &display_path))));
+        }
+        
+        if !config_file.exists() {
+            return Ok(skipped(SkipReason::NotInstalled));
+        }
+        
+        if let Some(since) = self.since {
+            if Self::modified_before(&config_file, since)? {
+                return Ok(skipped(SkipReason::NotModifiedSince(since)));
+            }
+        }
+        
+        let content = fs::read(&config_file)?;
+        
+        // The local file of a template section is rendered output; syncing it
+        // would replace the template, which is the copy to edit
+        if self.templates.tools.contains(section) {
+            let unchanged = repo_file.exists() && self.read_source(section, file)? == content;
+            return Ok(skipped(if unchanged { SkipReason::Unchanged } else { SkipReason::Template }));
+        }
+        
+        if repo_file.exists() && self.decode_from_repo(section, fs::read(&repo_file)?)? == content {
+            return Ok(skipped(SkipReason::Unchanged));
+        }
+        
+        if !self.confirm("Sync", section, file)? {
+            return Ok(skipped(SkipReason::Declined));
+        }
+        
+        if let Some(parent) = repo_file.parent() {
+            create_dir_all(parent)?;
+        }
+        let content = self.encode_for_repo(section, content)?;
+        let before = self.snapshot(&repo_file)?;
+        self.retry_io(&repo_file, || {
+            fs::write(&repo_file, &content)?;
+            Ok(())
+        })?;
+        self.log_operation("sync", section, file, "synced", before.as_ref())?;
+        
+        Ok(SyncResult::Synced { tool: section.to_string(), file: file.to_string() })
+    }
+    
+    // Print the status of a file that file_status determined
+    fn print_status(&mut self, section: &str, file: &str, outcome: StatusOutcome) -> Result<()> {
+        let config_file = self.paths.config_file_path(section, file);
+        let display_path = self.display_path(section, file);
+        
+        self.formatter.verbose(&format!("Checking status of file: {}", display_path))?;
+        self.formatter.verbose(&format!("Local path: {}", config_file.display()))?;
+        
+        match outcome {
+            StatusOutcome::Ignored => {
+                let message = self.ignored_message(file, &display_path);
+                self.formatter.verbose("File matched ignore pattern")?;
+                self.formatter.warning(&message)?;
+            },
+            StatusOutcome::MissingInRepo => {
+                self.formatter.verbose("File does not exist in source")?;
+                self.formatter.error(&format!("Missing in source: {}", display_path))?;
+            },
+            StatusOutcome::NotInstalled => {
+                self.formatter.verbose("File does not exist in local config")?;
+                self.formatter.not_installed(&format!("Not installed: {}", display_path))?;
+            },
+            StatusOutcome::Identical => {
+                self.formatter.verbose("Files are identical")?;
+                
+                // Only show identical files if show_all is true
+                if self.show_all {
+                    self.formatter.identical(&format!("Identical: {}", display_path))?;
+                }
+            },
+            StatusOutcome::Modified => {
+                self.formatter.verbose("Files have been modified locally")?;
+                self.formatter.modified(&format!("Modified locally: {}", display_path))?;
+            },
+        }
+        
+        Ok(())
+    }
+    
+    // Determine the status of a file without producing any output
+    fn file_status(&self, section: &str, file: &str) -> Result<StatusOutcome> {
+        if self.is_file_ignored(file) {
+            return Ok(StatusOutcome::Ignored);
+        }
+        
+        let file_exists = match self.source {
+            FileSource::Filesystem => self.repo_file(section, file).exists(),
+            FileSource::Embedded => DotfilesArchive::file_exists(section, &self.repo_name(section, file)),
+        };
+        if !file_exists {
+            return Ok(StatusOutcome::MissingInRepo);
+        }
+        
+        let config_file = self.paths.config_file_path(section, file);
+        if !config_file.exists() {
+            return Ok(StatusOutcome::NotInstalled);
+        }
+        
+        // Embedded and encrypted sources are only available in memory
+        let source_hash = match self.source {
+            FileSource::Filesystem if self.stored_verbatim(section) => hash_file(&self.repo_file(section, file))?,
+            _ => sha256_hex(&self.read_source(section, file)?),
+        };
+        if source_hash == hash_file(&config_file)? {
+            Ok(StatusOutcome::Identical)
+        } else {
+            Ok(StatusOutcome::Modified)
+        }
+    }
+    
+    fn add_file(&mut self, section: &str, file: &str, metadata: &SectionMetadata) -> Result<()> {
+        let source_dir = self.paths.config_section_dir(section);
+        let dest_dir = self.paths.repo_config_dir(section);
+        let source_file = source_dir.join(file);
+        let dest_file = dest_dir.join(self.repo_name(section, file));
+        let display_path = self.display_path(section, file);
+        
+        if !source_file.exists() {
+            return Err(DotfilesError::FileNotFound(source_file.to_string_lossy().to_string()).into());
+        }
+        
+        // Create destination directory if needed
+        if let Some(parent) = dest_file.parent() {
+            create_dir_all(parent)?;
+        }
+        
+        // Add file to distribution.toml
+        let parser = DistributionParser::new(self.paths.distribution_file.clone())
+            .with_retry_policy(self.retry_policy);
+        parser.add_file(section, file, metadata)?;
+        
+        // Copy file to repo
+        let content = self.encode_for_repo(section, fs::read(&source_file)?)?;
+        let before = self.snapshot(&dest_file)?;
+        self.retry_io(&dest_file, || {
+            fs::write(&dest_file, &content)?;
+            Ok(())
+        })?;
+        self.formatter.tracking(&format!("Added to tracking: {}", display_path))?;
+        self.log_operation("add", section, file, "added", before.as_ref())?;
+        
+        Ok(())
+    }
+    
+    fn remove_file(&mut self, section: &str, file: &str) -> Result<()> {
+        let repo_file = self.repo_file(section, file);
+        let display_path = self.display_path(section, file);
+        
+        // Remove file from distribution.toml
+        let parser = DistributionParser::new(self.paths.distribution_file.clone())
+            .with_retry_policy(self.retry_policy);
+        let remaining = parser.remove_file(section, file)?;
+        
+        self.formatter.info(&format!("Removed from distribution file: {}", display_path))?;
+        if remaining > 0 {
+            self.formatter.warning(&format!(
+                "{} is listed {} more time(s), only the first entry was removed (run 'dotfiles-rs +deduplicate' to clean up)",
+                display_path, remaining))?;
+        }
+        self.log_operation("remove", section, file, "removed", None)?;
+        
+        // Inform user to remove the file manually
+        if repo_file.exists() {
+            self.formatter.warning(&format!(
+                "To complete removal, manually delete the file: {}",
+                repo_file.display()
+            ))?;
+            self.formatter.print("   ", Some(Color::Cyan), false)?;
+            self.formatter.print(
+                &format!("rm {}", repo_file.display()),
+                Some(Color::Cyan),
+                false,
+            )?;
+            self.formatter.newline()?;
+        }
+        
+        Ok(())
+    }
+    
+    // Move a file's distribution entry and its repo copy to another tool. The
+    // repo file is moved first and put back if the distribution can't be written
+    fn move_file(&mut self, from_tool: &str, file: &str, to_tool: &str, new_file: &str) -> Result<()> {
+        let old_path = self.repo_file(from_tool, file);
+        let new_path = self.paths.repo_file_path(to_tool, new_file);
+        
+        if !old_path.exists() {
+            return Err(DotfilesError::FileNotFound(old_path.to_string_lossy().to_string()).into());
+        }
+        if new_path.exists() {
+            return Err(DotfilesError::InvalidCommand(format!("{} already exists", new_path.display())).into());
+        }
+        
+        if let Some(parent) = new_path.parent() {
+            self.formatter.verbose(&format!("Creating parent directory: {}", parent.display()))?;
+            create_dir_all(parent)?;
+        }
+        fs::rename(&old_path, &new_path)?;
+        
+        let parser = DistributionParser::new(self.paths.distribution_file.clone())
+            .with_retry_policy(self.retry_policy);
+        if let Err(e) = parser.move_file(from_tool, file, to_tool, new_file) {
+            fs::rename(&new_path, &old_path)?;
+            return Err(e);
+        }
+        
+        self.formatter.action(&format!("Moved: {}/{} {} {}/{}", from_tool, file, ARROW_MARK, to_tool, new_file))?;
+        Ok(())
+    }
+    
+    // Write the repo version of a file to `destination`, which isn't tracked.
+    // An existing destination is left alone unless `force` is set
+    fn copy_to(&mut self, section: &str, file: &str, destination: &Path, force: bool) -> Result<bool> {
+        let display_path = self.display_path(section, file);
+        let in_repo = match self.source {
+            FileSource::Filesystem => self.repo_file(section, file).exists(),
+            FileSource::Embedded => DotfilesArchive::file_exists(section, &self.repo_name(section, file)),
+        };
+        if !in_repo {
+            return Err(DotfilesError::FileNotFound(format!("{} is missing from the repository", display_path)).into());
+        }
+        
+        if destination.exists() && !force {
+            self.formatter.warning(&format!("Skipping (destination exists, use --force to overwrite): {}", destination.display()))?;
+            return Ok(false);
+        }
+        
+        if let Some(parent) = destination.parent() {
+            self.formatter.verbose(&format!("Creating parent directory: {}", parent.display()))?;
+            create_dir_all(parent)?;
+        }
+        
+        let content = self.read_source(section, file)?;
+        self.retry_io(destination, || {
+            fs::write(destination, &content)?;
+            Ok(())
+        })?;
+        
+        self.formatter.success(&format!("Copied {}/{} {} {}", section, file, ARROW_MARK, destination.display()))?;
+        Ok(true)
+    }
+}
+
+// Line-level comparison of the repo and local copies of a file
+#[derive(Debug)]
+enum FileDiff {
+    Identical,
+    OnlyInRepo,
+    OnlyInConfig,
+    Different { hunks: Vec<DiffHunk> },
+}
+
+// Changed line counts of a file for diff --stat
+enum DiffStat {
+    Lines { added: usize, removed: usize },
+    Binary,
+}
+
+// One changed region, with 1-based start lines as in unified diff output
+#[derive(Debug)]
+struct DiffHunk {
+    old_start: usize,
+    new_start: usize,
+    old_lines: Vec<String>,
+    new_lines: Vec<String>,
+}
+
+// What sync did with a single file
+#[derive(Debug)]
+enum SyncResult {
+    Synced { tool: String, file: String },
+    Skipped { tool: String, file: String, reason: SkipReason },
+    Failed { tool: String, file: String, error: String },
+}
+
+#[derive(Debug)]
+enum SkipReason {
+    // Holds the message naming the matching pattern
+    Ignored(String),
+    Unchanged,
+    NotInstalled,
+    // Not modified since the --since date
+    NotModifiedSince(chrono::NaiveDate),
+    // Answered no with --interactive
+    Declined,
+    // Differs from the rendered template of a template section
+    Template,
+}
+
+// Tools whose files are minijinja templates, and the --variables overrides
+#[derive(Debug, Clone, Default)]
+struct Templates {
+    tools: HashSet<String>,
+    overrides: Vec<(String, String)>,
+}
+
+impl SyncResult {
+    fn is_failure(&self) -> bool {
+        matches!(self, SyncResult::Failed { .. })
+    }
+    
+    fn tool(&self) -> &str {
+        match self {
+            SyncResult::Synced { tool, .. } | SyncResult::Skipped { tool, .. } | SyncResult::Failed { tool, .. } => tool,
+        }
+    }
+    
+    fn path(&self) -> String {
+        match self {
+            SyncResult::Synced { tool, file } | SyncResult::Skipped { tool, file, .. } | SyncResult::Failed { tool, file, .. } => {
+                format!("{}/{}", tool, file)
+            },
+        }
+    }
+}
+
+// Result of comparing a single tracked file
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StatusOutcome {
+    Identical,
+    Modified,
+    MissingInRepo,
+    NotInstalled,
+    Ignored,
+}
+
+impl StatusOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            StatusOutcome::Identical => "identical",
+            StatusOutcome::Modified => "modified",
+            StatusOutcome::MissingInRepo => "missing in source",
+            StatusOutcome::NotInstalled => "not installed",
+            StatusOutcome::Ignored => "ignored",
+        }
+    }
+}
+
+/// File counts of one `App::run`, e.g. to check an install without parsing
+/// its output
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunSummary {
+    /// The command's name, e.g. `install`
+    pub command: String,
+    pub synced: u32,
+    pub installed: u32,
+    pub skipped: u32,
+    pub failed: u32,
+    /// Warnings printed while the command ran
+    pub warnings: u32,
+}
+
+impl RunSummary {
+    /// Whether the command touched any files at all
+    pub fn has_files(&self) -> bool {
+        self.synced + self.installed + self.skipped + self.failed > 0
+    }
+    
+    /// 0 when no file failed, otherwise 1
+    pub fn exit_code(&self) -> i32 {
+        if self.failed > 0 { 1 } else { 0 }
+    }
+}
+
+impl std::fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let counts = [(self.synced, "synced"), (self.installed, "installed"), (self.skipped, "skipped")];
+        let mut parts: Vec<String> = counts.iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, label)| format!("{} {}", count, label))
+            .collect();
+        parts.push(format!("{} failed", self.failed));
+        parts.push(format!("{} warnings", self.warnings));
+        write!(f, "{}: {}", self.command, parts.join(", "))
+    }
+}
+
+// Files written and left alone by an install
+#[derive(Debug, Default)]
+struct InstallReport {
+    installed: u32,
+    skipped: u32,
+}
+
+impl InstallReport {
+    fn record(&mut self, installed: bool) {
+        if installed {
+            self.installed += 1;
+        } else {
+            self.skipped += 1;
+        }
+    }
+}
+
+// Which of a tracked file's paths +where prints on its own
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PathKind {
+    Repo,
+    Config,
+}
+
+// Totals over the repository copies of tracked files, for +stats. Files of
+// disabled sections and ignored files are only counted, not measured
+#[derive(Debug, Default, Serialize)]
+struct RepoStats {
+    tools: usize,
+    disabled_tools: usize,
+    files: usize,
+    disabled_files: usize,
+    ignored_files: usize,
+    missing_files: usize,
+    total_size: u64,
+    largest_tool: Option<String>,
+    largest_file: Option<FileStats>,
+    newest_file: Option<FileStats>,
+    per_tool: Vec<ToolStats>,
+}
+
+#[derive(Debug, Serialize)]
+struct ToolStats {
+    tool: String,
+    files: usize,
+    size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FileStats {
+    path: String,
+    size: u64,
+    modified: String,
+    #[serde(skip)]
+    mtime: std::time::SystemTime,
+}
+
+/// File counts from a status check
+#[derive(Debug, Default, Serialize)]
+pub struct StatusReport {
+    pub identical: u32,
+    pub modified: u32,
+    pub missing_in_repo: u32,
+    pub not_installed: u32,
+    pub ignored: u32,
+    // (tool, file, outcome) of each file checked, in order
+    #[serde(skip)]
+    files: Vec<(String, String, StatusOutcome)>,
+}
+
+impl StatusReport {
+    fn record(&mut self, tool: &str, file: &str, outcome: StatusOutcome) {
+        self.files.push((tool.to_string(), file.to_string(), outcome));
+        match outcome {
+            StatusOutcome::Identical => self.identical += 1,
+            StatusOutcome::Modified => self.modified += 1,
+            StatusOutcome::MissingInRepo => self.missing_in_repo += 1,
+            StatusOutcome::NotInstalled => self.not_installed += 1,
+            StatusOutcome::Ignored => self.ignored += 1,
+        }
+    }
+    
+    // 0 when in sync, 1 for modified files, 2 for files missing in the repo, 3 for both
+    fn exit_code(&self) -> i32 {
+        match (self.modified > 0, self.missing_in_repo > 0) {
+            (false, false) => 0,
+            (true, false) => 1,
+            (false, true) => 2,
+            (true, true) => 3,
+        }
+    }
+    
+    fn merge(&mut self, other: &StatusReport) {
+        self.identical += other.identical;
+        self.modified += other.modified;
+        self.missing_in_repo += other.missing_in_repo;
+        self.not_installed += other.not_installed;
+        self.ignored += other.ignored;
+        self.files.extend(other.files.iter().cloned());
+    }
+    
+    fn summary(&self) -> String {
+        format!(
+            "{} identical, {} modified, {} missing, {} not installed, {} ignored",
+            self.identical, self.modified, self.missing_in_repo, self.not_installed, self.ignored
+        )
+    }
+    
+    // Like summary, but only the non-zero counts
+    fn brief(&self) -> String {
+        let counts = [
+            (self.identical, "identical"),
+            (self.modified, "modified"),
+            (self.missing_in_repo, "missing"),
+            (self.not_installed, "not installed"),
+            (self.ignored, "ignored"),
+        ];
+        let parts: Vec<String> = counts.iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, label)| format!("{} {}", count, label))
+            .collect();
+        
+        if parts.is_empty() {
+            "no files".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+// Contents of stash_manifest.toml, written next to the stashed files
+#[derive(Debug, Serialize, Deserialize)]
+struct StashManifest {
+    timestamp: String,
+    hostname: String,
+    // Stashed files as tool/file
+    files: Vec<String>,
+}
+
+impl StashManifest {
+    const FILE_NAME: &'static str = "stash_manifest.toml";
+    
+    fn load(stash_dir: &Path) -> Result<Self> {
+        let content = fs::read_to_string(stash_dir.join(Self::FILE_NAME))?;
+        toml::from_str(&content).map_err(|e| anyhow!("Invalid stash manifest: {}", e))
+    }
+}
+
+// ErrorCollector gathers per-file errors so a run can report all of them at once
+struct ErrorCollector {
+    errors: Vec<(String, anyhow::Error)>,
+    fail_fast: bool,
+    // Pass the first ConflictDetected through, unless install --accumulate-errors
+    stop_on_conflict: bool,
+}
+
+impl ErrorCollector {
+    fn new(fail_fast: bool) -> Self {
+        Self {
+            errors: Vec::new(),
+            fail_fast,
+            stop_on_conflict: false,
+        }
+    }
+    
+    // Record a failed result, or pass it through in fail-fast mode
+    fn collect(&mut self, context: &str, result: Result<()>) -> Result<()> {
+        if let Err(e) = result {
+            let aborted = match e.downcast_ref::<DotfilesError>() {
+                Some(DotfilesError::Aborted(_)) => true,
+                Some(DotfilesError::ConflictDetected { .. }) => self.stop_on_conflict,
+                _ => false,
+            };
+            if self.fail_fast || aborted {
+                return Err(e);
+            }
+            self.errors.push((context.to_string(), e));
+        }
+        Ok(())
+    }
+    
+    fn len(&self) -> usize {
+        self.errors.len()
+    }
+    
+    // Move the conflicts out of the collected errors
+    fn take_conflicts(&mut self) -> ConflictSummary {
+        let (conflicts, errors) = std::mem::take(&mut self.errors).into_iter()
+            .partition(|(_, e)| matches!(e.downcast_ref::<DotfilesError>(), Some(DotfilesError::ConflictDetected { .. })));
+        self.errors = errors;
+        ConflictSummary { conflicts: conflicts.into_iter().map(|(_, e)| e).collect() }
+    }
+    
+    fn report(&self, formatter: &mut Formatter) -> Result<()> {
+        formatter.header(&format!("{} errors occurred:", self.errors.len()))?;
+        for (context, error) in &self.errors {
+            formatter.error(&format!("{}: {}", context, error))?;
+        }
+        Ok(())
+    }
+    
+    // Report collected errors and turn them into a single failure
+    fn finish(self, formatter: &mut Formatter) -> Result<()> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        
+        self.report(formatter)?;
+        Err(anyhow!("{} errors occurred", self.errors.len()))
+    }
+}
+
+// Local files that `--conflict error` refused to overwrite during an install
+struct ConflictSummary {
+    conflicts: Vec<anyhow::Error>,
+}
+
+impl ConflictSummary {
+    // Print each conflict and fail if there were any
+    fn finish(self, formatter: &mut Formatter) -> Result<()> {
+        if self.conflicts.is_empty() {
+            return Ok(());
+        }
+        
+        formatter.header(&format!("{} conflicts found:", self.conflicts.len()))?;
+        for conflict in &self.conflicts {
+            if let Some(DotfilesError::ConflictDetected { tool, file, repo_mtime, config_mtime }) = conflict.downcast_ref() {
+                formatter.error(&format!("{}/{}: repo modified {}, local modified {}",
+                    tool, file, format_mtime(*repo_mtime), format_mtime(*config_mtime)))?;
+            }
+        }
+        formatter.info("Use --conflict overwrite, skip or ask to resolve them")?;
+        Err(anyhow!("{} conflicts found", self.conflicts.len()))
+    }
+}
+
+#[derive(Debug)]
+enum AppMode {
+    // Use files from local filesystem
+    FilesystemMode,
+    // Use files from embedded archive
+    EmbeddedMode,
+}
+
+// App is the main application
+pub struct App {
+    paths: FilePaths,
+    formatter: Formatter,
+    distribution_parser: DistributionParser,
+    dotignore: DotIgnore,
+    tool_config: ToolConfig,
+    // tool_config before the distribution's [_settings] were merged in
+    base_config: ToolConfig,
+    status_totals: StatusReport,
+    install_totals: InstallReport,
+    // Counts for the command being run, returned by App::run
+    run_summary: RunSummary,
+    // Encrypted tools and their GPG key, see Section::encrypted
+    encrypted_tools: HashMap<String, Option<String>>,
+    templates: Templates,
+    retry_policy: RetryPolicy,
+    // Only tools with one of these tags are processed; empty means all tools
+    tag_filter: Vec<String>,
+    // Only tools in the --profile group are processed; None means all tools
+    profile_tools: Option<Vec<String>>,
+    tool_filter: Option<String>,
+    symlinks: SymlinkPolicy,
+    // Cleared by install --no-create-dirs
+    create_dirs: bool,
+    // This install's directory under the configured backup_dir
+    backup_dir: Option<PathBuf>,
+    // Set by --interactive on install and sync
+    prompt: Option<InteractivePrompt>,
+    operation_log: OperationLog,
+    mode: AppMode,
+    verbose: u8,
+    show_all: bool,
+}
+
+impl App {
+    /// Create the app the `dotfiles-rs` binary runs, configured by
+    /// [`ToolConfig::from_env_and_file`]. `repo_dir` overrides the configured
+    /// repository and `color` (`always`, `auto` or `never`) the configured
+    /// `color_output`. Binaries built with the `embedded` feature use the
+    /// dotfiles they carry
+    pub fn new(verbose: u8, quiet: bool, show_all: bool, repo_dir: Option<PathBuf>, color: Option<&str>) -> Result<Self> {
+        let mut config = ToolConfig::from_env_and_file()?;
+        if let Some(dir) = repo_dir {
+            config.repo_dir = dir.to_string_lossy().to_string();
+        }
+        let mut formatter = Formatter::new_with_choice(Formatter::color_choice(color, &config.color_output));
+        formatter.verbose = verbose;
+        formatter.set_quiet(quiet);
+        
+        if Self::uses_embedded_files() {
+            return Self::from_embedded(config, formatter, show_all);
+        }
+        Self::from_filesystem(config, formatter, show_all)
+    }
+    
+    /// Create an app that manages the repository and config directory named
+    /// in `config`, without reading the config file or the environment.
+    ///
+    /// ```no_run
+    /// use dotfiles_rs::{App, Commands, ToolConfig};
+    ///
+    /// let config = ToolConfig {
+    ///     repo_dir: "~/src/dotfiles".to_string(),
+    ///     config_dir: "~/.config".to_string(),
+    ///     ..ToolConfig::default()
+    /// };
+    /// let mut app = App::new_with_config(config)?;
+    /// app.run(&Commands::Tags)?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn new_with_config(config: ToolConfig) -> Result<Self> {
+        let formatter = Formatter::new_with_choice(Formatter::color_choice(None, &config.color_output));
+        Self::from_filesystem(config, formatter, false)
+    }
+    
+    /// Create the app [`App::new`] would, but with all output going through
+    /// `formatter`, e.g. one from [`Formatter::new_test_sink`] to capture it.
+    ///
+    /// ```no_run
+    /// use dotfiles_rs::{App, Commands, Formatter};
+    ///
+    /// let (formatter, output) = Formatter::new_test_sink();
+    /// let mut app = App::new_with_formatter(formatter)?;
+    /// app.run(&Commands::Tags)?;
+    /// println!("{}", String::from_utf8_lossy(&output.lock().unwrap()));
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn new_with_formatter(formatter: Formatter) -> Result<Self> {
+        let config = ToolConfig::from_env_and_file()?;
+        if Self::uses_embedded_files() {
+            return Self::from_embedded(config, formatter, false);
+        }
+        Self::from_filesystem(config, formatter, false)
+    }
+    
+    // Create an app that uses the repository in `tool_config`
+    fn from_filesystem(tool_config: ToolConfig, formatter: Formatter, show_all: bool) -> Result<Self> {
+        let paths = FilePaths::new(&tool_config)?;
+        let distribution_parser = DistributionParser::new(paths.distribution_file.clone());
+        Self::from_parser(tool_config, paths, formatter, show_all, distribution_parser, AppMode::FilesystemMode)
+    }
+    
+    // The rest of from_filesystem and from_embedded, once they have picked
+    // where the distribution file and .dotignore come from
+    fn from_parser(mut tool_config: ToolConfig, mut paths: FilePaths, formatter: Formatter, show_all: bool,
+        distribution_parser: DistributionParser, mode: AppMode) -> Result<Self> {
+        let base_config = tool_config.clone();
+        let verbose = formatter.verbosity();
+        let distribution_parser = distribution_parser.with_env(paths.env.clone());
+        let dotignore = match mode {
+            AppMode::FilesystemMode => DotIgnore::from_global_and_local(&paths.global_dotignore_file, &paths.dotignore_file)?,
+            AppMode::EmbeddedMode => DotIgnore::from_embedded()?,
+        };
+        paths.set_section_dirs(distribution_parser.section_dirs().unwrap_or_default());
+        
+        if let Ok(Distribution { settings: Some(settings), .. }) = distribution_parser.read_distribution() {
+            tool_config.merge_distribution_settings(&settings);
+        }
+        let encrypted_tools = distribution_parser.encrypted_tools().unwrap_or_default();
+        let templates = Templates { tools: distribution_parser.template_tools().unwrap_or_default(), overrides: Vec::new() };
+        let operation_log = OperationLog::new(&paths.repo_dir);
+        
+        Ok(Self {
+            paths,
+            formatter,
+            distribution_parser,
+            dotignore,
+            tool_config,
+            base_config,
+            status_totals: StatusReport::default(),
+            install_totals: InstallReport::default(),
+            run_summary: RunSummary::default(),
+            encrypted_tools,
+            templates,
+            retry_policy: RetryPolicy::default(),
+            tag_filter: Vec::new(),
+            profile_tools: None,
+            tool_filter: None,
+            symlinks: SymlinkPolicy::default(),
+            create_dirs: true,
+            backup_dir: None,
+            prompt: None,
+            operation_log,
+            mode,
+            verbose,
+            show_all,
+        })
+    }
+    
+    /// The formatter all output goes through
+    pub fn formatter(&mut self) -> &mut Formatter {
+        &mut self.formatter
+    }
+    
+    /// Exit code for the files `status` reported on: 0 when in sync, 1 for
+    /// modified files, 2 for files missing in the repo, 3 for both
+    pub fn exit_code(&self) -> i32 {
+        self.status_totals.exit_code()
+    }
+    
+    /// Only process the tools listed under `profile` in the reserved
+    /// `[_profiles]` section of the distribution file
+    pub fn set_profile(&mut self, profile: &str) -> Result<()> {
+        self.profile_tools = Some(self.distribution_parser.profile_tools(profile)?);
+        Ok(())
+    }
+    
+    /// Leave undefined `$VAR` references in file entries and
+    /// `source_dir`/`target_dir` as written instead of failing
+    pub fn set_allow_undefined_vars(&mut self, allow: bool) {
+        self.paths.env.allow_undefined = allow;
+        self.distribution_parser.env = Some(self.paths.env.clone());
+        self.paths.set_section_dirs(self.distribution_parser.section_dirs().unwrap_or_default());
+    }
+    
+    /// Whether this binary was built with dotfiles embedded in it
+    pub fn uses_embedded_files() -> bool {
+        cfg!(feature = "embedded") && has_embedded_files()
+    }
+    
+    // Create an app instance that uses the embedded files
+    fn from_embedded(tool_config: ToolConfig, formatter: Formatter, show_all: bool) -> Result<Self> {
+        let paths = FilePaths::new(&tool_config)?;
+        Self::from_parser(tool_config, paths, formatter, show_all, DistributionParser::from_embedded(), AppMode::EmbeddedMode)
+    }
+    
+    // Say which $XDG_CONFIG_HOME applies, except above JSON output. Relative
+    // values are invalid per the XDG spec, so FilePaths falls back to
+    // ~/.config for them
+    fn report_xdg_config_home(&mut self, json: bool) -> Result<()> {
+        let Some(value) = std::env::var("XDG_CONFIG_HOME").ok().filter(|value| !value.is_empty()) else {
+            return Ok(());
+        };
+        
+        if Path::new(&value).is_absolute() {
+            if !json {
+                self.formatter.info(&format!("Using XDG_CONFIG_HOME: {}", value))?;
+            }
+        } else {
+            self.formatter.warning(&format!("Ignoring XDG_CONFIG_HOME, which must be an absolute path: {}", value))?;
+        }
+        Ok(())
+    }
+    
+    fn check_paths(&mut self) -> Result<()> {
+        match self.mode {
+            AppMode::FilesystemMode => {
+                // Check repository directory
+                if !self.paths.repo_dir.exists() {
+                    return Err(DotfilesError::RepoNotFound(
+                        self.paths.repo_dir.to_string_lossy().to_string(),
+                    )
+                    .into());
+                }
+                
+                // Check distribution file
+                if !self.distribution_parser.reads_stdin() && !self.paths.distribution_file.exists() {
+                    return Err(DotfilesError::DistributionNotFound(
+                        self.paths.distribution_file.to_string_lossy().to_string(),
+                    )
+                    .into());
+                }
+            },
+            AppMode::EmbeddedMode => {
+                // In embedded mode, we don't need to check for physical files
+                // as everything should be in the embedded archive
+                self.formatter.info("Using embedded archive mode")?;
+            }
+        }
+        
+        // Create config directory if it doesn't exist
+        if !self.paths.config_dir.exists() {
+            self.formatter.warning(&format!(
+                "Config directory not found, creating: {}",
+                self.paths.config_dir.display()
+            ))?;
+            create_dir_all(&self.paths.config_dir)?;
+        }
+        
+        Ok(())
+    }
+    
+    // Replace the distribution file with the one piped to stdin, and reload
+    // what App::new took from it. `json` leaves stdout to the JSON output
+    fn read_distribution_from_stdin(&mut self, json: bool) -> Result<()> {
+        if !json {
+            self.formatter.info("Reading distribution from stdin")?;
+        }
+        self.distribution_parser = DistributionParser::from_stdin()?.with_env(self.paths.env.clone());
+        self.paths.set_section_dirs(self.distribution_parser.section_dirs().unwrap_or_default());
+        
+        // Only the piped distribution's settings apply, not those on disk
+        self.tool_config = self.base_config.clone();
+        if let Ok(Distribution { settings: Some(settings), .. }) = self.distribution_parser.read_distribution() {
+            self.tool_config.merge_distribution_settings(&settings);
+        }
+        self.encrypted_tools = self.distribution_parser.encrypted_tools().unwrap_or_default();
+        self.templates.tools = self.distribution_parser.template_tools().unwrap_or_default();
+        Ok(())
+    }
+    
+    // The parser precheck validates with: the distribution file, or the
+    // distribution read from stdin
+    fn precheck_parser(&self, format: DistributionFormat) -> DistributionParser {
+        if self.distribution_parser.reads_stdin() {
+            return self.distribution_parser.clone().with_format(format);
+        }
+        DistributionParser::new(self.paths.distribution_file.clone()).with_format(format)
+    }
+    
+    fn create_dotignore(&self) -> Result<()> {
+        match self.mode {
+            AppMode::FilesystemMode => {
+                DotIgnore::create_default(&self.paths.dotignore_file)?;
+            },
+            AppMode::EmbeddedMode => {
+                // In embedded mode, we don't need to create a physical dotignore file
+                // as it should be in the embedded archive
+            }
+        }
+        Ok(())
+    }
+    
+    // Turn on per-file confirmation, which needs a terminal to answer on
+    fn set_interactive(&mut self, interactive: bool) -> Result<()> {
+        if !interactive {
+            return Ok(());
+        }
+        if !std::io::stdin().is_terminal() {
+            return Err(DotfilesError::InvalidCommand(
+                "--interactive needs a terminal; run without it to apply every change without asking".to_string()).into());
+        }
+        self.prompt = Some(InteractivePrompt::default());
+        Ok(())
+    }
+    
+    // Restrict sync, status and install to one tool. Set tag_filter first:
+    // --tool and --tag compose, so a tool without a matching tag does nothing
+    fn set_tool_filter(&mut self, tool: &Option<String>) -> Result<()> {
+        if let Some(tool) = tool {
+            let tools = self.distribution_parser.get_tools()?;
+            if !tools.contains(tool) {
+                return Err(DotfilesError::tool_not_found(tool, &tools).into());
+            }
+            if !self.distribution_parser.has_any_tag(tool, &self.tag_filter)? {
+                self.formatter.warning(&format!("Tool '{}' has none of the tags given with --tag, nothing to do", tool))?;
+            }
+        }
+        self.tool_filter = tool.clone();
+        Ok(())
+    }
+    
+    // Tools in priority order, narrowed to --tool when given
+    fn selected_tools(&self) -> Result<Vec<String>> {
+        let mut tools = self.distribution_parser.get_tools_by_priority()?;
+        if let Some(only) = &self.tool_filter {
+            tools.retain(|tool| tool == only);
+        }
+        Ok(tools)
+    }
+    
+    // Override template variables with --variables key=value
+    fn set_template_variables(&mut self, variables: &[String]) -> Result<()> {
+        self.templates.overrides = variables.iter().map(|variable| match variable.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+            _ => Err(DotfilesError::InvalidCommand(format!("--variables takes key=value, got: {}", variable))),
+        }).collect::<std::result::Result<_, _>>()?;
+        Ok(())
+    }
+    
+    // Whether --profile, if given, lists the tool
+    fn in_profile(&self, tool: &str) -> bool {
+        self.profile_tools.as_ref().is_none_or(|tools| tools.iter().any(|name| name == tool))
+    }
+    
+    // Why --tag, --profile or the enabled flag leaves the tool out, if they do
+    fn skip_reason(&self, tool: &str) -> Result<Option<&'static str>> {
+        Ok(if !self.distribution_parser.has_any_tag(tool, &self.tag_filter)? {
+            Some("no matching tag")
+        } else if !self.in_profile(tool) {
+            Some("not in profile")
+        } else if !self.distribution_parser.is_enabled(tool)? {
+            Some("disabled")
+        } else {
+            None
+        })
+    }
+    
+    // selected_tools without those skip_reason leaves out, reporting each skip
+    fn filtered_tools(&mut self) -> Result<Vec<String>> {
+        let mut tools = Vec::new();
+        for tool in self.selected_tools()? {
+            match self.skip_reason(&tool)? {
+                None => tools.push(tool),
+                Some("disabled") => self.formatter.info(&format!("Skipped (disabled): {}", tool))?,
+                Some(reason) => self.formatter.verbose(&format!("Skipped ({}): {}", reason, tool))?,
+            }
+        }
+        Ok(tools)
+    }
+    
+    // Install or diff one tool; callers pick it with filtered_tools
+    fn process_section(&mut self, tool: &str, action: &str, conflict: ConflictStrategy, errors: &mut ErrorCollector) -> Result<()> {
+        self.formatter.verbose(&format!("Reading distribution file for tool: {}", tool))?;
+        let entries = self.distribution_parser.get_file_entries(tool)?;
+        
+        self.formatter.verbose(&format!("Found {} files for tool '{}'", entries.len(), tool))?;
+        self.formatter.info(&format!("Processing tool: {}", tool))?;
+        
+        let dest_dir = if action == "install" {
+            self.paths.install_section_dir(tool)
+        } else {
+            self.paths.config_section_dir(tool)
+        };
+        self.formatter.verbose(&format!("Tool config directory: {}", dest_dir.display()))?;
+        
+        if !dest_dir.exists() {
+            self.formatter.verbose(&format!("Config directory for '{}' does not exist", tool))?;
+            
+            // Only create directories for commands that should modify the filesystem
+            if action == "install" && !self.create_dirs {
+                self.formatter.verbose("Not creating it (--no-create-dirs)")?;
+            } else if action == "install" {
+                self.formatter.verbose(&format!("Action '{}' requires directory creation", action))?;
+                self.formatter.action(&format!("Creating directory: {}", dest_dir.display()))?;
+                create_dir_all(&dest_dir)?;
+            } else {
+                self.formatter.verbose(&format!("Skipping directory creation for read-only action: {}", action))?;
+            }
+        } else {
+            self.formatter.verbose(&format!("Config directory for '{}' already exists", tool))?;
+        }
+        
+        let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(tool))?;
+        self.formatter.verbose(&format!("Loaded {} .toolignore patterns for '{}'", tool_ignore.patterns.len(), tool))?;
+        
+        self.formatter.verbose(&format!("Creating file manager for mode: {:?}", self.mode))?;
+        
+        for entry in &entries {
+            let file = entry.destination();
+            self.formatter.verbose(&format!("Processing file '{}' with action '{}'", file, action))?;
+            if action == "install" {
+                self.formatter.progress_message(format!("Installing {}/{}", tool, file));
+            }
+            
+            // Create a new file manager for each file to avoid borrowing issues
+            let mut file_manager = match self.mode {
+                AppMode::FilesystemMode => FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+                AppMode::EmbeddedMode => FileManager::from_embedded(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+            }.with_renames(tool, &entries).with_encryption(&self.encrypted_tools).with_templates(&self.templates).with_retry_policy(self.retry_policy)
+                .with_operation_log(&self.operation_log).with_prompt(self.prompt.as_mut())
+                .with_symlinks(SymlinkPolicy { link_installs: self.tool_config.symlink_mode, ..self.symlinks })
+                .with_create_dirs(self.create_dirs).with_backup_dir(self.backup_dir.as_deref());
+            
+            let result = match action {
+                "install" => match entry {
+                    FileEntry::Rename { from, to } => file_manager.install_renamed(tool, from, to, conflict),
+                    FileEntry::Simple(file) => file_manager.install_file(tool, file, conflict),
+                }.map(|installed| {
+                    self.install_totals.record(installed);
+                    if installed {
+                        self.run_summary.installed += 1;
+                    } else {
+                        self.run_summary.skipped += 1;
+                    }
+                }),
+                "diff" => file_manager.show_diff(tool, file),
+                _ => {
+                    self.formatter.verbose(&format!("Invalid action requested: {}", action))?;
+                    return Err(DotfilesError::InvalidCommand(format!(
+                        "Invalid action: {}",
+                        action
+                    )).into())
+                }
+            };
+            self.formatter.progress_inc();
+            if result.is_err() {
+                self.run_summary.failed += 1;
+            }
+            errors.collect(&format!("{}/{}", tool, file), result)?;
+        }
+        
+        self.formatter.verbose(&format!("Completed processing tool: {}", tool))?;
+        Ok(())
+    }
+    
+    fn run_sync(&mut self, fail_fast: bool, since: Option<chrono::NaiveDate>, skip_post_sync: bool) -> Result<()> {
+        self.formatter.header("Syncing dotfiles...")?;
+        self.formatter.verbose("Starting dotfiles sync operation")?;
+        
+        let mut tools = Vec::new();
+        for tool in self.filtered_tools()? {
+            let entries = self.distribution_parser.get_file_entries(&tool)?;
+            tools.push((tool, entries));
+        }
+        self.formatter.verbose(&format!("Found {} tools in distribution file", tools.len()))?;
+        
+        // sync_all loads each tool's .toolignore itself
+        let mut file_manager = match self.mode {
+            AppMode::FilesystemMode => FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all),
+            AppMode::EmbeddedMode => FileManager::from_embedded(&self.paths, &mut self.formatter, &self.dotignore, self.show_all),
+        }.with_encryption(&self.encrypted_tools).with_templates(&self.templates).with_retry_policy(self.retry_policy)
+            .with_operation_log(&self.operation_log).with_since(since).with_prompt(self.prompt.as_mut());
+        // Prompts and the progress bar would draw over each other
+        if file_manager.prompt.is_none() {
+            file_manager.formatter.start_progress(tools.iter().map(|(_, entries)| entries.len()).sum());
+        }
+        let results = file_manager.sync_all(&tools, fail_fast);
+        file_manager.formatter.finish_progress();
+        let results = results?;
+        
+        let mut errors = ErrorCollector::new(fail_fast);
+        for result in &results {
+            match result {
+                SyncResult::Synced { .. } => self.run_summary.synced += 1,
+                SyncResult::Skipped { .. } => self.run_summary.skipped += 1,
+                SyncResult::Failed { error, .. } => {
+                    self.run_summary.failed += 1;
+                    errors.collect(&result.path(), Err(anyhow!("{}", error)))?;
+                },
+            }
+        }
+        if !skip_post_sync {
+            self.run_post_sync(&results)?;
+        }
+        errors.finish(&mut self.formatter)?;
+        
+        self.formatter.verbose("Sync operation completed")?;
+        Ok(())
+    }
+    
+    // Run the post_sync command of each tool that had files synced and none
+    // fail. A failing command is reported, but the synced files stay
+    fn run_post_sync(&mut self, results: &[SyncResult]) -> Result<()> {
+        let commands = self.distribution_parser.post_sync_commands()?;
+        let mut tools: Vec<&str> = results.iter().map(SyncResult::tool).collect();
+        tools.dedup();
+        
+        for tool in tools {
+            let Some(command) = commands.get(tool) else { continue };
+            let tool_results = || results.iter().filter(|result| result.tool() == tool);
+            if !tool_results().any(|result| matches!(result, SyncResult::Synced { .. })) || tool_results().any(SyncResult::is_failure) {
+                continue;
+            }
+            
+            self.formatter.action(&format!("Running post_sync for {}: {}", tool, command))?;
+            match util::run_shell(command, &self.paths.repo_config_dir(tool)) {
+                Ok(output) => {
+                    let stdout = String::from_utf8_lossy(&output.stdout);
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    for line in stdout.lines().chain(stderr.lines()) {
+                        self.formatter.info(&format!("post_sync {}: {}", tool, line))?;
+                    }
+                    if !output.status.success() {
+                        let code = output.status.code().map_or_else(|| "none (killed by a signal)".to_string(), |code| code.to_string());
+                        self.formatter.error(&format!("post_sync for {} failed with exit code {}", tool, code))?;
+                    }
+                },
+                Err(e) => self.formatter.error(&format!("post_sync for {} could not be run: {}", tool, e))?,
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Check every tracked file of the enabled tools selected by `--tool`,
+    /// `--tag` and `--profile` without printing anything
+    pub fn status_report(&mut self) -> Result<StatusReport> {
+        let mut tools = Vec::new();
+        for tool in self.selected_tools()? {
+            if self.skip_reason(&tool)?.is_none() {
+                tools.push(tool);
+            }
+        }
+        self.collect_status_report(&tools, &mut ErrorCollector::new(true))
+    }
+    
+    // status_report of `tools`, with the errors of each tool passed to `errors`
+    fn collect_status_report(&mut self, tools: &[String], errors: &mut ErrorCollector) -> Result<StatusReport> {
+        let mut report = StatusReport::default();
+        
+        for tool in tools {
+            let result = self.tool_status_report(tool).map(|tool_report| report.merge(&tool_report));
+            errors.collect(tool, result)?;
+        }
+        
+        Ok(report)
+    }
+    
+    fn tool_status_report(&mut self, tool: &str) -> Result<StatusReport> {
+        let mut report = StatusReport::default();
+        
+        let entries = self.distribution_parser.get_file_entries(tool)?;
+        for (file, outcome) in self.file_statuses(tool, &entries)? {
+            report.record(tool, &file, outcome);
+        }
+        
+        Ok(report)
+    }
+    
+    // Silently check the status of each file in a tool
+    fn file_statuses(&mut self, tool: &str, entries: &[FileEntry]) -> Result<Vec<(String, StatusOutcome)>> {
+        let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(tool))?;
+        let mut statuses = Vec::new();
+        
+        for entry in entries {
+            let file_manager = match self.mode {
+                AppMode::FilesystemMode => FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+                AppMode::EmbeddedMode => FileManager::from_embedded(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+            }.with_renames(tool, entries).with_encryption(&self.encrypted_tools).with_templates(&self.templates);
+            let file = entry.destination();
+            statuses.push((file.to_string(), file_manager.file_status(tool, file)?));
+        }
+        
+        Ok(statuses)
+    }
+    
+    fn run_diff(&mut self, tool: Option<&str>, file: Option<&str>, stat: bool) -> Result<()> {
+        self.formatter.header("Comparing repository and local files...")?;
+        
+        if tool.is_some() {
+            self.set_tool_filter(&tool.map(str::to_string))?;
+        }
+        
+        // A single file skips the per-tool processing
+        if let (Some(tool), Some(file)) = (tool, file) {
+            let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(tool))?;
+            let entries = self.distribution_parser.get_file_entries(tool)?;
+            let mut file_manager = match self.mode {
+                AppMode::FilesystemMode => FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+                AppMode::EmbeddedMode => FileManager::from_embedded(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+            }.with_renames(tool, &entries).with_encryption(&self.encrypted_tools).with_templates(&self.templates);
+            return file_manager.show_diff(tool, file);
+        }
+        
+        let tools = self.filtered_tools()?;
+        if stat {
+            return self.run_diff_stat(&tools, file);
+        }
+        let mut errors = ErrorCollector::new(false);
+        for tool in tools {
+            let result = self.process_section(&tool, "diff", ConflictStrategy::Overwrite, &mut errors);
+            errors.collect(&tool, result)?;
+        }
+        errors.finish(&mut self.formatter)
+    }
+    
+    // One row per differing file with the lines added and removed locally,
+    // like `git diff --stat`
+    fn run_diff_stat(&mut self, tools: &[String], file: Option<&str>) -> Result<()> {
+        let mut rows = Vec::new();
+        let mut binary = Vec::new();
+        let (mut total_added, mut total_removed) = (0, 0);
+        let mut errors = ErrorCollector::new(false);
+        
+        for tool in tools {
+            let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(tool))?;
+            let entries = self.distribution_parser.get_file_entries(tool)?;
+            let file_manager = match self.mode {
+                AppMode::FilesystemMode => FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+                AppMode::EmbeddedMode => FileManager::from_embedded(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+            }.with_renames(tool, &entries).with_encryption(&self.encrypted_tools).with_templates(&self.templates);
+            
+            for entry in &entries {
+                let name = entry.destination();
+                if file.is_some_and(|file| file != name) {
+                    continue;
+                }
+                match file_manager.diff_stat(tool, name) {
+                    Ok(Some(DiffStat::Lines { added, removed })) => {
+                        total_added += added;
+                        total_removed += removed;
+                        rows.push(vec![tool.clone(), name.to_string(), format!("+{}", added), format!("-{}", removed)]);
+                    },
+                    Ok(Some(DiffStat::Binary)) => {
+                        rows.push(vec![tool.clone(), name.to_string(), "-".to_string(), "-".to_string()]);
+                        binary.push(format!("{}/{}", tool, name));
+                    },
+                    Ok(None) => {},
+                    Err(e) => errors.collect(&format!("{}/{}", tool, name), Err(e))?,
+                }
+            }
+        }
+        
+        self.print_diff_stat(&rows, &binary, total_added, total_removed)?;
+        errors.finish(&mut self.formatter)
+    }
+    
+    fn print_diff_stat(&mut self, rows: &[Vec<String>], binary: &[String], total_added: usize, total_removed: usize) -> Result<()> {
+        if rows.is_empty() {
+            return self.formatter.validation("No differences found");
+        }
+        self.formatter.table(&["Tool", "File", "Added", "Removed"], rows, &[None, None, Some(Color::Green), Some(Color::Red)])?;
+        for path in binary {
+            self.formatter.info(&format!("Binary files differ: {}", path))?;
+        }
+        self.formatter.summary(&format!("{} files changed, {} insertions(+), {} deletions(-)",
+            rows.len(), total_added, total_removed))
+    }
+    
+    // Compare the files saved in a stash or other backup directory with
+    // stash_manifest.toml against the installed files: backup (-) / local (+)
+    fn run_diff_backup(&mut self, backup_dir: &Path, tool: Option<&str>, file: Option<&str>, stat: bool) -> Result<()> {
+        if !backup_dir.join(StashManifest::FILE_NAME).is_file() {
+            return Err(DotfilesError::InvalidCommand(format!(
+                "{} is not a backup: it has no {}", backup_dir.display(), StashManifest::FILE_NAME
+            )).into());
+        }
+        let manifest = StashManifest::load(backup_dir)?;
+        let tools = self.distribution_parser.get_tools()?;
+        let mut backed_up = BTreeSet::new();
+        for entry in &manifest.files {
+            if let Some((tool, file)) = entry.split_once('/') {
+                backed_up.insert((tool.to_string(), file.to_string()));
+            }
+        }
+        if let Some(tool) = tool {
+            if !tools.iter().any(|t| t == tool) && !backed_up.iter().any(|(t, _)| t == tool) {
+                return Err(DotfilesError::tool_not_found(tool, &tools).into());
+            }
+        }
+        self.formatter.header(&format!("Comparing backup from {} with local files...", manifest.timestamp))?;
+        
+        // Installed files are gathered the way +stash does, so a file only
+        // counts as added when a stash taken now would include it
+        let mut paths = backed_up.clone();
+        for tool in &tools {
+            if self.distribution_parser.is_enabled(tool)? {
+                for file in self.distribution_parser.get_files(tool)? {
+                    if self.paths.install_file_path(tool, &file).is_file() {
+                        paths.insert((tool.clone(), file));
+                    }
+                }
+            }
+        }
+        
+        let mut rows = Vec::new();
+        let mut binary = Vec::new();
+        let (mut total_added, mut total_removed, mut changed) = (0, 0, 0);
+        for (entry_tool, entry_file) in &paths {
+            if tool.is_some_and(|tool| tool != entry_tool) || file.is_some_and(|file| file != entry_file)
+                || !self.distribution_parser.has_any_tag(entry_tool, &self.tag_filter)? {
+                continue;
+            }
+            
+            let display_path = format!("{}/{}", entry_tool, entry_file);
+            let backup_file = backup_dir.join(entry_tool).join(entry_file);
+            let config_file = self.paths.install_file_path(entry_tool, entry_file);
+            let old = if backed_up.contains(&(entry_tool.clone(), entry_file.clone())) { Some(fs::read(&backup_file)?) } else { None };
+            let new = if config_file.is_file() { Some(fs::read(&config_file)?) } else { None };
+            if old == new {
+                if self.show_all && !stat {
+                    self.formatter.identical(&format!("Identical: {}", display_path))?;
+                }
+                continue;
+            }
+            
+            changed += 1;
+            if stat {
+                match FileManager::line_stat(old.as_deref().unwrap_or_default(), new.as_deref().unwrap_or_default()) {
+                    DiffStat::Lines { added, removed } => {
+                        total_added += added;
+                        total_removed += removed;
+                        rows.push(vec![entry_tool.clone(), entry_file.clone(), format!("+{}", added), format!("-{}", removed)]);
+                    },
+                    DiffStat::Binary => {
+                        rows.push(vec![entry_tool.clone(), entry_file.clone(), "-".to_string(), "-".to_string()]);
+                        binary.push(display_path);
+                    },
+                }
+                continue;
+            }
+            
+            match (old, new) {
+                (Some(old), Some(new)) => {
+                    self.formatter.modified(&format!("Changed since backup: {}", display_path))?;
+                    FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all)
+                        .print_hunks(&FileManager::hunks(&old, &new), None)?;
+                },
+                (Some(_), None) => self.formatter.not_installed(&format!("Deleted: {}", display_path))?,
+                (None, _) => self.formatter.installed(&format!("Added: {}", display_path))?,
+            }
+        }
+        
+        if stat {
+            return self.print_diff_stat(&rows, &binary, total_added, total_removed);
+        }
+        if changed == 0 {
+            self.formatter.validation("No differences found")?;
+        }
+        Ok(())
+    }
+    
+    fn run_status(&mut self, fail_fast: bool, json: bool) -> Result<()> {
+        if json {
+            let report = self.status_report()?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            self.status_totals = report;
+            return Ok(());
+        }
+        
+        self.formatter.header("Checking dotfiles status...")?;
+        self.formatter.verbose("Starting dotfiles status check")?;
+        
+        let tools = self.filtered_tools()?;
+        self.formatter.verbose(&format!("Found {} tools in distribution file", tools.len()))?;
+        
+        // Add example output
+        if self.verbose > 0 {
+            self.formatter.verbose("Sample output for reference:")?;
+            self.formatter.verbose("EXAMPLE:✓ Identical: nvim/icons.md\n✓ Identical: nvim/init.lua\n✓")?;
+            self.formatter.verbose("Actual file status:")?;
+        }
+        
+        let mut errors = ErrorCollector::new(fail_fast);
+        let report = self.collect_status_report(&tools, &mut errors)?;
+        
+        for tool in tools {
+            if !report.files.iter().any(|(file_tool, _, _)| *file_tool == tool) {
+                continue;
+            }
+            
+            self.formatter.info(&format!("Processing tool: {}", tool))?;
+            let entries = self.distribution_parser.get_file_entries(&tool)?;
+            let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(&tool))?;
+            for (_, file, outcome) in report.files.iter().filter(|(file_tool, _, _)| *file_tool == tool) {
+                let mut file_manager = match self.mode {
+                    AppMode::FilesystemMode => FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+                    AppMode::EmbeddedMode => FileManager::from_embedded(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+                }.with_renames(&tool, &entries).with_encryption(&self.encrypted_tools).with_templates(&self.templates);
+                file_manager.print_status(&tool, file, *outcome)?;
+            }
+        }
+        errors.finish(&mut self.formatter)?;
+        
+        // Show summary of files checked
+        if !self.show_all {
+            self.formatter.info(&format!("Status check completed: {} files checked (use --all to see identical files)", report.files.len()))?;
+        } else {
+            self.formatter.info(&format!("Status check completed: {} files checked", report.files.len()))?;
+        }
+        self.formatter.summary(&format!("Summary: {}", report.summary()))?;
+        self.status_totals = report;
+        
+        self.formatter.verbose("Status check completed")?;
+        Ok(())
+    }
+    
+    // PASS when the local copy of a tracked file is non-empty and matches the
+    // repo. Any FAIL fails the command, so CI and machine setup can rely on it
+    fn run_check(&mut self, strict: bool) -> Result<()> {
+        self.formatter.header("Checking installed files...")?;
+        
+        let (mut passed, mut failed) = (0, 0);
+        for tool in self.filtered_tools()? {
+            let entries = self.distribution_parser.get_file_entries(&tool)?;
+            for (file, outcome) in self.file_statuses(&tool, &entries)? {
+                let path = format!("{}/{}", tool, file);
+                let failure = match outcome {
+                    StatusOutcome::Ignored => continue,
+                    StatusOutcome::Identical if self.paths.config_file_path(&tool, &file).metadata()?.len() == 0 => Some("empty"),
+                    StatusOutcome::Identical => None,
+                    StatusOutcome::Modified => Some("differs from the repo"),
+                    StatusOutcome::MissingInRepo => Some("missing in the repo"),
+                    StatusOutcome::NotInstalled if strict => Some("not installed"),
+                    StatusOutcome::NotInstalled => {
+                        self.formatter.warning(&format!("SKIP: {} (not installed)", path))?;
+                        continue;
+                    },
+                };
+                match failure {
+                    None => {
+                        passed += 1;
+                        self.formatter.success(&format!("PASS: {}", path))?;
+                    },
+                    Some(reason) => {
+                        failed += 1;
+                        self.formatter.error(&format!("FAIL: {} ({})", path, reason))?;
+                    },
+                }
+            }
+        }
+        
+        self.formatter.summary(&format!("{} passed, {} failed", passed, failed))?;
+        if failed > 0 {
+            return Err(DotfilesError::InvalidCommand(format!("{} files failed the check", failed)).into());
+        }
+        Ok(())
+    }
+    
+    // Validate the distribution, then create every tool directory that's
+    // missing and install all tools; `run` has already created the .dotignore.
+    // Files that are already installed are skipped, so running it again is safe
+    fn run_bootstrap(&mut self, yes: bool, dry_run: bool) -> Result<()> {
+        match self.mode {
+            AppMode::FilesystemMode => self.run_precheck(None, false, false)?,
+            AppMode::EmbeddedMode => self.check_embedded_integrity()?,
+        }
+        self.formatter.header("Bootstrapping dotfiles...")?;
+        
+        let mut tools = Vec::new();
+        let mut missing_dirs: Vec<PathBuf> = Vec::new();
+        for tool in self.filtered_tools()? {
+            let dir = self.paths.install_section_dir(&tool);
+            if !dir.exists() && !missing_dirs.contains(&dir) {
+                missing_dirs.push(dir);
+            }
+            tools.push(tool);
+        }
+        
+        if dry_run {
+            for dir in &missing_dirs {
+                self.formatter.info(&format!("Would create directory: {}", dir.display()))?;
+            }
+            let mut count = 0;
+            for tool in &tools {
+                let entries = self.distribution_parser.get_file_entries(tool)?;
+                for (file, outcome) in self.file_statuses(tool, &entries)? {
+                    if matches!(outcome, StatusOutcome::NotInstalled | StatusOutcome::Modified) {
+                        self.formatter.info(&format!("Would install: {}/{}", tool, file))?;
+                        count += 1;
+                    }
+                }
+            }
+            return self.formatter.summary(&format!("Bootstrap would install {} files into {} tools (run without --dry-run to apply)",
+                count, tools.len()));
+        }
+        
+        if !yes {
+            self.formatter.print(&format!("Install {} tools into {}? [y/N]: ", tools.len(), self.paths.config_dir.display()),
+                Some(Color::Cyan), true)?;
+            self.formatter.stdout.flush()?;
+            
+            let answer = match std::io::stdin().lines().next() {
+                Some(line) => line?,
+                None => String::new(),
+            };
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                return Err(DotfilesError::Aborted("bootstrap stopped by user".to_string()).into());
+            }
+        }
+        
+        for dir in &missing_dirs {
+            self.formatter.action(&format!("Creating directory: {}", dir.display()))?;
+            create_dir_all(dir)?;
+        }
+        
+        let conflict = ConflictStrategy::from_str(&self.tool_config.default_conflict_strategy, true)
+            .map_err(DotfilesError::InvalidCommand)?;
+        let mut errors = ErrorCollector::new(false);
+        for tool in &tools {
+            let result = self.process_section(tool, "install", conflict, &mut errors);
+            errors.collect(tool, result)?;
+        }
+        
+        self.formatter.summary(&format!("Bootstrap complete: {} files installed, {} skipped, {} errors",
+            self.install_totals.installed, self.install_totals.skipped, errors.len()))?;
+        errors.finish(&mut self.formatter)
+    }
+    
+    fn run_install(&mut self, conflict: ConflictStrategy, fail_fast: bool, accumulate_errors: bool) -> Result<()> {
+        self.formatter.header("Installing dotfiles...")?;
+        self.formatter.verbose("Starting dotfiles installation")?;
+        self.formatter.verbose(&format!("Using conflict strategy: {:?}", conflict))?;
+        
+        let tools = self.filtered_tools()?;
+        self.formatter.verbose(&format!("Found {} tools in distribution file", tools.len()))?;
+        
+        // Each install backs up into its own timestamped directory; an empty
+        // backup_dir turns backups off
+        self.backup_dir = match self.tool_config.backup_dir.as_str() {
+            "" => None,
+            dir => Some(self.paths.expand_path(dir)?.join(chrono::Local::now().format("%Y%m%dT%H%M%S").to_string())),
+        };
+        
+        // Prompts and the progress bar would draw over each other
+        if self.prompt.is_none() && conflict != ConflictStrategy::Ask {
+            let mut total_files = 0;
+            for tool in &tools {
+                total_files += self.distribution_parser.get_files(tool)?.len();
+            }
+            self.formatter.start_progress(total_files);
+        }
+        
+        let mut errors = ErrorCollector::new(fail_fast);
+        errors.stop_on_conflict = !accumulate_errors;
+        for tool in tools {
+            let result = self.process_section(&tool, "install", conflict, &mut errors);
+            errors.collect(&tool, result)?;
+        }
+        self.formatter.finish_progress();
+        let conflicts = errors.take_conflicts();
+        let result = errors.finish(&mut self.formatter);
+        conflicts.finish(&mut self.formatter)?;
+        result?;
+        
+        self.formatter.verbose("Installation completed")?;
+        Ok(())
+    }
+    
+    fn run_add(&mut self, tool: &str, file: &str, metadata: &SectionMetadata, sort: bool) -> Result<()> {
+        self.formatter.verbose(&format!("Adding file {}/{} to tracking", tool, file))?;
+        let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(tool))?;
+        let mut file_manager = FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore)
+            .with_encryption(&self.encrypted_tools).with_templates(&self.templates)
+            .with_retry_policy(self.retry_policy)
+            .with_operation_log(&self.operation_log);
+        file_manager.add_file(tool, file, metadata)?;
+        self.formatter.verbose("File added successfully")?;
+        
+        let parser = DistributionParser::new(self.paths.distribution_file.clone());
+        let auto_sort = parser.read_distribution()?
+            .settings
+            .and_then(|settings| settings.auto_sort)
+            .unwrap_or(false);
+        if sort || auto_sort {
+            parser.sort()?;
+            self.formatter.verbose("Sorted distribution file")?;
+        }
+        
+        Ok(())
+    }
+    
+    fn run_deduplicate(&mut self, case_insensitive: bool) -> Result<()> {
+        let parser = DistributionParser::new(self.paths.distribution_file.clone());
+        
+        // Entries differing only by case name the same file on case-insensitive filesystems
+        if cfg!(target_os = "macos") && !case_insensitive {
+            for (tool, file) in parser.case_insensitive_duplicates()? {
+                self.formatter.warning(&format!("Case-insensitive duplicate: {}/{} (use --case-insensitive to remove)", tool, file))?;
+            }
+        }
+        
+        let removed = parser.deduplicate(case_insensitive)?;
+        if removed > 0 {
+            self.formatter.action(&format!("Removed {} duplicate entries", removed))?;
+        } else {
+            self.formatter.validation("No duplicate entries found")?;
+        }
+        
+        Ok(())
+    }
+    
+    // Report (and with `delete`, remove) repo files no section references
+    fn run_clean(&mut self, delete: bool, yes: bool) -> Result<()> {
+        self.formatter.header("Looking for orphaned repo files...")?;
+        
+        let config_root = self.paths.repo_dir.join("config");
+        if !config_root.is_dir() {
+            self.formatter.validation("No orphaned files found")?;
+            return Ok(());
+        }
+        
+        let mut tracked = HashSet::new();
+        for (tool, section) in self.distribution_parser.read_distribution()?.sections {
+            for entry in &section.files {
+                tracked.insert(self.paths.repo_file_path(&tool, &section.repo_file_name(entry)));
+            }
+        }
+        
+        let mut orphans = Vec::new();
+        for entry in WalkDir::new(&config_root).min_depth(1) {
+            let entry = entry?;
+            let is_tool_setting = entry.file_name() == DotIgnore::TOOLIGNORE_FILE || entry.file_name() == TEMPLATE_VARIABLES_FILE;
+            if entry.file_type().is_file() && !is_tool_setting && !tracked.contains(entry.path()) {
+                orphans.push(entry.into_path());
+            }
+        }
+        
+        for orphan in &orphans {
+            let display_path = orphan.strip_prefix(&self.paths.repo_dir).unwrap_or(orphan);
+            self.formatter.modified(&format!("Orphaned: {}", display_path.display()))?;
+        }
+        
+        if delete {
+            if orphans.len() > 10 && !yes {
+                return Err(DotfilesError::InvalidCommand(format!(
+                    "Refusing to delete {} files without --yes", orphans.len())).into());
+            }
+            for orphan in &orphans {
+                fs::remove_file(orphan)?;
+            }
+        }
+        
+        // Walk children before parents so nested empty directories are all found
+        let mut empty_dirs = 0;
+        for entry in WalkDir::new(&config_root).min_depth(1).contents_first(true) {
+            let entry = entry?;
+            if !entry.file_type().is_dir() || fs::read_dir(entry.path())?.next().is_some() {
+                continue;
+            }
+            
+            empty_dirs += 1;
+            let display_path = entry.path().strip_prefix(&self.paths.repo_dir).unwrap_or(entry.path());
+            if delete {
+                fs::remove_dir(entry.path())?;
+                self.formatter.action(&format!("Removed empty directory: {}", display_path.display()))?;
+            } else {
+                self.formatter.modified(&format!("Empty directory: {}", display_path.display()))?;
+            }
+        }
+        
+        if orphans.is_empty() && empty_dirs == 0 {
+            self.formatter.validation("No orphaned files found")?;
+        } else if delete {
+            self.formatter.summary(&format!("Removed {} orphaned files and {} empty directories", orphans.len(), empty_dirs))?;
+        } else {
+            self.formatter.summary(&format!("Found {} orphaned files and {} empty directories (use --delete to remove)", orphans.len(), empty_dirs))?;
+        }
+        
+        Ok(())
+    }
+    
+    // Remove sections without files (and disabled ones with `strict`) from the distribution file
+    fn run_gc(&mut self, dry_run: bool, yes: bool, remove_empty_dirs: bool, strict: bool) -> Result<()> {
+        self.formatter.header("Looking for empty sections...")?;
+        
+        // Included files aren't edited, so only look at this file's sections
+        let parser = DistributionParser::new(self.paths.distribution_file.clone());
+        let distribution = parser.read_without_includes()?;
+        
+        let mut sections = Vec::new();
+        let mut empty_dirs = Vec::new();
+        for (tool, section) in &distribution.sections {
+            if section.files.is_empty() {
+                self.formatter.modified(&format!("Empty section: {}", tool))?;
+            } else if strict && !section.enabled {
+                self.formatter.modified(&format!("Disabled section: {}", tool))?;
+            } else {
+                continue;
+            }
+            sections.push(tool.clone());
+            
+            let dir = self.paths.repo_config_dir(tool);
+            if remove_empty_dirs && dir.is_dir() && fs::read_dir(&dir)?.next().is_none() {
+                let display_path = dir.strip_prefix(&self.paths.repo_dir).unwrap_or(&dir);
+                self.formatter.modified(&format!("Empty directory: {}", display_path.display()))?;
+                empty_dirs.push(dir);
+            }
+        }
+        
+        if sections.is_empty() {
+            self.formatter.validation("No empty sections found")?;
+            return Ok(());
+        }
+        
+        if dry_run {
+            self.formatter.summary(&format!("Found {} empty sections, {} empty directories (run without --dry-run to remove)",
+                sections.len(), empty_dirs.len()))?;
+            return Ok(());
+        }
+        
+        if sections.len() > 1 && !yes {
+            self.formatter.print(&format!("Remove {} sections? [y/N]: ", sections.len()), Some(Color::Cyan), true)?;
+            self.formatter.stdout.flush()?;
+            
+            let answer = match std::io::stdin().lines().next() {
+                Some(line) => line?,
+                None => String::new(),
+            };
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                return Err(DotfilesError::Aborted("gc stopped by user".to_string()).into());
+            }
+        }
+        
+        parser.remove_sections(&sections)?;
+        for dir in &empty_dirs {
+            fs::remove_dir(dir)?;
+        }
+        
+        self.formatter.summary(&format!("Removed {} empty sections, {} empty directories", sections.len(), empty_dirs.len()))?;
+        Ok(())
+    }
+    
+    fn run_log(&mut self, last_n: Option<usize>, command_filter: Option<&str>, tool_filter: Option<&str>) -> Result<()> {
+        self.formatter.header("Operation history:")?;
+        
+        let mut entries: Vec<_> = self.operation_log.read()?.into_iter()
+            .filter(|entry| command_filter.is_none_or(|command| entry.command == command))
+            .filter(|entry| tool_filter.is_none_or(|tool| entry.tool() == tool))
+            .collect();
+        if let Some(last_n) = last_n {
+            entries = entries.split_off(entries.len().saturating_sub(last_n));
+        }
+        
+        if entries.is_empty() {
+            self.formatter.info("No operations recorded")?;
+            return Ok(());
+        }
+        
+        for entry in &entries {
+            self.formatter.print(&format!("  {} ", entry.timestamp), Some(Color::Cyan), false)?;
+            self.formatter.print(&format!("{} ", entry.hostname), None, false)?;
+            self.formatter.print(&format!("{:<8}", entry.command), Some(Color::White), true)?;
+            self.formatter.print(&format!("{} ", entry.path), None, false)?;
+            self.formatter.print(&entry.result, Some(Color::Green), false)?;
+            self.formatter.newline()?;
+        }
+        
+        Ok(())
+    }
+    
+    fn run_log_clear(&mut self) -> Result<()> {
+        let count = self.operation_log.read()?.len();
+        if count == 0 {
+            self.formatter.info("No operations recorded")?;
+            return Ok(());
+        }
+        
+        self.formatter.print(&format!("Clear {} entries from {}? [y/N]: ", count, OperationLog::FILE_NAME), Some(Color::Cyan), true)?;
+        self.formatter.stdout.flush()?;
+        
+        let answer = match std::io::stdin().lines().next() {
+            Some(line) => line?,
+            None => String::new(),
+        };
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            return Err(DotfilesError::Aborted("log not cleared".to_string()).into());
+        }
+        
+        self.operation_log.clear()?;
+        self.formatter.action(&format!("Cleared {} entries", count))?;
+        Ok(())
+    }
+    
+    fn run_undo(&mut self, steps: usize) -> Result<()> {
+        let pending = self.operation_log.pending()?;
+        if pending.is_empty() {
+            self.formatter.info("Nothing to undo")?;
+            return Ok(());
+        }
+        
+        for entry in pending.iter().rev().take(steps) {
+            self.undo_operation(entry)?;
+            self.operation_log.append("undo", entry.tool(), entry.file(), "undone", None)?;
+            self.formatter.action(&format!("Undid {}: {}", entry.command, entry.path))?;
+        }
+        
+        Ok(())
+    }
+    
+    // Put back what a single logged operation changed
+    fn undo_operation(&mut self, entry: &oplog::LogEntry) -> Result<()> {
+        let (tool, file) = (entry.tool(), entry.file());
+        let entries = self.distribution_parser.get_file_entries(tool)?;
+        let repo_file = FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all)
+            .with_renames(tool, &entries)
+            .with_encryption(&self.encrypted_tools).with_templates(&self.templates)
+            .repo_file(tool, file);
+        let undo_cache = self.operation_log.undo_cache();
+        let parser = DistributionParser::new(self.paths.distribution_file.clone());
+        
+        match (entry.command.as_str(), &entry.before) {
+            ("sync", Some(before)) => undo_cache.restore(before, &repo_file),
+            ("install", Some(before)) => undo_cache.restore(before, &self.paths.install_file_path(tool, file)),
+            ("add", before) => {
+                parser.remove_file(tool, file)?;
+                match before {
+                    Some(before) => undo_cache.restore(before, &repo_file),
+                    None => Ok(()),
+                }
+            },
+            ("remove", _) => parser.add_file(tool, file, &SectionMetadata::default()),
+            _ => Err(DotfilesError::InvalidCommand(format!("Can't undo {} of {}", entry.command, entry.path)).into()),
+        }
+    }
+    
+    // Check for common setup problems. Failed checks make the command fail;
+    // warnings don't
+    fn run_doctor(&mut self) -> Result<()> {
+        self.formatter.header("Checking dotfiles-rs setup...")?;
+        let mut issues = 0;
+        
+        let repo_dir = self.paths.repo_dir.clone();
+        if !repo_dir.is_dir() {
+            issues += 1;
+            self.formatter.error(&format!("Repository not found: {}", repo_dir.display()))?;
+        } else if !repo_dir.join(".git").exists() {
+            issues += 1;
+            self.formatter.error(&format!("Repository is not a git repository: {}", repo_dir.display()))?;
+        } else {
+            self.formatter.validation(&format!("Repository: {}", repo_dir.display()))?;
+        }
+        
+        let distribution = match self.distribution_parser.read_distribution() {
+            Ok(distribution) => {
+                self.formatter.validation("Distribution file exists and parses")?;
+                Some(distribution)
+            },
+            Err(e) => {
+                issues += 1;
+                self.formatter.error(&format!("Distribution file: {}", e))?;
+                None
+            },
+        };
+        
+        if matches!(self.mode, AppMode::EmbeddedMode) || self.paths.dotignore_file.exists() {
+            self.formatter.validation(".dotignore exists")?;
+        } else {
+            issues += 1;
+            self.formatter.error(&format!(".dotignore not found: {}", self.paths.dotignore_file.display()))?;
+        }
+        
+        if let Some(distribution) = &distribution {
+            let mut missing_dirs = 0;
+            let mut outside_links = 0;
+            for (tool, section) in distribution.sections.iter().filter(|(_, section)| section.enabled) {
+                let dir = self.paths.config_section_dir(tool);
+                if !dir.is_dir() {
+                    missing_dirs += 1;
+                    self.formatter.error(&format!("Config directory missing for {}: {} (run +bootstrap)", tool, dir.display()))?;
+                }
+                
+                if section.files.is_empty() {
+                    self.formatter.warning(&format!("Section has no files: {}", tool))?;
+                }
+                
+                for entry in &section.files {
+                    let config_file = self.paths.config_file_path(tool, entry.destination());
+                    if let Some(target) = Self::link_outside(&config_file, &repo_dir) {
+                        outside_links += 1;
+                        self.formatter.error(&format!("{}/{} links outside the repository: {}",
+                            tool, entry.destination(), target.display()))?;
+                    }
+                }
+            }
+            if missing_dirs == 0 {
+                self.formatter.validation("All config directories exist")?;
+            }
+            if outside_links == 0 {
+                self.formatter.validation("No tracked files link outside the repository")?;
+            }
+            issues += missing_dirs + outside_links;
+        }
+        
+        for program in ["dotfiles-rs", "git"] {
+            match util::find_in_path(program) {
+                Some(path) => self.formatter.validation(&format!("{} found: {}", program, path.display()))?,
+                None => {
+                    issues += 1;
+                    self.formatter.error(&format!("{} not found in PATH", program))?;
+                },
+            }
+        }
+        
+        self.formatter.newline()?;
+        if issues > 0 {
+            self.formatter.summary(&format!("{} issues found", issues))?;
+            return Err(anyhow!("{} issues found", issues));
+        }
+        self.formatter.summary("All checks passed")
+    }
+    
+    // Find tracked files that are symlinks to a missing target, e.g. after the
+    // repository was moved, and link them to the current repo copy again
+    fn run_repair(&mut self, dry_run: bool, remove_dangling: bool) -> Result<()> {
+        self.formatter.header("Looking for dangling symlinks...")?;
+        
+        let (mut dangling, mut failed) = (0, 0);
+        for (tool, section) in self.distribution_parser.read_distribution()?.sections {
+            if !section.enabled {
+                continue;
+            }
+            for entry in &section.files {
+                let config_file = self.paths.install_file_path(&tool, entry.destination());
+                let is_symlink = fs::symlink_metadata(&config_file).is_ok_and(|metadata| metadata.file_type().is_symlink());
+                if !is_symlink || config_file.exists() {
+                    continue;
+                }
+                
+                dangling += 1;
+                let display_path = format!("{}/{}", tool, entry.destination());
+                let old_target = fs::read_link(&config_file)?;
+                if dry_run {
+                    self.formatter.modified(&format!("Dangling: {} -> {}", display_path, old_target.display()))?;
+                    continue;
+                }
+                
+                let repo_file = self.paths.repo_file_path(&tool, &section.repo_file_name(entry));
+                if !remove_dangling && !repo_file.is_file() {
+                    failed += 1;
+                    self.formatter.error(&format!("Can't repair {}: not in the repository ({})", display_path, repo_file.display()))?;
+                    continue;
+                }
+                
+                let result = fs::remove_file(&config_file).and_then(|_| match remove_dangling {
+                    true => Ok(()),
+                    false => util::symlink(&repo_file, &config_file),
+                });
+                match result {
+                    Ok(()) if remove_dangling => self.formatter.success(&format!("Removed dangling symlink: {}", display_path))?,
+                    Ok(()) => self.formatter.success(&format!("Repaired: {} -> {}", display_path, repo_file.display()))?,
+                    Err(e) => {
+                        failed += 1;
+                        self.formatter.error(&format!("Can't repair {}: {}", display_path, e))?;
+                    },
+                }
+            }
+        }
+        
+        if dangling == 0 {
+            self.formatter.validation("No dangling symlinks found")?;
+        } else if dry_run {
+            self.formatter.summary(&format!("Found {} dangling symlinks (run without --dry-run to repair)", dangling))?;
+        } else {
+            let verb = if remove_dangling { "Removed" } else { "Repaired" };
+            self.formatter.summary(&format!("{} {} of {} dangling symlinks", verb, dangling - failed, dangling))?;
+        }
+        
+        if failed > 0 {
+            return Err(anyhow!("{} symlinks could not be repaired", failed));
+        }
+        Ok(())
+    }
+    
+    // The repository copy and install path of a tracked file, for +link and
+    // +unlink. Encrypted and template tools are refused because their
+    // repository copies aren't what install writes
+    fn link_paths(&mut self, tool: &str, file: &str) -> Result<(PathBuf, PathBuf)> {
+        let tools = self.distribution_parser.get_tools()?;
+        if !tools.contains(&tool.to_string()) {
+            return Err(DotfilesError::tool_not_found(tool, &tools).into());
+        }
+        let mut distribution = self.distribution_parser.read_distribution()?;
+        let section = distribution.sections.swap_remove(tool).ok_or_else(|| DotfilesError::tool_not_found(tool, &tools))?;
+        let Some(entry) = section.files.iter().find(|entry| entry.destination() == file) else {
+            return Err(DotfilesError::FileNotFound(format!("{}/{} is not in the distribution file", tool, file)).into());
+        };
+        if section.encrypted || self.templates.tools.contains(tool) {
+            return Err(DotfilesError::InvalidCommand(format!("Can't link {}: it is stored encrypted or as a template in the repo", tool)).into());
+        }
+        
+        let repo_file = self.paths.repo_file_path(tool, &section.repo_file_name(entry));
+        if !repo_file.is_file() {
+            return Err(DotfilesError::FileNotFound(format!("{}/{} is not in the repository ({})", tool, file, repo_file.display())).into());
+        }
+        Ok((repo_file, self.paths.install_file_path(tool, file)))
+    }
+    
+    fn run_link(&mut self, tool: &str, file: &str, relink: bool) -> Result<()> {
+        let (repo_file, config_file) = self.link_paths(tool, file)?;
+        let display_path = self.paths.tilde(&config_file);
+        
+        match fs::symlink_metadata(&config_file) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                let target = fs::read_link(&config_file)?;
+                let target = config_file.parent().map(|parent| parent.join(&target)).unwrap_or(target);
+                if target.canonicalize().ok() == repo_file.canonicalize().ok() {
+                    self.formatter.validation(&format!("Already linked: {}", display_path))?;
+                    return Ok(());
+                }
+                if !relink {
+                    return Err(DotfilesError::InvalidCommand(format!(
+                        "{} is a symlink to {} (use --relink to replace it)", display_path, target.display()
+                    )).into());
+                }
+                fs::remove_file(&config_file)?;
+            },
+            Ok(metadata) if metadata.is_dir() => {
+                return Err(DotfilesError::InvalidCommand(format!("{} is a directory", display_path)).into());
+            },
+            Ok(_) => {
+                let backup = util::unused_backup_path(&config_file);
+                fs::rename(&config_file, &backup)?;
+                self.formatter.modified(&format!("Backed up: {} -> {}", display_path, self.paths.tilde(&backup)))?;
+            },
+            Err(_) => {
+                if let Some(parent) = config_file.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+            },
+        }
+        
+        util::symlink(&repo_file, &config_file)?;
+        self.run_summary.installed += 1;
+        self.formatter.success(&format!("Linked: {} -> {}", display_path, repo_file.display()))?;
+        Ok(())
+    }
+    
+    fn run_unlink(&mut self, tool: &str, file: &str) -> Result<()> {
+        let (repo_file, config_file) = self.link_paths(tool, file)?;
+        let display_path = self.paths.tilde(&config_file);
+        
+        if !fs::symlink_metadata(&config_file).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return Err(DotfilesError::InvalidCommand(format!("{} is not a symlink", display_path)).into());
+        }
+        fs::remove_file(&config_file)?;
+        fs::copy(&repo_file, &config_file)?;
+        self.run_summary.installed += 1;
+        self.formatter.success(&format!("Unlinked: {} (now a copy of {})", display_path, repo_file.display()))?;
+        Ok(())
+    }
+    
+    // Where `path` points when it is a symlink to something outside `repo_dir`
+    fn link_outside(path: &Path, repo_dir: &Path) -> Option<PathBuf> {
+        if !fs::symlink_metadata(path).ok()?.file_type().is_symlink() {
+            return None;
+        }
+        let target = fs::read_link(path).ok()?;
+        let target = path.parent().map(|parent| parent.join(&target)).unwrap_or(target);
+        let resolved = target.canonicalize().unwrap_or_else(|_| target.clone());
+        let repo_dir = repo_dir.canonicalize().unwrap_or_else(|_| repo_dir.to_path_buf());
+        
+        (!resolved.starts_with(&repo_dir)).then_some(target)
+    }
+    
+    fn run_tags(&mut self) -> Result<()> {
+        self.formatter.header("Tags:")?;
+        
+        let counts = self.distribution_parser.tag_counts()?;
+        if counts.is_empty() {
+            self.formatter.info("No tools have tags")?;
+            return Ok(());
+        }
+        
+        for (tag, count) in counts {
+            self.formatter.print(&format!("  - {}: ", tag), Some(Color::White), true)?;
+            self.formatter.print(&format!("{} {}", count, if count == 1 { "tool" } else { "tools" }), None, false)?;
+            self.formatter.newline()?;
+        }
+        
+        Ok(())
+    }
+    
+    fn run_info(&mut self, tool: &str) -> Result<()> {
+        let distribution = self.distribution_parser.read_distribution()?;
+        let Some(section) = distribution.sections.get(tool) else {
+            return Err(DotfilesError::tool_not_found(tool, distribution.sections.keys()).into());
+        };
+        
+        self.formatter.header(tool)?;
+        let mut fields = Vec::new();
+        if let Some(description) = &section.description {
+            fields.push(("Description", description.clone()));
+        }
+        if let Some(url) = &section.url {
+            fields.push(("URL", url.clone()));
+        }
+        fields.push(("State", if section.enabled { "enabled" } else { "disabled" }.to_string()));
+        fields.push(("Priority", section.priority.to_string()));
+        if section.encrypted {
+            fields.push(("Encrypted", section.gpg_key_id.clone().unwrap_or_else(|| "default key".to_string())));
+        }
+        if !section.tags.is_empty() {
+            fields.push(("Tags", section.tags.join(", ")));
+        }
+        fields.push(("Repository", self.paths.repo_config_dir(tool).display().to_string()));
+        fields.push(("Files", section.files.len().to_string()));
+        
+        for (name, value) in fields {
+            self.formatter.print(&format!("  {}: ", name), Some(Color::Cyan), false)?;
+            self.formatter.print(&value, None, false)?;
+            self.formatter.newline()?;
+        }
+        
+        for (file, outcome) in self.file_statuses(tool, &section.files)? {
+            self.formatter.print(&format!("    - {}: ", file), Some(Color::White), false)?;
+            self.formatter.print(outcome.label(), Some(Color::Cyan), false)?;
+            self.formatter.newline()?;
+        }
+        
+        Ok(())
+    }
+    
+    // Copy each tracked file from the same path under the home directory of
+    // `host`. Files outside the home directory are fetched by absolute path
+    fn run_fetch(&mut self, host: &SshHost, to_repo: bool) -> Result<()> {
+        if util::find_in_path("scp").is_none() {
+            return Err(DotfilesError::InvalidCommand("scp was not found on PATH".to_string()).into());
+        }
+        
+        self.formatter.header(&format!("Fetching dotfiles from {}...", host.host))?;
+        let (mut fetched, mut failed) = (0, 0);
+        for tool in self.filtered_tools()? {
+            if to_repo && (self.encrypted_tools.contains_key(&tool) || self.templates.tools.contains(&tool)) {
+                self.formatter.warning(&format!("Skipped (stored encrypted or as a template in the repo): {}", tool))?;
+                continue;
+            }
+            
+            let entries = self.distribution_parser.get_file_entries(&tool)?;
+            let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(&tool))?;
+            let file_manager = FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore)
+                .with_renames(&tool, &entries);
+            let targets: Vec<(String, PathBuf, PathBuf)> = entries.iter()
+                .map(|entry| entry.destination())
+                .filter(|file| !file_manager.is_file_ignored(file))
+                .map(|file| {
+                    let config_file = self.paths.config_file_path(&tool, file);
+                    let target = if to_repo { file_manager.repo_file(&tool, file) } else { config_file.clone() };
+                    (file.to_string(), config_file, target)
+                })
+                .collect();
+            
+            for (file, config_file, target) in targets {
+                let display_path = format!("{}/{}", tool, file);
+                self.formatter.info(&format!("Fetching from {}: {}", host.host, display_path))?;
+                
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let output = host.scp().arg(host.remote(&self.paths.home_relative(&config_file))).arg(&target).output();
+                
+                match output {
+                    Ok(output) if output.status.success() => {
+                        self.formatter.success(&format!("Fetched: {}", self.paths.tilde(&target)))?;
+                        fetched += 1;
+                    },
+                    Ok(output) => {
+                        let stderr = String::from_utf8_lossy(&output.stderr);
+                        let reason = stderr.lines().last().map(str::to_string)
+                            .unwrap_or_else(|| format!("scp exited with {}", output.status));
+                        self.formatter.error(&format!("Failed to fetch {}: {}", display_path, reason))?;
+                        failed += 1;
+                    },
+                    Err(e) => {
+                        self.formatter.error(&format!("Failed to fetch {}: {}", display_path, e))?;
+                        failed += 1;
+                    },
+                }
+            }
+        }
+        
+        self.formatter.summary(&format!("Summary: {} fetched, {} failed", fetched, failed))?;
+        if failed > 0 {
+            return Err(DotfilesError::InvalidCommand(format!("{} files could not be fetched from {}", failed, host.host)).into());
+        }
+        Ok(())
+    }
+    
+    fn run_push(&mut self, host: &SshHost, dry_run: bool, diff_first: bool) -> Result<()> {
+        for program in ["ssh", "scp"] {
+            if util::find_in_path(program).is_none() {
+                return Err(DotfilesError::InvalidCommand(format!("{} was not found on PATH", program)).into());
+            }
+        }
+        
+        if dry_run {
+            self.formatter.header(&format!("Pushing dotfiles to {} (dry run)...", host.host))?;
+        } else {
+            self.formatter.header(&format!("Pushing dotfiles to {}...", host.host))?;
+        }
+        let (mut pushed, mut failed) = (0, 0);
+        for tool in self.filtered_tools()? {
+            self.formatter.info(&format!("Processing tool: {}", tool))?;
+            
+            let entries = self.distribution_parser.get_file_entries(&tool)?;
+            let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(&tool))?;
+            let mut file_manager = match self.mode {
+                AppMode::FilesystemMode => FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+                AppMode::EmbeddedMode => FileManager::from_embedded(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+            }.with_renames(&tool, &entries).with_encryption(&self.encrypted_tools).with_templates(&self.templates);
+            
+            // Create the directories on the host in one ssh call per tool
+            if !dry_run {
+                let mut dirs: Vec<String> = entries.iter()
+                    .filter_map(|entry| self.paths.install_file_path(&tool, entry.destination()).parent().map(Path::to_path_buf))
+                    .map(|dir| util::shell_quote(&self.paths.home_relative(&dir).to_string_lossy()))
+                    .collect();
+                dirs.sort();
+                dirs.dedup();
+                let created = host.ssh(&format!("mkdir -p {}", dirs.join(" "))).output()
+                    .is_ok_and(|output| output.status.success());
+                if !dirs.is_empty() && !created {
+                    file_manager.formatter.error(&format!("Failed to create the directories of {} on {}", tool, host.host))?;
+                    failed += entries.len();
+                    continue;
+                }
+            }
+            
+            for entry in &entries {
+                match file_manager.push_file(host, &tool, entry.destination(), dry_run, diff_first) {
+                    Ok(true) => pushed += 1,
+                    Ok(false) => {},
+                    Err(e) => {
+                        file_manager.formatter.error(&format!("Failed to push {}/{}: {}", tool, entry.destination(), e))?;
+                        failed += 1;
+                    },
+                }
+            }
+        }
+        
+        let verb = if dry_run { "would be pushed" } else { "pushed" };
+        self.formatter.summary(&format!("Summary: {} {}, {} failed", pushed, verb, failed))?;
+        if failed > 0 {
+            return Err(DotfilesError::InvalidCommand(format!("{} files could not be pushed to {}", failed, host.host)).into());
+        }
+        Ok(())
+    }
+    
+    fn run_where(&mut self, tool: &str, file: &str, open: bool, only: Option<PathKind>) -> Result<()> {
+        let tools = self.distribution_parser.get_tools()?;
+        if !tools.contains(&tool.to_string()) {
+            return Err(DotfilesError::tool_not_found(tool, &tools).into());
+        }
+        let entries = self.distribution_parser.get_file_entries(tool)?;
+        if !entries.iter().any(|entry| entry.destination() == file) {
+            return Err(DotfilesError::FileNotFound(format!("{}/{} is not in the distribution file", tool, file)).into());
+        }
+        
+        let repo_file = FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all)
+            .with_renames(tool, &entries)
+            .with_encryption(&self.encrypted_tools).with_templates(&self.templates)
+            .repo_file(tool, file);
+        let config_file = self.paths.install_file_path(tool, file);
+        
+        match only {
+            // Full paths without decoration, for use in scripts
+            Some(PathKind::Repo) => println!("{}", repo_file.display()),
+            Some(PathKind::Config) => println!("{}", config_file.display()),
+            None => {
+                for (label, path) in [("Repo:   ", &repo_file), ("Config: ", &config_file)] {
+                    self.formatter.print(label, Some(Color::Cyan), false)?;
+                    self.formatter.print(&format!("{} ", self.paths.tilde(path)), None, false)?;
+                    if path.exists() {
+                        self.formatter.print(CHECK_MARK, Some(Color::Green), false)?;
+                    } else {
+                        self.formatter.print(&format!("{} (missing)", CROSS_MARK), Some(Color::Red), false)?;
+                    }
+                    self.formatter.newline()?;
+                }
+            },
+        }
+        
+        if open {
+            let path = if only == Some(PathKind::Config) { config_file } else { repo_file };
+            util::open_in_editor(&path)?;
+        }
+        
+        Ok(())
+    }
+    
+    fn run_touch(&mut self, tool: &str, file: &str, side: TouchSide) -> Result<()> {
+        let tools = self.distribution_parser.get_tools()?;
+        if !tools.contains(&tool.to_string()) {
+            return Err(DotfilesError::tool_not_found(tool, &tools).into());
+        }
+        let entries = self.distribution_parser.get_file_entries(tool)?;
+        if !entries.iter().any(|entry| entry.destination() == file) {
+            return Err(DotfilesError::FileNotFound(format!("{}/{} is not in the distribution file", tool, file)).into());
+        }
+        
+        let repo_file = FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all)
+            .with_renames(tool, &entries)
+            .with_encryption(&self.encrypted_tools).with_templates(&self.templates)
+            .repo_file(tool, file);
+        let paths = match side {
+            TouchSide::Config => vec![self.paths.config_file_path(tool, file)],
+            TouchSide::Repo => vec![repo_file],
+            TouchSide::Both => vec![self.paths.config_file_path(tool, file), repo_file],
+        };
+        
+        let mut failed = 0;
+        for path in &paths {
+            let display_path = self.paths.tilde(path);
+            match fs::File::open(path).and_then(|f| f.set_modified(SystemTime::now())) {
+                Ok(()) => self.formatter.action(&format!("Touched: {}", display_path))?,
+                Err(e) => {
+                    self.formatter.error(&format!("Failed to touch {}: {}", display_path, e))?;
+                    failed += 1;
+                },
+            }
+        }
+        
+        if failed > 0 {
+            return Err(DotfilesError::InvalidCommand(format!("{} of {} files could not be touched", failed, paths.len())).into());
+        }
+        Ok(())
+    }
+    
+    fn run_stats(&mut self, json: bool) -> Result<()> {
+        let stats = self.repo_stats()?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+            return Ok(());
+        }
+        
+        self.formatter.header("Repository statistics:")?;
+        let mut rows = vec![
+            vec!["Tools".to_string(), stats.tools.to_string()],
+            vec!["Disabled tools".to_string(), stats.disabled_tools.to_string()],
+            vec!["Files".to_string(), stats.files.to_string()],
+            vec!["Files in disabled tools".to_string(), stats.disabled_files.to_string()],
+            vec!["Ignored files".to_string(), stats.ignored_files.to_string()],
+            vec!["Missing in repo".to_string(), stats.missing_files.to_string()],
+            vec!["Total size".to_string(), util::format_size(stats.total_size)],
+        ];
+        if let Some(tool) = &stats.largest_tool {
+            rows.push(vec!["Largest tool".to_string(), tool.clone()]);
+        }
+        if let Some(file) = &stats.largest_file {
+            rows.push(vec!["Largest file".to_string(), format!("{} ({})", file.path, util::format_size(file.size))]);
+        }
+        if let Some(file) = &stats.newest_file {
+            rows.push(vec!["Most recently modified".to_string(), format!("{} ({})", file.path, file.modified)]);
+        }
+        self.formatter.table(&["Metric", "Value"], &rows, &[Some(Color::Cyan), None])?;
+        
+        if !stats.per_tool.is_empty() {
+            self.formatter.newline()?;
+            let rows: Vec<Vec<String>> = stats.per_tool.iter()
+                .map(|tool| vec![tool.tool.clone(), tool.files.to_string(), util::format_size(tool.size)])
+                .collect();
+            self.formatter.table(&["Tool", "Files", "Size"], &rows, &[Some(Color::White), None, Some(Color::Cyan)])?;
+        }
+        
+        Ok(())
+    }
+    
+    // Gather +stats from the metadata of the repository copies, without
+    // reading any file contents
+    fn repo_stats(&self) -> Result<RepoStats> {
+        let distribution = self.distribution_parser.read_distribution()?;
+        let mut stats = RepoStats::default();
+        
+        for (tool, section) in &distribution.sections {
+            if !section.enabled {
+                stats.disabled_tools += 1;
+                stats.disabled_files += section.files.len();
+                continue;
+            }
+            stats.tools += 1;
+            
+            let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(tool))?;
+            let mut tool_stats = ToolStats { tool: tool.clone(), files: 0, size: 0 };
+            for entry in &section.files {
+                let file = entry.destination();
+                if self.dotignore.is_ignored(file) || tool_ignore.is_ignored(file) {
+                    stats.ignored_files += 1;
+                    continue;
+                }
+                
+                let name = if section.encrypted { format!("{}.gpg", entry.source()) } else { entry.source().to_string() };
+                let Ok(metadata) = fs::metadata(self.paths.repo_file_path(tool, &name)) else {
+                    stats.missing_files += 1;
+                    continue;
+                };
+                let mtime = metadata.modified()?;
+                let file_stats = FileStats {
+                    path: format!("{}/{}", tool, name),
+                    size: metadata.len(),
+                    modified: chrono::DateTime::<chrono::Local>::from(mtime).format("%Y-%m-%d %H:%M").to_string(),
+                    mtime,
+                };
+                
+                tool_stats.files += 1;
+                tool_stats.size += file_stats.size;
+                if stats.largest_file.as_ref().is_none_or(|largest| file_stats.size > largest.size) {
+                    stats.largest_file = Some(file_stats.clone());
+                }
+                if stats.newest_file.as_ref().is_none_or(|newest| file_stats.mtime > newest.mtime) {
+                    stats.newest_file = Some(file_stats);
+                }
+            }
+            
+            stats.files += tool_stats.files;
+            stats.total_size += tool_stats.size;
+            stats.per_tool.push(tool_stats);
+        }
+        
+        stats.per_tool.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.tool.cmp(&b.tool)));
+        stats.largest_tool = stats.per_tool.first().filter(|tool| tool.size > 0).map(|tool| tool.tool.clone());
+        Ok(stats)
+    }
+    
+    fn run_fmt(&mut self) -> Result<()> {
+        let parser = DistributionParser::new(self.paths.distribution_file.clone());
+        if parser.format_file()? {
+            self.formatter.action(&format!("Formatted {}", self.paths.distribution_file.display()))?;
+        } else {
+            self.formatter.validation(&format!("{} is already formatted", self.paths.distribution_file.display()))?;
+        }
+        
+        Ok(())
+    }
+    
+    fn run_set_enabled(&mut self, tool: &str, enabled: bool) -> Result<()> {
+        let parser = DistributionParser::new(self.paths.distribution_file.clone());
+        parser.set_enabled(tool, enabled)?;
+        
+        if enabled {
+            self.formatter.action(&format!("Enabled: {}", tool))?;
+        } else {
+            self.formatter.action(&format!("Disabled: {}", tool))?;
+        }
+        Ok(())
+    }
+    
+    fn run_ignore(&mut self, action: &IgnoreAction) -> Result<()> {
+        // Always work on the file in the repository, even when running from the embedded archive
+        let path = self.paths.dotignore_file.clone();
+        self.dotignore = DotIgnore::from_global_and_local(&self.paths.global_dotignore_file, &path)?;
+        
+        match action {
+            IgnoreAction::Add { pattern } => {
+                self.dotignore.add_pattern_to_file(&path, pattern)?;
+                self.formatter.action(&format!("Added to .dotignore: {}", pattern))?;
+            },
+            IgnoreAction::Remove { pattern } => {
+                if self.dotignore.remove_pattern_from_file(&path, pattern)? {
+                    self.formatter.action(&format!("Removed from .dotignore: {}", pattern))?;
+                } else {
+                    self.formatter.warning(&format!("Pattern not found in .dotignore: {}", pattern))?;
+                }
+            },
+            IgnoreAction::List => {
+                self.formatter.header(&format!("Patterns in {}:", path.display()))?;
+                if !path.exists() {
+                    self.formatter.info("No .dotignore file; the default patterns apply")?;
+                    return Ok(());
+                }
+                
+                let content = fs::read_to_string(&path)?;
+                for (line_number, line) in content.lines().enumerate() {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    self.formatter.print(&format!("  {:>4}: ", line_number + 1), Some(Color::Cyan), false)?;
+                    self.formatter.print(line, None, false)?;
+                    self.formatter.newline()?;
+                }
+            },
+            IgnoreAction::Test { filename } => {
+                match self.dotignore.explain(filename) {
+                    Some(pattern) if self.dotignore.is_ignored(filename) => {
+                        self.formatter.warning(&format!("Ignored: {} (matched pattern: `{}`)", filename, pattern))?;
+                    },
+                    Some(pattern) => {
+                        self.formatter.validation(&format!("Not ignored: {} (un-ignored by `{}`)", filename, pattern))?;
+                    },
+                    None => {
+                        self.formatter.validation(&format!("Not ignored: {} (no pattern matches)", filename))?;
+                    },
+                }
+            },
+        }
+        
+        Ok(())
+    }
+    
+    fn run_profile(&mut self, action: &ProfileAction) -> Result<()> {
+        match action {
+            ProfileAction::List => {
+                self.formatter.header("Profiles:")?;
+                let profiles = self.distribution_parser.profiles()?;
+                if profiles.is_empty() {
+                    self.formatter.info("No profiles in the distribution file")?;
+                    return Ok(());
+                }
+                
+                for (name, tools) in profiles {
+                    self.formatter.print(&format!("  - {}: ", name), Some(Color::White), true)?;
+                    self.formatter.print(&format!("{} {}", tools.len(), if tools.len() == 1 { "tool" } else { "tools" }), None, false)?;
+                    self.formatter.newline()?;
+                }
+            },
+            ProfileAction::Show { name } => {
+                let tools = self.distribution_parser.profile_tools(name)?;
+                self.formatter.header(&format!("Tools in profile {}:", name))?;
+                for tool in tools {
+                    self.formatter.print(&format!("  - {}", tool), None, false)?;
+                    self.formatter.newline()?;
+                }
+            },
+            ProfileAction::AddTool { profile, tool } => {
+                let parser = DistributionParser::new(self.paths.distribution_file.clone());
+                if parser.add_profile_tool(profile, tool)? {
+                    self.formatter.action(&format!("Added to profile {}: {}", profile, tool))?;
+                } else {
+                    self.formatter.warning(&format!("Profile {} already has {}", profile, tool))?;
+                }
+            },
+            ProfileAction::RemoveTool { profile, tool } => {
+                let parser = DistributionParser::new(self.paths.distribution_file.clone());
+                if parser.remove_profile_tool(profile, tool)? {
+                    self.formatter.action(&format!("Removed from profile {}: {}", profile, tool))?;
+                } else {
+                    self.formatter.warning(&format!("Profile {} doesn't have {}", profile, tool))?;
+                }
+            },
+        }
+        
+        Ok(())
+    }
+    
+    // Entries for the repository's .gitignore: files dotfiles-rs and editors
+    // leave behind, plus the extension patterns from .dotignore so sensitive
+    // files that slip into the repository aren't committed
+    fn gitignore_entries(&self) -> Result<Vec<String>> {
+        let mut entries: Vec<String> = ["*.swp", "*.bak", "*.tmp", "backups/", "stash/", "snapshots/"].iter()
+            .map(|entry| entry.to_string())
+            .collect();
+        entries.push(format!("{}/", UndoCache::DIR_NAME));
+        entries.push(OperationLog::FILE_NAME.to_string());
+        
+        let dotignore = DotIgnore::new(&self.paths.dotignore_file)?;
+        for rule in dotignore.patterns.iter().filter(|rule| !rule.negate) {
+            let is_extension = rule.raw.strip_prefix("*.")
+                .is_some_and(|ext| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+            if is_extension && !entries.contains(&rule.raw) {
+                entries.push(rule.raw.clone());
+            }
+        }
+        
+        Ok(entries)
+    }
+    
+    fn run_gitignore(&mut self, append: bool, dry_run: bool) -> Result<()> {
+        let path = self.paths.repo_dir.join(".gitignore");
+        let entries = self.gitignore_entries()?;
+        
+        let existing = if append && path.exists() { fs::read_to_string(&path)? } else { String::new() };
+        let missing: Vec<&String> = entries.iter()
+            .filter(|entry| !existing.lines().any(|line| line.trim() == entry.as_str()))
+            .collect();
+        
+        if missing.is_empty() {
+            self.formatter.info(&format!("Nothing to add to {}", path.display()))?;
+            return Ok(());
+        }
+        
+        if dry_run {
+            for entry in &missing {
+                self.formatter.info(&format!("Would add to .gitignore: {}", entry))?;
+            }
+            return Ok(());
+        }
+        
+        let mut content = existing;
+        if content.is_empty() {
+            content.push_str("# Generated by dotfiles-rs +gitignore\n");
+        } else if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        for entry in &missing {
+            content.push_str(entry);
+            content.push('\n');
+        }
+        fs::write(&path, content)?;
+        
+        for entry in &missing {
+            self.formatter.action(&format!("Added to .gitignore: {}", entry))?;
+        }
+        Ok(())
+    }
+    
+    fn run_sort(&mut self) -> Result<()> {
+        let parser = DistributionParser::new(self.paths.distribution_file.clone());
+        parser.sort()?;
+        self.formatter.action(&format!("Sorted distribution file: {}", self.paths.distribution_file.display()))?;
+        Ok(())
+    }
+    
+    fn run_update(&mut self, tool: Option<&str>, add: bool, interactive: bool, recursive: bool) -> Result<()> {
+        self.formatter.header("Looking for untracked files...")?;
+        
+        let mut tools = self.distribution_parser.get_tools()?;
+        if let Some(tool) = tool {
+            if !tools.iter().any(|t| t == tool) {
+                return Err(DotfilesError::tool_not_found(tool, &tools).into());
+            }
+            tools = vec![tool.to_string()];
+        }
+        
+        let mut untracked_count = 0;
+        for tool in tools {
+            let tracked = self.distribution_parser.get_files(&tool)?;
+            let config_dir = self.paths.config_section_dir(&tool);
+            if !config_dir.is_dir() {
+                self.formatter.verbose(&format!("Config directory for '{}' does not exist", tool))?;
+                continue;
+            }
+            
+            let max_depth = if recursive { usize::MAX } else { 1 };
+            let mut untracked = Vec::new();
+            for entry in WalkDir::new(&config_dir).min_depth(1).max_depth(max_depth).sort_by_file_name() {
+                let entry = entry?;
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let file = entry.path().strip_prefix(&config_dir)?.to_string_lossy().to_string();
+                if !tracked.contains(&file) {
+                    untracked.push(file);
+                }
+            }
+            
+            let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(&tool))?;
+            let mut file_manager = FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore)
+                .with_encryption(&self.encrypted_tools).with_templates(&self.templates);
+            for file in untracked {
+                if file_manager.is_file_ignored(&file) {
+                    continue;
+                }
+                
+                untracked_count += 1;
+                file_manager.formatter.modified(&format!("Untracked: {}/{}", tool, file))?;
+                
+                let track = if interactive {
+                    file_manager.formatter.print("Track this file? [y/N/q]: ", Some(Color::Cyan), true)?;
+                    file_manager.formatter.stdout.flush()?;
+                    
+                    // Treat end of input as no
+                    let answer = match std::io::stdin().lines().next() {
+                        Some(line) => line?,
+                        None => "n".to_string(),
+                    };
+                    match answer.trim().to_lowercase().as_str() {
+                        "y" | "yes" => true,
+                        "q" => return Err(DotfilesError::Aborted("update stopped by user".to_string()).into()),
+                        _ => false,
+                    }
+                } else {
+                    add
+                };
+                
+                if track {
+                    file_manager.add_file(&tool, &file, &SectionMetadata::default())?;
+                }
+            }
+        }
+        
+        if untracked_count == 0 {
+            self.formatter.validation("No untracked files found")?;
+        } else {
+            self.formatter.summary(&format!("Found {} untracked files", untracked_count))?;
+        }
+        
+        Ok(())
+    }
+    
+    fn stash_root(&self) -> PathBuf {
+        self.paths.repo_dir.join("stash")
+    }
+    
+    fn run_stash(&mut self) -> Result<()> {
+        let now = chrono::Utc::now();
+        let stash_id = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let stash_dir = self.stash_root().join(&stash_id);
+        
+        let mut files = Vec::new();
+        for tool in self.distribution_parser.get_tools()? {
+            if !self.distribution_parser.is_enabled(&tool)? {
+                continue;
+            }
+            for file in self.distribution_parser.get_files(&tool)? {
+                let config_file = self.paths.install_file_path(&tool, &file);
+                if !config_file.is_file() {
+                    continue;
+                }
+                
+                let stash_file = stash_dir.join(&tool).join(&file);
+                if let Some(parent) = stash_file.parent() {
+                    create_dir_all(parent)?;
+                }
+                fs::copy(&config_file, &stash_file)?;
+                self.formatter.verbose(&format!("Stashed: {}/{}", tool, file))?;
+                files.push(format!("{}/{}", tool, file));
+            }
+        }
+        
+        if files.is_empty() {
+            self.formatter.warning("Nothing to stash: no tracked files are installed")?;
+            return Ok(());
+        }
+        
+        let manifest = StashManifest {
+            timestamp: now.to_rfc3339(),
+            hostname: hostname::get()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|_| "unknown".to_string()),
+            files,
+        };
+        let content = toml::to_string(&manifest).map_err(|e| anyhow!("Failed to serialize stash manifest: {}", e))?;
+        fs::write(stash_dir.join(StashManifest::FILE_NAME), content)?;
+        
+        self.formatter.action(&format!("Stashed {} files as {}", manifest.files.len(), stash_id))?;
+        Ok(())
+    }
+    
+    fn run_unstash(&mut self, stash_id: Option<&str>, list: bool, drop: Option<&str>) -> Result<()> {
+        if let Some(stash_id) = drop {
+            let stash_dir = self.stash_dir(stash_id)?;
+            fs::remove_dir_all(&stash_dir)?;
+            self.formatter.action(&format!("Dropped stash: {}", stash_id))?;
+            return Ok(());
+        }
+        
+        let stash_id = match stash_id {
+            Some(stash_id) if !list => stash_id,
+            _ => return self.list_stashes(),
+        };
+        
+        let stash_dir = self.stash_dir(stash_id)?;
+        let manifest = StashManifest::load(&stash_dir)?;
+        for entry in &manifest.files {
+            let Some((tool, file)) = entry.split_once('/') else { continue };
+            let config_file = self.paths.install_file_path(tool, file);
+            if let Some(parent) = config_file.parent() {
+                create_dir_all(parent)?;
+            }
+            fs::copy(stash_dir.join(tool).join(file), &config_file)?;
+            self.formatter.installed(&format!("Restored: {}", entry))?;
+        }
+        
+        self.formatter.summary(&format!("Restored {} files from stash {}", manifest.files.len(), stash_id))?;
+        Ok(())
+    }
+    
+    fn list_stashes(&mut self) -> Result<()> {
+        self.formatter.header("Stashes:")?;
+        
+        let stash_root = self.stash_root();
+        let mut stash_ids: Vec<String> = match fs::read_dir(&stash_root) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().join(StashManifest::FILE_NAME).exists())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        stash_ids.sort();
+        
+        if stash_ids.is_empty() {
+            self.formatter.info("No stashes found")?;
+            return Ok(());
+        }
+        
+        for stash_id in stash_ids {
+            let manifest = StashManifest::load(&stash_root.join(&stash_id))?;
+            self.formatter.print(&format!("  - {}: ", stash_id), Some(Color::White), true)?;
+            self.formatter.print(&format!("{} ({} files from {})", manifest.timestamp, manifest.files.len(), manifest.hostname), None, false)?;
+            self.formatter.newline()?;
+        }
+        
+        Ok(())
+    }
+    
+    // Resolve a stash id to its directory, rejecting anything that isn't a plain name
+    fn stash_dir(&self, stash_id: &str) -> Result<PathBuf> {
+        let stash_dir = self.stash_root().join(stash_id);
+        if stash_id.contains(['/', '\\']) || stash_id.starts_with('.') || !stash_dir.is_dir() {
+            return Err(DotfilesError::InvalidCommand(format!("Stash not found: {}", stash_id)).into());
+        }
+        Ok(stash_dir)
+    }
+    
+    fn snapshot_root(&self) -> PathBuf {
+        self.paths.repo_dir.join("snapshots")
+    }
+    
+    // Resolve a snapshot name to its directory, which may not exist yet
+    fn snapshot_dir(&self, name: &str) -> Result<PathBuf> {
+        if !SNAPSHOT_NAME.is_match(name) {
+            return Err(DotfilesError::InvalidCommand(format!(
+                "Invalid snapshot name: '{}' (use letters, digits, '.', '-' and '_')", name)).into());
+        }
+        Ok(self.snapshot_root().join(name))
+    }
+    
+    // Where a snapshot keeps its copy of the distribution file
+    fn snapshot_distribution_file(&self, snapshot_dir: &Path) -> PathBuf {
+        let file_name = self.paths.distribution_file.file_name().unwrap_or_else(|| "distribution.toml".as_ref());
+        snapshot_dir.join(file_name)
+    }
+    
+    // Files named in [_includes], relative to the repository. A snapshot
+    // can't hold an include from outside the repository, so that is an error
+    fn snapshot_include_files(&self) -> Result<Vec<PathBuf>> {
+        let parser = DistributionParser::new(self.paths.distribution_file.clone());
+        let repo_dir = self.paths.repo_dir.canonicalize()?;
+        let mut files = Vec::new();
+        for (path, _) in parser.read_includes(&parser.read_without_includes()?)? {
+            let relative = path.canonicalize()?.strip_prefix(&repo_dir).map(Path::to_path_buf)
+                .map_err(|_| DotfilesError::InvalidCommand(format!(
+                    "Can't snapshot {}: included files must be inside the repository", path.display())))?;
+            files.push(relative);
+        }
+        Ok(files)
+    }
+    
+    // Copy config/, the distribution file and its included files into
+    // `snapshot_dir`; returns the number of files copied. Symlinks are kept
+    // as symlinks
+    fn write_snapshot(&self, snapshot_dir: &Path) -> Result<usize> {
+        let include_files = self.snapshot_include_files()?;
+        let config_root = self.paths.repo_dir.join("config");
+        let mut copied = 0;
+        if config_root.is_dir() {
+            copied += util::copy_tree(&config_root, &snapshot_dir.join("config"))?;
+        }
+        
+        create_dir_all(snapshot_dir)?;
+        fs::copy(&self.paths.distribution_file, self.snapshot_distribution_file(snapshot_dir))?;
+        for file in &include_files {
+            let target = snapshot_dir.join("includes").join(file);
+            if let Some(parent) = target.parent() {
+                create_dir_all(parent)?;
+            }
+            fs::copy(self.paths.repo_dir.join(file), &target)?;
+        }
+        Ok(copied + 1 + include_files.len())
+    }
+    
+    fn run_snapshot(&mut self, name: &str) -> Result<()> {
+        let snapshot_dir = self.snapshot_dir(name)?;
+        if snapshot_dir.exists() {
+            return Err(DotfilesError::InvalidCommand(format!("Snapshot '{}' already exists", name)).into());
+        }
+        
+        let copied = self.write_snapshot(&snapshot_dir)?;
+        self.formatter.action(&format!("Saved {} files as snapshot {}", copied, name))?;
+        Ok(())
+    }
+    
+    fn run_snapshot_list(&mut self) -> Result<()> {
+        self.formatter.header("Snapshots:")?;
+        
+        let mut snapshots: Vec<(String, PathBuf)> = match fs::read_dir(self.snapshot_root()) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path()))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        snapshots.sort();
+        
+        if snapshots.is_empty() {
+            self.formatter.info("No snapshots found")?;
+            return Ok(());
+        }
+        
+        for (name, dir) in snapshots {
+            let metadata = fs::metadata(&dir)?;
+            let created: chrono::DateTime<chrono::Local> = metadata.created().or_else(|_| metadata.modified())?.into();
+            let mut size = 0;
+            for entry in WalkDir::new(&dir) {
+                let entry = entry?;
+                if entry.file_type().is_file() {
+                    size += entry.metadata()?.len();
+                }
+            }
+            
+            self.formatter.print(&format!("  - {}: ", name), Some(Color::White), true)?;
+            self.formatter.print(&format!("{} ({})", created.format("%Y-%m-%d %H:%M"), util::format_size(size)), None, false)?;
+            self.formatter.newline()?;
+        }
+        
+        Ok(())
+    }
+    
+    // Replace config/ and the distribution file with the snapshot's copies,
+    // after saving the current state as another snapshot
+    fn run_snapshot_restore(&mut self, name: &str) -> Result<()> {
+        let snapshot_dir = self.snapshot_dir(name)?;
+        if !self.snapshot_distribution_file(&snapshot_dir).is_file() {
+            return Err(DotfilesError::InvalidCommand(format!("Snapshot not found: {}", name)).into());
+        }
+        
+        let backup_name = format!("before-restore-{}", chrono::Local::now().format("%Y%m%dT%H%M%S"));
+        let backup_dir = self.snapshot_dir(&backup_name)?;
+        self.write_snapshot(&backup_dir)?;
+        self.formatter.action(&format!("Saved current state as snapshot {}", backup_name))?;
+        
+        let config_root = self.paths.repo_dir.join("config");
+        if config_root.exists() {
+            fs::remove_dir_all(&config_root)?;
+        }
+        let snapshot_config = snapshot_dir.join("config");
+        let mut restored = 0;
+        if snapshot_config.is_dir() {
+            restored += util::copy_tree(&snapshot_config, &config_root)?;
+        }
+        fs::copy(self.snapshot_distribution_file(&snapshot_dir), &self.paths.distribution_file)?;
+        
+        // Included files are put back over the current ones; files the
+        // current distribution includes but the snapshot's doesn't are left
+        let snapshot_includes = snapshot_dir.join("includes");
+        if snapshot_includes.is_dir() {
+            restored += util::copy_tree(&snapshot_includes, &self.paths.repo_dir)?;
+        }
+        
+        self.formatter.summary(&format!("Restored {} files and the distribution file from snapshot {}", restored, name))?;
+        Ok(())
+    }
+    
+    fn run_snapshot_delete(&mut self, name: &str, yes: bool) -> Result<()> {
+        let snapshot_dir = self.snapshot_dir(name)?;
+        if !snapshot_dir.is_dir() {
+            return Err(DotfilesError::InvalidCommand(format!("Snapshot not found: {}", name)).into());
+        }
+        
+        if !yes {
+            self.formatter.print(&format!("Delete snapshot {}? [y/N]: ", name), Some(Color::Cyan), true)?;
+            self.formatter.stdout.flush()?;
+            
+            let answer = match std::io::stdin().lines().next() {
+                Some(line) => line?,
+                None => String::new(),
+            };
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                return Err(DotfilesError::Aborted("snapshot-delete stopped by user".to_string()).into());
+            }
+        }
+        
+        fs::remove_dir_all(&snapshot_dir)?;
+        self.formatter.action(&format!("Deleted snapshot: {}", name))?;
+        Ok(())
+    }
+    
+    fn run_new(&mut self, tool: &str, metadata: &SectionMetadata, config_dir: bool) -> Result<()> {
+        if tool.is_empty() || tool.starts_with('_') || tool.contains(['/', '\\']) || tool == "." || tool == ".." {
+            return Err(DotfilesError::InvalidCommand(format!("Invalid tool name: '{}'", tool)).into());
+        }
+        if self.distribution_parser.get_tools()?.iter().any(|name| name == tool) {
+            return Err(DotfilesError::InvalidCommand(format!("Tool '{}' already exists", tool)).into());
+        }
+        
+        let mut dirs = vec![self.paths.repo_config_dir(tool)];
+        if config_dir {
+            dirs.push(self.paths.config_section_dir(tool));
+        }
+        for dir in dirs {
+            if !dir.exists() {
+                create_dir_all(&dir)?;
+                self.formatter.action(&format!("Created directory: {}", dir.display()))?;
+            }
+        }
+        
+        let parser = DistributionParser::new(self.paths.distribution_file.clone());
+        parser.add_section(tool, metadata)?;
+        self.formatter.action(&format!("Added to distribution file: {}", tool))?;
+        self.formatter.info(&format!("Now run: dotfiles-rs +add {} <file>", tool))?;
+        Ok(())
+    }
+    
+    fn run_remove(&mut self, tool: &str, file: &str) -> Result<()> {
+        self.formatter.verbose(&format!("Removing file {}/{} from tracking", tool, file))?;
+        let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(tool))?;
+        let mut file_manager = FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore)
+            .with_encryption(&self.encrypted_tools).with_templates(&self.templates)
+            .with_operation_log(&self.operation_log);
+        file_manager.remove_file(tool, file)?;
+        self.formatter.verbose("File removed successfully")?;
+        Ok(())
+    }
+    
+    fn run_mv(&mut self, from_tool: &str, file: &str, to_tool: &str, new_file: Option<&str>, create_section: bool) -> Result<()> {
+        let entries = self.distribution_parser.get_file_entries(from_tool)?;
+        if !entries.iter().any(|entry| entry.destination() == file) {
+            return Err(DotfilesError::InvalidCommand(format!("{}/{} is not tracked", from_tool, file)).into());
+        }
+        let tools = self.distribution_parser.get_tools()?;
+        if !create_section && !tools.iter().any(|tool| tool == to_tool) {
+            return Err(DotfilesError::tool_not_found(to_tool, &tools).into());
+        }
+        
+        let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(from_tool))?;
+        let mut file_manager = FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore)
+            .with_renames(from_tool, &entries)
+            .with_retry_policy(self.retry_policy);
+        file_manager.move_file(from_tool, file, to_tool, new_file.unwrap_or(file))?;
+        
+        // The installed copy stays where the old section put it
+        self.formatter.warning("Run 'install' to update the live config location")
+    }
+    
+    fn run_cp(&mut self, tool: &str, file: &str, destination: &Path, force: bool) -> Result<()> {
+        let entries = self.distribution_parser.get_file_entries(tool)?;
+        if !entries.iter().any(|entry| entry.destination() == file) {
+            return Err(DotfilesError::InvalidCommand(format!("{}/{} is not tracked", tool, file)).into());
+        }
+        
+        let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(tool))?;
+        let mut file_manager = match self.mode {
+            AppMode::FilesystemMode => FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+            AppMode::EmbeddedMode => FileManager::from_embedded(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+        }.with_renames(tool, &entries).with_encryption(&self.encrypted_tools).with_templates(&self.templates);
+        file_manager.copy_to(tool, file, destination, force)?;
+        Ok(())
+    }
+    
+    fn run_template(&mut self, tool: &str, file: &str) -> Result<()> {
+        if !self.templates.tools.contains(tool) {
+            return Err(DotfilesError::InvalidCommand(format!("'{}' is not a template section (set template = true)", tool)).into());
+        }
+        let entries = self.distribution_parser.get_file_entries(tool)?;
+        if !entries.iter().any(|entry| entry.destination() == file) {
+            return Err(DotfilesError::InvalidCommand(format!("{}/{} is not tracked", tool, file)).into());
+        }
+        
+        let tool_ignore = DotIgnore::for_tool(&self.paths.repo_config_dir(tool))?;
+        let file_manager = match self.mode {
+            AppMode::FilesystemMode => FileManager::new(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+            AppMode::EmbeddedMode => FileManager::from_embedded(&self.paths, &mut self.formatter, &self.dotignore, self.show_all).with_tool_ignore(&tool_ignore),
+        }.with_renames(tool, &entries).with_encryption(&self.encrypted_tools).with_templates(&self.templates);
+        print!("{}", String::from_utf8_lossy(&file_manager.read_source(tool, file)?));
+        Ok(())
+    }
+    
+    // Draw every tool's files as a tree under the repository name
+    fn run_tree(&mut self, depth: Option<usize>, status: bool, ascii: bool) -> Result<()> {
+        let mut tree = Tree::default();
+        for (tool, section) in self.distribution_parser.read_distribution()?.sections {
+            if !self.in_profile(&tool) {
+                continue;
+            }
+            tree.insert_dir(&tool, (!section.enabled).then(|| "off".to_string()));
+            
+            if status {
+                for (file, outcome) in self.file_statuses(&tool, &section.files)? {
+                    tree.insert(&format!("{}/{}", tool, file), Some(outcome.label().to_string()));
+                }
+            } else {
+                for entry in &section.files {
+                    tree.insert(&format!("{}/{}", tool, entry.destination()), None);
+                }
+            }
+        }
+        
+        let root = self.paths.repo_dir.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| "dotfiles".to_string());
+        self.formatter.print(&root, Some(Color::White), true)?;
+        self.formatter.newline()?;
+        for line in tree.render(depth, ascii) {
+            self.formatter.print(&line.prefix, None, false)?;
+            if line.is_dir {
+                self.formatter.print(&line.name, Some(Color::Cyan), true)?;
+            } else {
+                self.formatter.print(&line.name, None, false)?;
+            }
+            if let Some(note) = &line.note {
+                self.formatter.dim(&format!("  ({})", note))?;
+            }
+            self.formatter.newline()?;
+        }
+        
+        Ok(())
+    }
+    
+    fn run_list(&mut self, describe: bool, format: ListFormat, priority_order: bool) -> Result<()> {
+        self.formatter.header("Tracked tools:")?;
+        
+        let distribution = self.distribution_parser.read_distribution()?;
+        // Disabled tools stay in the list, marked [off]
+        let mut tools: Vec<&String> = Vec::new();
+        for tool in distribution.sections.keys() {
+            if matches!(self.skip_reason(tool)?, None | Some("disabled")) {
+                tools.push(tool);
+            }
+        }
+        if priority_order {
+            tools.sort_by_key(|tool| distribution.sections[*tool].priority);
+        } else {
+            tools.sort();
+        }
+        self.formatter.verbose(&format!("Found {} tools in distribution file", tools.len()))?;
+        
+        if format == ListFormat::Table {
+            let mut headers = vec!["Tool", "Files", "Status"];
+            let mut rows = Vec::new();
+            for tool in &tools {
+                let section = &distribution.sections[*tool];
+                let mut row = vec![
+                    if section.enabled { tool.to_string() } else { format!("{} [off]", tool) },
+                    section.files.len().to_string(),
+                    self.tool_status_report(tool)?.brief(),
+                ];
+                if describe {
+                    row.push(section.description.clone().unwrap_or_default());
+                    row.push(section.url.clone().unwrap_or_default());
+                }
+                rows.push(row);
+            }
+            if describe {
+                headers.extend(["Description", "URL"]);
+            }
+            
+            return self.formatter.table(&headers, &rows, &[Some(Color::White), None, Some(Color::Cyan)]);
+        }
+        
+        for tool in tools {
+            let section = &distribution.sections[tool];
+            self.formatter.print(&format!("  - {}: ", tool), Some(Color::White), true)?;
+            self.formatter.print(&format!("{} files", section.files.len()), None, false)?;
+            if priority_order {
+                self.formatter.print(&format!(" (priority {})", section.priority), Some(Color::Cyan), false)?;
+            }
+            if !section.enabled {
+                self.formatter.print(" [off]", Some(Color::Yellow), false)?;
+            }
+            self.formatter.newline()?;
+            
+            if describe {
+                if let Some(description) = &section.description {
+                    self.formatter.print("      Description: ", Some(Color::Cyan), false)?;
+                    self.formatter.print(description, None, false)?;
+                    self.formatter.newline()?;
+                }
+                if let Some(url) = &section.url {
+                    self.formatter.print("      URL: ", Some(Color::Cyan), false)?;
+                    self.formatter.print(url, None, false)?;
+                    self.formatter.newline()?;
+                }
+                if !section.tags.is_empty() {
+                    self.formatter.print("      Tags: ", Some(Color::Cyan), false)?;
+                    self.formatter.print(&section.tags.join(", "), None, false)?;
+                    self.formatter.newline()?;
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // Repair the issues `validate` reports that have an unambiguous fix and
+    // return how many were fixed
+    fn fix_distribution_entries(&mut self, format: DistributionFormat) -> Result<usize> {
+        self.formatter.verbose("Fixing distribution sections")?;
+        let parser = DistributionParser::new(self.paths.distribution_file.clone()).with_format(format);
+        let mut fixed = 0;
+        
+        // Normalize separators first so entries that only differed by them are deduplicated
+        let normalized = parser.normalize_separators()?;
+        if normalized > 0 {
+            self.formatter.modified(&format!("Fixed: replaced '\\' with '/' in {} entries", normalized))?;
+            fixed += normalized;
+        }
+        
+        let removed = parser.deduplicate(false)?;
+        if removed > 0 {
+            self.formatter.modified(&format!("Fixed: removed {} duplicate entries", removed))?;
+            fixed += removed;
+        }
+        
+        let empty: Vec<String> = parser.read_without_includes()?.sections.iter()
+            .filter(|(_, section)| section.files.is_empty())
+            .map(|(tool, _)| tool.clone())
+            .collect();
+        if !empty.is_empty() {
+            parser.remove_sections(&empty)?;
+            for tool in &empty {
+                self.formatter.modified(&format!("Fixed: removed empty section {}", tool))?;
+            }
+            fixed += empty.len();
+        }
+        
+        if !parser.is_sorted()? {
+            parser.sort()?;
+            self.formatter.modified("Fixed: sorted sections and files")?;
+            fixed += 1;
+        }
+        
+        Ok(fixed)
+    }
+    
+    // Print each warning as tool/file: message; returns the number of errors
+    fn report_validation(&mut self, warnings: &[ValidationWarning]) -> Result<usize> {
+        let mut error_count = 0;
+        for warning in warnings {
+            let location = match &warning.file {
+                Some(file) => format!("{}/{}", warning.tool, file),
+                None => warning.tool.clone(),
+            };
+            let message = format!("{}: {}", location, warning.message);
+            
+            match warning.severity {
+                Severity::Error => {
+                    error_count += 1;
+                    self.formatter.error(&message)?;
+                },
+                Severity::Warning => self.formatter.warning(&message)?,
+                Severity::Info => self.formatter.info(&message)?,
+            }
+        }
+        Ok(error_count)
+    }
+    
+    // Check distribution.toml against the style rules without changing it.
+    // Warnings only fail the check with `strict`
+    fn run_lint(&mut self, strict: bool) -> Result<()> {
+        self.formatter.header("Linting distribution file...")?;
+        let warnings = self.distribution_parser.lint()?;
+        let errors = self.report_validation(&warnings)?;
+        let warning_count = warnings.len() - errors;
+        
+        if warnings.is_empty() {
+            self.formatter.validation("No style problems found")?;
+            return Ok(());
+        }
+        self.formatter.summary(&format!("Summary: {} errors, {} warnings", errors, warning_count))?;
+        
+        if errors > 0 || (strict && warning_count > 0) {
+            let count = if strict { warnings.len() } else { errors };
+            return Err(DotfilesError::DistributionParseError(format!("{} style problems in distribution file", count)).into());
+        }
+        Ok(())
+    }
+    
+    // Report section-level problems found by DistributionParser::validate.
+    // `fixed` is the number of issues `--fix` repaired, if it was given
+    fn check_distribution_entries(&mut self, format: DistributionFormat, fixed: Option<usize>) -> Result<()> {
+        self.formatter.verbose("Validating distribution sections")?;
+        // Always validate the file on disk, even when running from the embedded archive
+        let parser = self.precheck_parser(format);
+        let warnings = parser.validate(&self.paths.repo_dir.join("config"))?;
+        let error_count = self.report_validation(&warnings)?;
+        
+        if let Some(fixed) = fixed {
+            let remaining = warnings.iter().filter(|warning| warning.severity != Severity::Info).count();
+            self.formatter.summary(&format!("Fixed {} issues, {} warnings remain", fixed, remaining))?;
+        }
+        
+        if error_count > 0 {
+            return Err(DotfilesError::DistributionParseError(
+                format!("{} invalid entries in distribution file", error_count)).into());
+        }
+        
+        if warnings.is_empty() {
+            self.formatter.validation("All sections valid")?;
+        }
+        
+        Ok(())
+    }
+    
+    // Compare the files under the repo config/ directory with every file the
+    // distribution lists. Listed files missing on disk are errors; files on
+    // disk that no tool lists are warnings
+    fn check_repo_files(&mut self, distribution: &Distribution) -> Result<()> {
+        self.formatter.verbose("Comparing repository files with distribution")?;
+        
+        let mut listed = Vec::new();
+        for (tool, section) in &distribution.sections {
+            for entry in &section.files {
+                let file = section.repo_file_name(entry);
+                listed.push((format!("{}/{}", tool, file), self.paths.repo_file_path(tool, &file)));
+            }
+        }
+        
+        let config_root = self.paths.repo_dir.join("config");
+        let mut on_disk = HashSet::new();
+        if config_root.is_dir() {
+            for entry in WalkDir::new(&config_root).min_depth(1) {
+                let entry = entry?;
+                if entry.file_type().is_file() && entry.file_name() != DotIgnore::TOOLIGNORE_FILE
+                    && entry.file_name() != TEMPLATE_VARIABLES_FILE {
+                    on_disk.insert(entry.into_path());
+                }
+            }
+        }
+        
+        let mut missing = 0;
+        for (name, path) in &listed {
+            if !on_disk.contains(path) {
+                missing += 1;
+                self.formatter.error(&format!("Listed but missing from repo: {}", name))?;
+            }
+        }
+        
+        let listed_paths: HashSet<&PathBuf> = listed.iter().map(|(_, path)| path).collect();
+        let mut untracked: Vec<&PathBuf> = on_disk.iter().filter(|path| !listed_paths.contains(path)).collect();
+        untracked.sort();
+        for path in &untracked {
+            let display_path = path.strip_prefix(&config_root).unwrap_or(path);
+            self.formatter.warning(&format!("In repo but not listed: {}", display_path.display()))?;
+        }
+        
+        if missing > 0 {
+            return Err(DotfilesError::FileNotFound(
+                format!("{} listed files are missing from the repository", missing)).into());
+        }
+        
+        if untracked.is_empty() {
+            self.formatter.validation("Repository files match distribution")?;
+        }
+        
+        Ok(())
+    }
+    
+    fn run_audit(&mut self, fix: bool) -> Result<()> {
+        self.formatter.header("Auditing tracked files...")?;
+        
+        let file_patterns: Vec<Pattern> = AUDIT_FILE_PATTERNS.iter()
+            .filter_map(|pattern| Pattern::new(pattern).ok())
+            .collect();
+        
+        let mut warnings = 0;
+        let mut errors = 0;
+        // (tool, entry, whether its content looks like a secret)
+        let mut flagged: Vec<(String, FileEntry, bool)> = Vec::new();
+        
+        let distribution = self.distribution_parser.read_distribution()?;
+        for tool in distribution.sections.keys() {
+            for entry in self.distribution_parser.get_file_entries(tool)? {
+                let file = entry.destination().to_string();
+                let display_path = format!("{}/{}", tool, file);
+                let basename = Path::new(&file).file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| file.clone());
+                self.formatter.verbose(&format!("Auditing: {}", display_path))?;
+                let mut name_flagged = false;
+                
+                // Match names against the full tool/file path so patterns like *kube/config* work
+                if let Some(pattern) = file_patterns.iter().find(|p| p.matches(&display_path) || p.matches(&basename)) {
+                    warnings += 1;
+                    self.formatter.warning(&format!("Sensitive filename: {} (matched pattern: `{}`)", display_path, pattern))?;
+                    name_flagged = true;
+                }
+                
+                let content = match self.mode {
+                    AppMode::FilesystemMode => fs::read(self.paths.repo_file_path(tool, entry.source())).ok(),
+                    AppMode::EmbeddedMode => DotfilesArchive::get_file(tool, entry.source()).ok(),
+                };
+                let Some(content) = content else {
+                    if name_flagged {
+                        flagged.push((tool.clone(), entry, false));
+                    }
+                    continue;
+                };
+                
+                let text = String::from_utf8_lossy(&content);
+                let mut content_flagged = false;
+                for (line_number, line) in text.lines().enumerate() {
+                    if let Some((_, description)) = AUDIT_CONTENT_PATTERNS.iter().find(|(regex, _)| regex.is_match(line)) {
+                        errors += 1;
+                        content_flagged = true;
+                        self.formatter.error(&format!("Possible secret: {}:{} ({})", display_path, line_number + 1, description))?;
+                    }
+                }
+                if name_flagged || content_flagged {
+                    flagged.push((tool.clone(), entry, content_flagged));
+                }
+            }
+        }
+        
+        if warnings == 0 && errors == 0 {
+            self.formatter.validation("No issues found")?;
+            return Ok(());
+        }
+        
+        if fix {
+            for (tool, entry, has_secret) in &flagged {
+                self.fix_audited_file(tool, &distribution.sections[tool], entry, *has_secret)?;
+            }
+        }
+        
+        self.formatter.summary(&format!("Audit found {} sensitive filenames and {} possible secrets", warnings, errors))?;
+        if errors > 0 {
+            if fix {
+                self.formatter.warning("Files removed by --fix are still in the repository's git history")?;
+            }
+            return Err(anyhow!("{} possible secrets found in tracked files", errors));
+        }
+        
+        Ok(())
+    }
+    
+    // Ignore a file flagged by audit in its tool's .toolignore, so the
+    // pattern doesn't catch same-named files of other tools. A file whose
+    // content looks like a secret is also untracked and its repository copy
+    // deleted
+    fn fix_audited_file(&mut self, tool: &str, section: &Section, entry: &FileEntry, has_secret: bool) -> Result<()> {
+        let file = entry.destination();
+        let display_path = format!("{}/{}", tool, file);
+        let tool_dir = self.paths.repo_config_dir(tool);
+        let pattern = format!("/{}", Pattern::escape(file));
+        if !DotIgnore::for_tool(&tool_dir)?.is_ignored(file) {
+            create_dir_all(&tool_dir)?;
+            let mut toolignore = OpenOptions::new().create(true).append(true).open(tool_dir.join(DotIgnore::TOOLIGNORE_FILE))?;
+            writeln!(toolignore, "{}", pattern)?;
+            self.formatter.action(&format!("Added to {}/{}: {}", tool, DotIgnore::TOOLIGNORE_FILE, pattern))?;
+        }
+        
+        if has_secret {
+            let repo_file = self.paths.repo_file_path(tool, &section.repo_file_name(entry));
+            DistributionParser::new(self.paths.distribution_file.clone()).remove_file(tool, file)?;
+            self.formatter.action(&format!("Removed from distribution file: {}", display_path))?;
+            if repo_file.exists() {
+                fs::remove_file(&repo_file)?;
+                self.formatter.action(&format!("Deleted repository copy: {}", self.paths.tilde(&repo_file)))?;
+            }
+        }
+        Ok(())
+    }
+    
+    // Read every file in [_includes], reporting the first one that is missing or invalid
+    fn check_includes(&mut self, parser: &DistributionParser) -> Result<Distribution> {
+        self.formatter.verbose("Checking included distribution files")?;
+        
+        match parser.read_distribution() {
+            Ok(distribution) => {
+                self.formatter.validation("All included files are valid")?;
+                Ok(distribution)
+            },
+            Err(e) => {
+                self.formatter.error(&e.to_string())?;
+                Err(e)
+            },
+        }
+    }
+    
+    // Compare the embedded files against the checksums taken when the binary was built
+    fn check_embedded_integrity(&mut self) -> Result<()> {
+        self.formatter.verbose(&format!("Verifying {} embedded files", EMBEDDED_CHECKSUMS.len()))?;
+        let errors = DotfilesArchive::verify_integrity()?;
+        
+        for error in &errors {
+            self.formatter.error(&format!("Embedded file is corrupt: {}", error.path))?;
+            self.formatter.verbose(&format!("  expected sha256 {}", error.expected))?;
+            self.formatter.verbose(&format!("  actual sha256   {}", error.actual))?;
+        }
+        
+        if !errors.is_empty() {
+            return Err(DotfilesError::ArchiveReadError(format!("{} embedded files failed verification", errors.len())).into());
+        }
+        
+        self.formatter.validation(&format!("All {} embedded files match their checksums", EMBEDDED_CHECKSUMS.len()))?;
+        Ok(())
+    }
+    
+    fn run_verify(&mut self, embedded: bool) -> Result<()> {
+        if !embedded {
+            return Err(DotfilesError::InvalidCommand("+verify needs --embedded".to_string()).into());
+        }
+        
+        self.formatter.header("Verifying embedded files...")?;
+        self.check_embedded_integrity()
+    }
+    
+    // Where tools of each dir_type live on this machine
+    fn run_xdg_dirs(&mut self) -> Result<()> {
+        self.formatter.header("XDG base directories:")?;
+        
+        for dir_type in [DirType::Config, DirType::Data, DirType::State, DirType::Cache, DirType::AppSupport, DirType::Preferences] {
+            self.formatter.print(&format!("  {}: ", dir_type.name()), Some(Color::Cyan), false)?;
+            self.formatter.print(&self.paths.base_dir(dir_type).display().to_string(), None, false)?;
+            self.formatter.newline()?;
+        }
+        
+        Ok(())
+    }
+    
+    // List the active ignore patterns by the file they come from, in the
+    // order they are evaluated
+    fn run_ignore_info(&mut self) -> Result<()> {
+        let sources = [(true, self.paths.global_dotignore_file.clone()), (false, self.paths.dotignore_file.clone())];
+        for (global, path) in sources {
+            self.formatter.header(&format!("Patterns from {}:", path.display()))?;
+            let rules: Vec<&str> = self.dotignore.patterns.iter()
+                .filter(|rule| rule.global == global)
+                .map(|rule| rule.raw.as_str())
+                .collect();
+            if rules.is_empty() {
+                self.formatter.info("No patterns")?;
+            } else if !global && !path.exists() {
+                self.formatter.info("No .dotignore file; the default patterns apply")?;
+            }
+            
+            for rule in rules {
+                self.formatter.print("  - ", Some(Color::Cyan), false)?;
+                self.formatter.print(rule, None, false)?;
+                self.formatter.newline()?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // Every $VAR in file entries and source_dir/target_dir must be defined on
+    // this machine, even with --allow-undefined-vars
+    fn run_check_vars(&mut self) -> Result<()> {
+        self.formatter.header("Checking variables in the distribution file...")?;
+        
+        let mut env = self.paths.env.clone();
+        env.allow_undefined = false;
+        let distribution = self.distribution_parser.read_distribution()?;
+        let (mut checked, mut undefined) = (0, 0);
+        for (tool, section) in &distribution.sections {
+            let files = section.files.iter().flat_map(|entry| match entry {
+                FileEntry::Simple(file) => vec![file.as_str()],
+                FileEntry::Rename { from, to } => vec![from.as_str(), to.as_str()],
+            });
+            let values = files.chain(section.source_dir.as_deref()).chain(section.target_dir.as_deref());
+            for raw in values.filter(|raw| raw.contains('$')) {
+                checked += 1;
+                match env.expand(raw) {
+                    Ok(value) => self.formatter.validation(&format!("{}: {} → {}", tool, raw, value))?,
+                    Err(e) => {
+                        undefined += 1;
+                        self.formatter.error(&format!("{}: {}", tool, e))?;
+                    },
+                }
+            }
+        }
+        
+        if undefined > 0 {
+            return Err(DotfilesError::InvalidCommand(format!("{} of {} values with variables don't expand", undefined, checked)).into());
+        }
+        if checked == 0 {
+            self.formatter.validation("No variables in the distribution file")?;
+        }
+        Ok(())
+    }
+    
+    // source_dir and target_dir must expand to absolute paths without `..`
+    fn check_section_dirs(&mut self, distribution: &Distribution) -> Result<()> {
+        let mut invalid = 0;
+        for (tool, section) in &distribution.sections {
+            let dirs = [("source_dir", &section.source_dir), ("target_dir", &section.target_dir)];
+            for (key, raw) in dirs {
+                let Some(raw) = raw else { continue };
+                self.formatter.verbose(&format!("Checking {} for tool: {}", key, tool))?;
+                match self.paths.expand_path(raw) {
+                    Ok(path) if path.components().any(|c| matches!(c, Component::ParentDir)) => {
+                        invalid += 1;
+                        self.formatter.error(&format!("{} for {} must not contain '..': {}", key, tool, path.display()))?;
+                    },
+                    Ok(path) if path.is_absolute() => self.formatter.validation(&format!(
+                        "Valid {} for {}: {}", key, tool, path.display()))?,
+                    Ok(path) => {
+                        invalid += 1;
+                        self.formatter.error(&format!("{} for {} is not absolute: {}", key, tool, path.display()))?;
+                    },
+                    Err(e) => {
+                        invalid += 1;
+                        self.formatter.error(&format!("Invalid {} for {}: {}", key, tool, e))?;
+                    },
+                }
+            }
+        }
+        
+        if invalid > 0 {
+            return Err(DotfilesError::InvalidCommand(format!("{} invalid source_dir/target_dir values", invalid)).into());
+        }
+        
+        Ok(())
+    }
+    
+    // Every tool named in [_profiles] must have a section
+    fn check_profiles(&mut self, distribution: &Distribution) -> Result<()> {
+        let Some(profiles) = &distribution.profiles else {
+            return Ok(());
+        };
+        
+        let mut unknown = 0;
+        for (name, tools) in profiles {
+            self.formatter.verbose(&format!("Checking profile: {}", name))?;
+            for tool in tools {
+                if !distribution.sections.contains_key(tool) {
+                    unknown += 1;
+                    self.formatter.error(&format!("Profile {} lists unknown tool: {}", name, tool))?;
+                }
+            }
+        }
+        
+        if unknown > 0 {
+            return Err(DotfilesError::InvalidCommand(format!("{} unknown tools in profiles", unknown)).into());
+        }
+        
+        self.formatter.validation(&format!("Valid profiles: {}", profiles.len()))?;
+        Ok(())
+    }
+    
+    // Validate every .toolignore file found under the repo config directory
+    fn check_toolignore_files(&mut self) -> Result<()> {
+        let config_root = self.paths.repo_dir.join("config");
+        if !config_root.is_dir() {
+            return Ok(());
+        }
+        
+        let mut invalid = 0;
+        for entry in fs::read_dir(&config_root)? {
+            let tool_dir = entry?.path();
+            if !tool_dir.join(DotIgnore::TOOLIGNORE_FILE).exists() {
+                continue;
+            }
+            
+            let tool = tool_dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+            self.formatter.verbose(&format!("Checking .toolignore for tool: {}", tool))?;
+            match DotIgnore::for_tool(&tool_dir) {
+                Ok(tool_ignore) => self.formatter.validation(&format!(
+                    "Valid .toolignore for {} ({} patterns)", tool, tool_ignore.patterns.len()))?,
+                Err(e) => {
+                    invalid += 1;
+                    self.formatter.error(&format!("Invalid .toolignore for {}: {}", tool, e))?;
+                }
+            }
+        }
+        
+        if invalid > 0 {
+            return Err(DotfilesError::InvalidCommand(format!("{} invalid .toolignore files", invalid)).into());
+        }
+        
+        Ok(())
+    }
+    
+    fn run_precheck(&mut self, format: Option<DistributionFormat>, check_repos: bool, fix: bool) -> Result<()> {
+        self.formatter.header("Checking distribution file...")?;
+        self.formatter.verbose("Starting distribution file precheck")?;
+        
+        if Self::uses_embedded_files() {
+            self.check_embedded_integrity()?;
+        }
+        
+        let from_stdin = self.distribution_parser.reads_stdin();
+        if from_stdin {
+            if fix {
+                return Err(DotfilesError::InvalidCommand("--fix can't edit a distribution read from stdin".to_string()).into());
+            }
+            self.formatter.print("Distribution file: ", Some(Color::Cyan), false)?;
+            self.formatter.print("<stdin>", None, false)?;
+            self.formatter.newline()?;
+        } else {
+            // Check if distribution file exists
+            self.formatter.verbose(&format!("Checking distribution file at: {}", self.paths.distribution_file.display()))?;
+            self.formatter.print("Distribution file: ", Some(Color::Cyan), false)?;
+            self.formatter.print(&self.paths.distribution_file.to_string_lossy(), None, false)?;
+            self.formatter.newline()?;
+            
+            if !self.paths.distribution_file.exists() {
+                self.formatter.verbose("Distribution file does not exist")?;
+                self.formatter.error("Distribution file not found")?;
+                return Err(DotfilesError::DistributionNotFound(
+                    self.paths.distribution_file.to_string_lossy().to_string()).into());
+            }
+            
+            self.formatter.verbose("Distribution file exists, proceeding with checks")?;
+            self.formatter.validation("Distribution file exists")?;
+        }
+        
+        // Use the requested format, otherwise go by the file extension
+        let format = match format {
+            Some(format) => format,
+            None if from_stdin => self.distribution_parser.format,
+            None => DistributionFormat::from_path(&self.paths.distribution_file),
+        };
+        let parser = self.precheck_parser(format);
+        
+        // Check if it's valid TOML/YAML
+        self.formatter.verbose(&format!("Checking {} syntax validity", format.name()))?;
+        self.formatter.print(&format!("Checking {} syntax... ", format.name()), Some(Color::Cyan), false)?;
+        
+        let content = parser.read_content()?;
+        self.formatter.verbose(&format!("Read {} bytes from distribution file", content.len()))?;
+        
+        // Try to parse the content
+        match parser.parse(&content) {
+            Ok(distribution) => {
+                self.formatter.verbose(&format!("{} syntax is valid", format.name()))?;
+                self.formatter.validation(&format!("Valid {} syntax", format.name()))?;
+                
+                // Validate the reserved [_settings] section
+                if let Some(settings) = &distribution.settings {
+                    self.formatter.verbose("Checking [_settings] section")?;
+                    for key in DistributionSettings::unknown_keys(&content, format) {
+                        self.formatter.warning(&format!("Unknown key in [_settings]: {}", key))?;
+                    }
+                    
+                    let problems = settings.validate();
+                    for problem in &problems {
+                        self.formatter.error(&format!("Invalid setting: {}", problem))?;
+                    }
+                    if !problems.is_empty() {
+                        return Err(DotfilesError::DistributionParseError(
+                            "Invalid values in [_settings] section".to_string()).into());
+                    }
+                }
+                
+                // Sections from included files are part of the distribution from here on
+                let distribution = match distribution.includes {
+                    Some(_) => self.check_includes(&parser)?,
+                    None => distribution,
+                };
+                
+                // Show basic info
+                let line_count = content.lines().count();
+                self.formatter.verbose(&format!("Distribution file has {} lines", line_count))?;
+                self.formatter.print("Line count: ", Some(Color::Cyan), false)?;
+                self.formatter.print(&format!("{} lines", line_count), None, false)?;
+                self.formatter.newline()?;
+                
+                let tools = self.distribution_parser.get_tools()?;
+                let total_files = tools.iter().fold(0, |acc, tool| {
+                    if let Ok(files) = self.distribution_parser.get_files(tool) {
+                        acc + files.len()
+                    } else {
+                        acc
+                    }
+                });
+                
+                self.formatter.verbose(&format!("Found {} tools and {} files in distribution", tools.len(), total_files))?;
+                self.formatter.print("Total tools: ", Some(Color::Cyan), false)?;
+                self.formatter.print(&format!("{}", tools.len()), None, false)?;
+                self.formatter.newline()?;
+                
+                let disabled = distribution.sections.values().filter(|section| !section.enabled).count();
+                if disabled > 0 {
+                    self.formatter.print("Disabled tools: ", Some(Color::Cyan), false)?;
+                    self.formatter.print(&format!("{}", disabled), None, false)?;
+                    self.formatter.newline()?;
+                }
+                
+                if self.verbose > 0 {
+                    self.formatter.print("Total files tracked: ", Some(Color::Cyan), false)?;
+                    self.formatter.print(&format!("{}", total_files), None, false)?;
+                    self.formatter.newline()?;
+                    
+                    // List all tools, file counts and metadata in verbose mode
+                    for tool in &tools {
+                        if let Some(section) = distribution.sections.get(tool) {
+                            self.formatter.print(&format!("  - {}: ", tool), Some(Color::White), true)?;
+                            self.formatter.print(&format!("{} files", section.files.len()), None, false)?;
+                            self.formatter.newline()?;
+                            
+                            if let Some(description) = &section.description {
+                                self.formatter.print("      Description: ", Some(Color::Cyan), false)?;
+                                self.formatter.print(description, None, false)?;
+                                self.formatter.newline()?;
+                            }
+                            if let Some(url) = &section.url {
+                                self.formatter.print("      URL: ", Some(Color::Cyan), false)?;
+                                self.formatter.print(url, None, false)?;
+                                self.formatter.newline()?;
+                            }
+                            
+                            for (file, outcome) in self.file_statuses(tool, &section.files)? {
+                                self.formatter.print(&format!("      {}: ", file), None, false)?;
+                                self.formatter.print(outcome.label(), Some(Color::Cyan), false)?;
+                                self.formatter.newline()?;
+                            }
+                        }
+                    }
+                }
+                
+                let fixed = if fix { Some(self.fix_distribution_entries(format)?) } else { None };
+                
+                self.check_section_dirs(&distribution)?;
+                self.check_profiles(&distribution)?;
+                self.check_toolignore_files()?;
+                self.check_distribution_entries(format, fixed)?;
+                if check_repos {
+                    // Fixes may have changed the file since it was parsed above
+                    let distribution = if fix { parser.read_distribution()? } else { distribution };
+                    self.check_repo_files(&distribution)?;
+                }
+                
+                self.formatter.newline()?;
+                self.formatter.verbose("Precheck completed successfully")?;
+                self.formatter.validation("Precheck passed successfully")?;
+            },
+            Err(e) => {
+                self.formatter.verbose(&format!("{} syntax is invalid: {}", format.name(), e))?;
+                self.formatter.error(&format!("Invalid {} syntax: {}", format.name(), e))?;
+                return Err(e);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    fn run_merge(&mut self, other_file: &Path, output: Option<&Path>) -> Result<()> {
+        if !other_file.exists() {
+            return Err(DotfilesError::DistributionNotFound(other_file.to_string_lossy().to_string()).into());
+        }
+        
+        // Merge the on-disk distribution, not the one embedded in the binary
+        let parser = DistributionParser::new(self.paths.distribution_file.clone());
+        let merged = parser.merge(&DistributionParser::new(other_file.to_path_buf()))?;
+        
+        let output = output.map(Path::to_path_buf).unwrap_or_else(|| self.paths.distribution_file.clone());
+        DistributionParser::new(output.clone()).write_distribution(&merged)?;
+        
+        self.formatter.action(&format!("Merged {} into {} ({} tools)",
+            other_file.display(), output.display(), merged.sections.len()))?;
+        Ok(())
+    }
+    
+    // Read a distribution file and write it out in the format of the destination's extension
+    fn run_convert(&mut self, from: &Path, to: &Path) -> Result<()> {
+        if !from.exists() {
+            return Err(DotfilesError::DistributionNotFound(from.to_string_lossy().to_string()).into());
+        }
+        if to.exists() {
+            return Err(DotfilesError::InvalidCommand(
+                format!("Refusing to overwrite existing file: {}", to.display())).into());
+        }
+        
+        let from_format = DistributionFormat::from_path(from);
+        let to_format = DistributionFormat::from_path(to);
+        self.formatter.verbose(&format!("Reading {} from {}", from_format.name(), from.display()))?;
+        
+        let parser = DistributionParser::new(from.to_path_buf());
+        let content = match to_format {
+            DistributionFormat::Yaml => parser.to_yaml()?,
+            DistributionFormat::Json => parser.to_json()?,
+            DistributionFormat::Toml => DistributionParser::serialize_as(&parser.read_distribution()?, to_format)?,
+        };
+        fs::write(to, content)?;
+        
+        self.formatter.action(&format!("Converted {} to {} ({})", from.display(), to.display(), to_format.name()))?;
+        Ok(())
+    }
+    
+    fn run_config(&mut self, action: &ConfigAction) -> Result<()> {
+        let config_file = ToolConfig::config_file()?;
+        
+        match action {
+            ConfigAction::Init => {
+                if config_file.exists() {
+                    self.formatter.warning(&format!("Config file already exists: {}", config_file.display()))?;
+                    return Ok(());
+                }
+                
+                let path = ToolConfig::default().save()?;
+                self.formatter.action(&format!("Created config file: {}", path.display()))?;
+            },
+            ConfigAction::Show => {
+                self.formatter.header("dotfiles-rs configuration")?;
+                self.formatter.print("Config file: ", Some(Color::Cyan), false)?;
+                self.formatter.print(&config_file.to_string_lossy(), None, false)?;
+                if !config_file.exists() {
+                    self.formatter.print(" (not found, using defaults)", None, false)?;
+                }
+                self.formatter.newline()?;
+                
+                for (key, value) in self.tool_config.entries() {
+                    self.formatter.print(&format!("  {}: ", key), Some(Color::White), true)?;
+                    self.formatter.print(&value, None, false)?;
+                    self.formatter.newline()?;
+                }
+            },
+            ConfigAction::Set { key, value } => {
+                self.formatter.verbose(&format!("Setting {} to {}", key, value))?;
+                // Start from the file on disk so distribution settings aren't persisted
+                let mut tool_config = ToolConfig::load()?;
+                tool_config.set(key, value)?;
+                let path = tool_config.save()?;
+                self.formatter.action(&format!("Updated {}: {} = {}", path.display(), key, value))?;
+            },
+        }
+        
+        Ok(())
+    }
+    
+    /// Run one command, as parsed by the `dotfiles-rs` binary, and return
+    /// how many files it synced, installed, skipped or failed on
+    pub fn run(&mut self, command: &Commands) -> Result<RunSummary> {
+        self.run_summary = RunSummary {
+            command: command.name().to_string(),
+            ..RunSummary::default()
+        };
+        let warnings = self.formatter.warnings;
+        let result = self.run_command(command);
+        self.run_summary.warnings = self.formatter.warnings - warnings;
+        result.map(|_| self.run_summary.clone())
+    }
+    
+    /// The counts of the last `run`, also when it failed
+    pub fn summary(&self) -> &RunSummary {
+        &self.run_summary
+    }
+    
+    fn run_command(&mut self, command: &Commands) -> Result<()> {
+        match command {
+            Commands::Version => {
+                // Print version and build information
+                println!("dotfiles-rs {}", env!("CARGO_PKG_VERSION"));
+                println!("Build identity: {}", env!("BUILD_IDENTITY", "unknown"));
+                println!("Newest file: {}", env!("NEWEST_FILE", "unknown"));
+                return Ok(());
+            },
+            Commands::Help => {
+                // Reuse the Usage command for now
+                return self.run_usage();
+            },
+            Commands::Config { action } => {
+                // Tool settings don't depend on the dotfiles repository
+                return self.run_config(action);
+            },
+            Commands::Install { from_stdin: true, .. } | Commands::Status { from_stdin: true, .. }
+            | Commands::Precheck { from_stdin: true, .. } => {
+                let json = matches!(command, Commands::Status { json: true, .. });
+                self.report_xdg_config_home(json)?;
+                self.read_distribution_from_stdin(json)?;
+                self.check_paths()?;
+                self.create_dotignore()?;
+            },
+            _ => {
+                self.report_xdg_config_home(matches!(command, Commands::Status { json: true, .. } | Commands::Stats { json: true, .. }))?;
+                
+                // Check required paths
+                self.check_paths()?;
+                
+                // Create dotignore if it doesn't exist
+                self.create_dotignore()?;
+            }
+        }
+        
+        match command {
+            Commands::Sync { fail_fast, since, tool, tag, retries, retry_delay_ms, interactive, variables, skip_post_sync } => {
+                self.set_template_variables(variables)?;
+                self.tag_filter = tag.clone();
+                self.set_tool_filter(tool)?;
+                self.set_interactive(*interactive)?;
+                self.retry_policy = RetryPolicy::with_overrides(*retries, *retry_delay_ms);
+                self.run_sync(*fail_fast, *since, *skip_post_sync)?
+            },
+            Commands::Status { fail_fast, json, tool, tag, .. } => {
+                self.tag_filter = tag.clone();
+                self.set_tool_filter(tool)?;
+                self.run_status(*fail_fast, *json)?
+            },
+            Commands::Bootstrap { yes, dry_run } => self.run_bootstrap(*yes, *dry_run)?,
+            Commands::Check { strict } => self.run_check(*strict)?,
+            Commands::Install { conflict, force, tool, tag, fail_fast, accumulate_errors, retries, retry_delay_ms, overwrite_symlinks, allow_symlinks_in_repo, interactive, no_create_dirs, variables, from_stdin } => {
+                self.set_template_variables(variables)?;
+                self.tag_filter = tag.clone();
+                self.set_tool_filter(tool)?;
+                self.set_interactive(*interactive)?;
+                self.retry_policy = RetryPolicy::with_overrides(*retries, *retry_delay_ms);
+                self.symlinks = SymlinkPolicy { overwrite_local: *overwrite_symlinks, allow_in_repo: *allow_symlinks_in_repo, ..SymlinkPolicy::default() };
+                self.create_dirs = !*no_create_dirs;
+                // --force wins, otherwise fall back to the configured default strategy
+                let conflict = match conflict {
+                    _ if *force => ConflictStrategy::Overwrite,
+                    Some(conflict) => *conflict,
+                    None => ConflictStrategy::from_str(&self.tool_config.default_conflict_strategy, true)
+                        .map_err(DotfilesError::InvalidCommand)?,
+                };
+                // Prompts would read their answers from the distribution
+                if *from_stdin && (*interactive || conflict == ConflictStrategy::Ask) {
+                    return Err(DotfilesError::InvalidCommand(
+                        "--from-stdin can't be combined with --interactive or --conflict ask".to_string()).into());
+                }
+                self.run_install(conflict, *fail_fast, *accumulate_errors)?
+            },
+            Commands::Add { tool, file, description, url, tag, sort, retries, retry_delay_ms } => {
+                self.retry_policy = RetryPolicy::with_overrides(*retries, *retry_delay_ms);
+                let metadata = SectionMetadata {
+                    description: description.clone(),
+                    url: url.clone(),
+                    tags: tag.clone(),
+                };
+                self.run_add(tool, file, &metadata, *sort)?
+            },
+            Commands::New { tool, description, tag, no_config_dir } => {
+                let metadata = SectionMetadata {
+                    description: description.clone(),
+                    url: None,
+                    tags: tag.clone(),
+                };
+                self.run_new(tool, &metadata, !*no_config_dir)?
+            },
+            Commands::Remove { tool, file } => self.run_remove(tool, file)?,
+            Commands::Mv { from_tool, file, to_tool, new_file, create_section } => {
+                self.run_mv(from_tool, file, to_tool, new_file.as_deref(), *create_section)?
+            },
+            Commands::Cp { tool, file, destination, force } => self.run_cp(tool, file, destination, *force)?,
+            Commands::Template { tool, file, variables } => {
+                self.set_template_variables(variables)?;
+                self.run_template(tool, file)?
+            },
+            Commands::List { describe, format, priority_order, tag } => {
+                self.tag_filter = tag.clone();
+                self.run_list(*describe, format.unwrap_or(ListFormat::Plain), *priority_order)?
+            },
+            Commands::Diff { tool, file, tag, stat, from_backup } => {
+                self.tag_filter = tag.clone();
+                match from_backup {
+                    Some(backup_dir) => self.run_diff_backup(backup_dir, tool.as_deref(), file.as_deref(), *stat)?,
+                    None => self.run_diff(tool.as_deref(), file.as_deref(), *stat)?,
+                }
+            },
+            Commands::Audit { fix } => self.run_audit(*fix)?,
+            Commands::Clean { delete, dry_run, yes } => self.run_clean(*delete && !*dry_run, *yes)?,
+            Commands::Update { tool, add, interactive, recursive } => {
+                self.run_update(tool.as_deref(), *add, *interactive, *recursive)?
+            },
+            Commands::Gc { dry_run, yes, remove_empty_dirs, strict } => {
+                self.run_gc(*dry_run, *yes, *remove_empty_dirs, *strict)?
+            },
+            Commands::Enable { tool } => self.run_set_enabled(tool, true)?,
+            Commands::Disable { tool } => self.run_set_enabled(tool, false)?,
+            Commands::Stash => self.run_stash()?,
+            Commands::Snapshot { name } => self.run_snapshot(name)?,
+            Commands::SnapshotList => self.run_snapshot_list()?,
+            Commands::SnapshotRestore { name } => self.run_snapshot_restore(name)?,
+            Commands::SnapshotDelete { name, yes } => self.run_snapshot_delete(name, *yes)?,
+            Commands::Unstash { stash_id, list, drop } => {
+                self.run_unstash(stash_id.as_deref(), *list, drop.as_deref())?
+            },
+            Commands::Ignore { action } => self.run_ignore(action)?,
+            Commands::GitIgnore { append, dry_run } => self.run_gitignore(*append, *dry_run)?,
+            Commands::Sort => self.run_sort()?,
+            Commands::Log { last_n, command_filter, tool_filter, clear } => {
+                if *clear {
+                    self.run_log_clear()?
+                } else {
+                    self.run_log(*last_n, command_filter.as_deref(), tool_filter.as_deref())?
+                }
+            },
+            Commands::Undo { steps } => self.run_undo(steps.unwrap_or(1))?,
+            Commands::Doctor => self.run_doctor()?,
+            Commands::Repair { dry_run, remove_dangling } => self.run_repair(*dry_run, *remove_dangling)?,
+            Commands::Link { tool, file, relink } => self.run_link(tool, file, *relink)?,
+            Commands::Unlink { tool, file } => self.run_unlink(tool, file)?,
+            Commands::Tags => self.run_tags()?,
+            Commands::Profile { action } => self.run_profile(action)?,
+            Commands::Info { tool } => self.run_info(tool)?,
+            Commands::Stats { json } => self.run_stats(*json)?,
+            Commands::Tree { depth, status, ascii } => self.run_tree(*depth, *status, *ascii)?,
+            Commands::Fetch { host, tool, ssh_user, ssh_port, to_repo } => {
+                self.set_tool_filter(tool)?;
+                self.run_fetch(&SshHost::new(host, ssh_user.as_deref(), *ssh_port), *to_repo)?
+            },
+            Commands::Push { host, tool, ssh_user, ssh_port, dry_run, diff_first } => {
+                self.set_tool_filter(tool)?;
+                self.run_push(&SshHost::new(host, ssh_user.as_deref(), *ssh_port), *dry_run, *diff_first)?
+            },
+            Commands::Touch { tool, file, side } => self.run_touch(tool, file, *side)?,
+            Commands::Where { tool, file, open, repo, config } => {
+                let only = match (*repo, *config) {
+                    (true, true) => return Err(DotfilesError::InvalidCommand("--repo and --config can't be used together".to_string()).into()),
+                    (true, false) => Some(PathKind::Repo),
+                    (false, true) => Some(PathKind::Config),
+                    (false, false) => None,
+                };
+                self.run_where(tool, file, *open, only)?
+            },
+            Commands::Fmt => self.run_fmt()?,
+            Commands::Lint { strict } => self.run_lint(*strict)?,
+            Commands::Verify { embedded } => self.run_verify(*embedded)?,
+            Commands::Deduplicate { case_insensitive } => self.run_deduplicate(*case_insensitive)?,
+            Commands::Precheck { xdg: true, .. } => self.run_xdg_dirs()?,
+            Commands::Precheck { check_vars: true, .. } => self.run_check_vars()?,
+            Commands::Precheck { ignore_info: true, .. } => self.run_ignore_info()?,
+            Commands::Precheck { format, check_repos, fix, .. } => self.run_precheck(*format, *check_repos, *fix)?,
+            Commands::Merge { other_file, output } => self.run_merge(other_file, output.as_deref())?,
+            Commands::Convert { from, to } => self.run_convert(from, to)?,
+            Commands::Version => {}, // Already handled above
+            Commands::Help => {}, // Already handled above
+            Commands::Config { .. } => {}, // Already handled above
+            Commands::Usage => self.run_usage()?,
+        }
+        
+        Ok(())
+    }
+    
+    fn run_usage(&self) -> Result<()> {
+        // Print help information
+        println!("dotfiles-rs - Manages dotfiles between system configuration and git repository");
+        println!();
+        println!("Actions:");
+        println!("  +bootstrap            - Set up this machine: precheck, create directories, install every tool.");
+        println!("      [--yes] [--dry-run]");
+        println!("  +sync                 - Sync configuration.");
+        println!("      [--fail-fast] [--since <YYYY-MM-DD>] [--tool <tool>] [--tag <tag>]... [--retries <n>] [--retry-delay-ms <ms>] [--interactive]");
+        println!("      [--variables <key=value>]... [--skip-post-sync]");
+        println!("  +status               - Show configuration status.");
+        println!("      [--fail-fast] [--json] [--tool <tool>] [--tag <tag>]... [--from-stdin]");
+        println!("      Exits with 0 when in sync, 1 if files are modified, 2 if files are missing");
+        println!("      in the repo, 3 if both");
+        println!("  +check                - Verify every tracked file is installed and matches the repo.");
+        println!("      [--strict]");
+        println!("  +install              - Install configuration.");
+        println!("      [--conflict <overwrite|skip|ask|error>] [--accumulate-errors] [--force] [--fail-fast] [--tool <tool>] [--tag <tag>]...");
+        println!("      [--retries <n>] [--retry-delay-ms <ms>]");
+        println!("      [--overwrite-symlinks] [--allow-symlinks-in-repo] [--interactive] [--variables <key=value>]...");
+        println!("      [--no-create-dirs] [--from-stdin]");
+        println!("      --force overwrites local files even when they are newer (destructive)");
+        println!("  +diff [tool] [file]   - Show differences between repo and local files.");
+        println!("      [--tag <tag>]... [--stat] [--from-backup <dir>]");
+        println!("  +update [tool]        - List untracked files in tool config directories.");
+        println!("      [--add] [--interactive] [--recursive]");
+        println!("  +stash                - Save installed config files to the repo stash.");
+        println!("  +unstash [id]         - List stashes, or restore the given stash.");
+        println!("      [--list] [--drop <id>]");
+        println!("  +snapshot <name>      - Save a named copy of config/ and distribution.toml.");
+        println!("  +snapshot-list        - List snapshots with their creation time and size.");
+        println!("  +snapshot-restore <name> - Restore a snapshot, saving the current state first.");
+        println!("  +snapshot-delete <name> - Delete a snapshot.");
+        println!("      [--yes]");
+        println!("  +add <tool> <file>    - Add file to distribution.toml.");
+        println!("      [--description <text>] [--url <url>] [--tag <tag>]... [--sort] [--retries <n>] [--retry-delay-ms <ms>]");
+        println!("  +new <tool>           - Create the directories and distribution.toml section for a new tool.");
+        println!("      [--description <text>] [--tag <tag>]... [--no-config-dir]");
+        println!("  +remove <tool> <file> - Remove file from distribution.toml.");
+        println!("  +mv <tool> <file> <to-tool> [new-file] - Move a tracked file to another tool.");
+        println!("      [--create-section]");
+        println!("  +cp <tool> <file> <destination> - Copy the repo version of a file elsewhere, untracked.");
+        println!("      [--force]");
+        println!("  +template <tool> <file> - Print a file of a template section as install would write it.");
+        println!("      [--variables <key=value>]...");
+        println!("  +audit                - Check tracked files for possible secrets.");
+        println!("      [--fix]");
+        println!("  +clean                - List repo files not in distribution.toml.");
+        println!("      [--delete] [--dry-run] [--yes]");
+        println!("  +gc                   - Remove tool sections that have no files.");
+        println!("      [--dry-run] [--yes] [--remove-empty-dirs] [--strict]");
+        println!("  +enable <tool>        - Resume syncing a disabled tool.");
+        println!("  +disable <tool>       - Skip a tool without removing it.");
+        println!("  +ignore <action>      - Manage .dotignore (add <pattern>, remove <pattern>, list, test <file>).");
+        println!("  +gitignore            - Write a .gitignore for local-only and sensitive files in the repo.");
+        println!("      [--append] [--dry-run]");
+        println!("  +sort                 - Sort tools and files in distribution.toml.");
+        println!("  +fmt                  - Rewrite distribution.toml in a consistent layout.");
+        println!("  +lint [--strict]      - Check distribution.toml for style problems.");
+        println!("  +verify --embedded    - Check embedded files against their build-time checksums.");
+        println!("  +deduplicate          - Remove duplicate entries from distribution.toml.");
+        println!("      [--case-insensitive]");
+        println!("  +list                 - List tracked tools.");
+        println!("      [--describe] [--format <plain|table>] [--priority-order] [--tag <tag>]...");
+        println!("  +doctor               - Diagnose common setup problems.");
+        println!("  +repair               - Re-point dangling symlinks at the repository.");
+        println!("      [--dry-run] [--remove-dangling]");
+        println!("  +link <tool> <file>   - Symlink a tracked file's config path to its repository copy.");
+        println!("      [--relink]");
+        println!("  +unlink <tool> <file> - Replace a linked file with a copy of the repository file.");
+        println!("  +tags                 - List tags and how many tools have each.");
+        println!("  +info <tool>          - Show a tool's settings and the status of its files.");
+        println!("  +stats [--json]       - Show file counts, sizes and the largest and newest files.");
+        println!("  +tree                 - Show tracked files as a directory tree.");
+        println!("      [--depth <n>] [--status] [--ascii]");
+        println!("  +fetch <host>         - Copy the tracked files from another machine with scp.");
+        println!("      [--tool <tool>] [--ssh-user <user>] [--ssh-port <port>] [--to-repo]");
+        println!("  +push <host>          - Copy the tracked files to another machine with scp.");
+        println!("      [--tool <tool>] [--ssh-user <user>] [--ssh-port <port>] [--dry-run] [--diff-first]");
+        println!("  +where <tool> <file>  - Show the repository and config paths of a tracked file.");
+        println!("      [--repo | --config] [--open]");
+        println!("  +touch <tool> <file>  - Set a tracked file's modification time to now.");
+        println!("      [--side <config|repo|both>]");
+        println!("  +profile <action>     - Manage profiles (list, show <name>, add-tool <profile> <tool>, remove-tool <profile> <tool>).");
+        println!("  +log                  - Show the operation history from operations.log.");
+        println!("      [--last <n>] [--command <command>] [--tool <tool>] [--clear]");
+        println!("  +undo [steps]         - Reverse the last operation, or the last <steps> operations.");
+        println!("  +precheck             - Check that distribution.toml exists and has valid syntax");
+        println!("      [--format <toml|yaml|json>] [--check-repos] [--fix] [--xdg] [--check-vars] [--ignore-info]");
+        println!("  +merge <file>         - Merge another distribution file into distribution.toml.");
+        println!("      [--output <file>]");
+        println!("  +convert <from> <to>  - Convert a distribution file between .toml, .yaml and .json.");
+        println!("  +config init          - Write the default dotfiles-rs config file.");
+        println!("  +config show          - Show dotfiles-rs settings.");
+        println!("  +config set <k> <v>   - Update a dotfiles-rs setting.");
+        println!("  +version              - Show version and build information.");
+        println!("  +usage                - Show this help message.");
+        println!("  +help                 - Show this help message.");
+        println!();
+        println!("Usage: dotfiles-rs +<action> [flags]");
+        println!();
+        println!("Options:");
+        println!("  -v, --verbose  Enable verbose output with detailed information (-vv for sizes and checksums)");
+        println!("  -a, --all      Show all files including identical ones when checking status");
+        println!("  -q, --quiet    Only print errors, headers and final summaries");
+        println!("  --log-file <path> [--rotate-log]  Also write output to a log file");
+        println!("  --repo-dir <path>  Use this dotfiles repository instead of the configured one");
+        println!("  --timestamp    Prefix every output line with the UTC time");
+        println!("  --color <always|auto|never>  When to use colors (NO_COLOR turns them off)");
+        println!("  --profile <name>  Only process the tools in this profile");
+        println!("  --allow-undefined-vars  Leave undefined $VAR references in the distribution file as written");
+        println!();
+        println!("Examples:");
+        println!("  dotfiles-rs +status");
+        println!("  dotfiles-rs +install");
+        println!("  dotfiles-rs +add nvim init.lua");
+        println!();
+        println!("Files matching patterns in $HOME/repos/dotfiles/.dotignore will be skipped");
+        println!("Tools named with a leading ~ (for example ~home) track files directly in $HOME");
+        println!("Per-tool patterns can be placed in $HOME/repos/dotfiles/config/<tool>/.toolignore");
+        Ok(())
+    }
+}
+
+// Filenames audit reports as sensitive, on top of the .dotignore defaults
+const AUDIT_FILE_PATTERNS: &[&str] = &[
+    "*_rsa",
+    "*_dsa",
+    "*_ecdsa",
+    "*_ed25519",
+    "*.p12",
+    "*.pfx",
+    "*.pem",
+    "*.key",
+    "*.kdbx",
+    "*secret*",
+    "*password*",
+    "*passwd*",
+    "*credentials*",
+    "*token*",
+    "*.env",
+    ".env*",
+    "*kube/config*",
+];
+
+// Snapshot names become directory names under snapshots/
+static SNAPSHOT_NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9][A-Za-z0-9._-]{0,63}$").expect("valid snapshot name pattern"));
+
+// Content that suggests an embedded secret, with a description for the report
+static AUDIT_CONTENT_PATTERNS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| {
+    [
+        (r"-----BEGIN", "PEM block"),
+        (r#"(?i)token\s*=\s*""#, "token assignment"),
+        (r#"(?i)password\s*=\s*""#, "password assignment"),
+        (r"[A-Za-z0-9+/]{40,}={0,2}", "long base64-like string"),
+    ]
+    .iter()
+    .map(|(pattern, description)| (Regex::new(pattern).expect("valid audit pattern"), *description))
+    .collect()
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    fn output(buffer: &Arc<Mutex<Vec<u8>>>) -> String {
+        String::from_utf8(buffer.lock().unwrap().clone()).unwrap()
+    }
+    
+    // A repository tracking zsh/zshrc and an empty config directory, with
+    // output going to the returned buffer
+    fn fixture_app(verbose: u8) -> (TempDir, App, Arc<Mutex<Vec<u8>>>) {
+        fixture_app_with(verbose, ToolConfig::default())
+    }
+    
+    // fixture_app with the other settings of `tool_config`; backups go to
+    // <dir>/backups unless backup_dir is empty
+    fn fixture_app_with(verbose: u8, tool_config: ToolConfig) -> (TempDir, App, Arc<Mutex<Vec<u8>>>) {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join("config").join("zsh")).unwrap();
+        fs::write(repo.join("distribution.toml"), "[zsh]\nfiles = [\"zshrc\"]\n").unwrap();
+        fs::write(repo.join("config").join("zsh").join("zshrc"), "export EDITOR=nvim\n").unwrap();
+        fs::create_dir_all(dir.path().join("config")).unwrap();
+        
+        let backup_dir = match tool_config.backup_dir.as_str() {
+            "" => String::new(),
+            _ => dir.path().join("backups").to_string_lossy().to_string(),
+        };
+        let config = ToolConfig {
+            repo_dir: repo.to_string_lossy().to_string(),
+            config_dir: dir.path().join("config").to_string_lossy().to_string(),
+            backup_dir,
+            ..tool_config
+        };
+        let (mut formatter, buffer) = Formatter::new_test_sink();
+        formatter.verbose = verbose;
+        let app = App::from_filesystem(config, formatter, false).unwrap();
+        (dir, app, buffer)
+    }
+    
+    #[test]
+    fn formatter_sink_captures_messages() {
+        let (mut formatter, buffer) = Formatter::new_test_sink();
+        formatter.info("Processing tool: zsh").unwrap();
+        formatter.warning("Not tracked: zsh/zshenv").unwrap();
+        formatter.error("Failed: zsh/zshrc").unwrap();
+        
+        let output = output(&buffer);
+        assert_eq!(output.lines().count(), 3);
+        assert!(output.lines().next().unwrap().ends_with("Processing tool: zsh"));
+        assert!(output.contains("Not tracked: zsh/zshenv"));
+        assert!(output.contains("Failed: zsh/zshrc"));
+        assert!(!output.contains('\x1b'));
+    }
+    
+    #[test]
+    fn formatter_sink_respects_quiet() {
+        let (mut formatter, buffer) = Formatter::new_test_sink();
+        formatter.set_quiet(true);
+        formatter.info("Processing tool: zsh").unwrap();
+        formatter.warning("Not tracked: zsh/zshenv").unwrap();
+        formatter.error("Failed: zsh/zshrc").unwrap();
+        formatter.summary("install: 0 installed, 0 skipped, 1 failed").unwrap();
+        
+        assert_eq!(output(&buffer).lines().collect::<Vec<_>>().len(), 2);
+        assert!(output(&buffer).contains("Failed: zsh/zshrc"));
+        assert!(output(&buffer).contains("install: 0 installed, 0 skipped, 1 failed"));
+        assert_eq!(formatter.warnings, 1);
+    }
+    
+    #[test]
+    fn timestamp_prefixes_each_line_once() {
+        let (mut formatter, buffer) = Formatter::new_test_sink();
+        formatter.set_timestamp(true);
+        formatter.info("Processing tool: zsh").unwrap();
+        formatter.modified("Modified: zsh/zshrc").unwrap();
+        formatter.header("Tracked tools:").unwrap();
+        
+        let output = output(&buffer);
+        assert_eq!(output.lines().count(), 3);
+        for line in output.lines() {
+            let (timestamp, rest) = line.strip_prefix('[').and_then(|line| line.split_once("] ")).unwrap();
+            chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%SZ").unwrap();
+            assert!(!rest.contains('['), "{}", line);
+        }
+    }
+    
+    // The only test that changes NO_COLOR, so it can't race another one
+    #[test]
+    fn no_color_selects_never_unless_color_is_given() {
+        std::env::set_var("NO_COLOR", "1");
+        let configured = Formatter::color_choice(None, "always");
+        let flag = Formatter::color_choice(Some("always"), "auto");
+        std::env::set_var("NO_COLOR", "");
+        let empty = Formatter::color_choice(None, "always");
+        std::env::remove_var("NO_COLOR");
+        
+        assert_eq!(configured, ColorChoice::Never);
+        assert_eq!(flag, ColorChoice::Always);
+        assert_eq!(empty, ColorChoice::Always);
+    }
+    
+    // Records each write along with the color spec it was written in
+    struct SpecRecorder {
+        writes: Arc<Mutex<Vec<(ColorSpec, String)>>>,
+        spec: ColorSpec,
+    }
+    
+    impl Write for SpecRecorder {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.writes.lock().unwrap().push((self.spec.clone(), String::from_utf8_lossy(buf).to_string()));
+            Ok(buf.len())
+        }
+        
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    impl WriteColor for SpecRecorder {
+        fn supports_color(&self) -> bool {
+            true
+        }
+        
+        fn set_color(&mut self, spec: &ColorSpec) -> std::io::Result<()> {
+            self.spec = spec.clone();
+            Ok(())
+        }
+        
+        fn reset(&mut self) -> std::io::Result<()> {
+            self.spec = ColorSpec::new();
+            Ok(())
+        }
+    }
+    
+    #[test]
+    fn status_paths_are_dimmed() {
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let mut formatter = Formatter::new_with_sink(Box::new(SpecRecorder { writes: Arc::clone(&writes), spec: ColorSpec::new() }));
+        formatter.identical("Identical: zsh/zshrc").unwrap();
+        
+        let writes = writes.lock().unwrap();
+        let spec_of = |text: &str| writes.iter().find(|(_, written)| written == text).map(|(spec, _)| spec.clone()).unwrap();
+        assert!(spec_of("zsh/zshrc").dimmed());
+        assert!(!spec_of("Identical: ").dimmed());
+        assert_eq!(spec_of(&format!("{} ", CHECK_MARK)).fg(), Some(&Color::Green));
+    }
+    
+    #[test]
+    fn app_writes_through_its_formatter() {
+        let (_dir, mut app, buffer) = fixture_app(0);
+        app.run(&Commands::List { describe: false, format: None, priority_order: false, tag: Vec::new() }).unwrap();
+        
+        let output = output(&buffer);
+        assert!(output.contains("Tracked tools:"));
+        assert!(output.contains("zsh"));
+    }
+    
+    fn status_output(verbose: u8) -> (TempDir, String) {
+        let (dir, mut app, buffer) = fixture_app(verbose);
+        app.show_all = true;
+        fs::create_dir_all(dir.path().join("config").join("zsh")).unwrap();
+        fs::write(dir.path().join("config").join("zsh").join("zshrc"), "export EDITOR=nvim\n").unwrap();
+        app.run(&Commands::Status { fail_fast: false, json: false, tool: None, tag: Vec::new(), from_stdin: false }).unwrap();
+        (dir, output(&buffer))
+    }
+    
+    #[test]
+    fn verbose_status_shows_absolute_paths() {
+        let (_dir, output) = status_output(0);
+        assert!(output.contains("Identical: zsh/zshrc"), "{}", output);
+        
+        let (dir, output) = status_output(1);
+        let local = dir.path().join("config").join("zsh").join("zshrc");
+        let repo = dir.path().join("repo").join("config").join("zsh").join("zshrc");
+        let expected = format!("Identical: {} (repo: {})", local.display(), repo.display());
+        assert!(output.lines().any(|line| line.ends_with(&expected)), "{}", output);
+        assert!(!output.contains("sha256"));
+    }
+    
+    #[test]
+    fn very_verbose_status_shows_sizes_and_checksums() {
+        let (_dir, output) = status_output(2);
+        let details = format!("19 bytes, sha256 {}", sha256_hex(b"export EDITOR=nvim\n"));
+        let line = output.lines().find(|line| line.contains("Identical: /")).unwrap();
+        assert!(line.contains(&format!("[local: {}]", details)), "{}", line);
+        assert!(line.contains(&format!("[repo: {}]", details)), "{}", line);
+    }
+    
+    // One file for each status outcome, plus a disabled tool
+    fn status_fixture() -> (TempDir, App, Arc<Mutex<Vec<u8>>>) {
+        let (dir, app, buffer) = fixture_app(0);
+        let repo = dir.path().join("repo");
+        fs::write(repo.join("distribution.toml"),
+            "[zsh]\nfiles = [\"zshrc\", \"zshenv\", \"zprofile\", \"zlogin\", \"secret\"]\n\n[git]\nfiles = [\"config\"]\nenabled = false\n").unwrap();
+        fs::write(repo.join("config").join("zsh").join("zshenv"), "export PATH=~/bin:$PATH\n").unwrap();
+        fs::write(repo.join("config").join("zsh").join("zlogin"), "fortune\n").unwrap();
+        fs::write(repo.join("config").join("zsh").join(".toolignore"), "secret\n").unwrap();
+        
+        let local = dir.path().join("config").join("zsh");
+        fs::create_dir_all(&local).unwrap();
+        fs::write(local.join("zshrc"), "export EDITOR=nvim\n").unwrap();
+        fs::write(local.join("zshenv"), "export PATH=~/.local/bin:$PATH\n").unwrap();
+        fs::write(local.join("zprofile"), "umask 022\n").unwrap();
+        (dir, app, buffer)
+    }
+    
+    #[test]
+    fn status_report_counts_each_outcome() {
+        let (_dir, mut app, buffer) = status_fixture();
+        let report = app.status_report().unwrap();
+        
+        assert_eq!((report.identical, report.modified, report.missing_in_repo, report.not_installed, report.ignored), (1, 1, 1, 1, 1));
+        assert_eq!(report.exit_code(), 3);
+        assert!(output(&buffer).is_empty());
+        assert_eq!(serde_json::to_value(&report).unwrap(),
+            serde_json::json!({ "identical": 1, "modified": 1, "missing_in_repo": 1, "not_installed": 1, "ignored": 1 }));
+    }
+    
+    #[test]
+    fn status_prints_the_report() {
+        let (_dir, mut app, buffer) = status_fixture();
+        app.run(&Commands::Status { fail_fast: false, json: false, tool: None, tag: Vec::new(), from_stdin: false }).unwrap();
+        
+        let output = output(&buffer);
+        for expected in ["Skipped (disabled): git", "Modified locally: zsh/zshenv", "Missing in source: zsh/zprofile",
+            "Not installed: zsh/zlogin", "Ignored by .toolignore: zsh/secret", "5 files checked",
+            "Summary: 1 identical, 1 modified, 1 missing, 1 not installed, 1 ignored"] {
+            assert!(output.contains(expected), "missing {:?} in:\n{}", expected, output);
+        }
+        assert!(!output.contains("Identical: zsh/zshrc"));
+        assert_eq!(app.exit_code(), 3);
+    }
+    
+    #[test]
+    fn precheck_rejects_section_dirs_outside_their_path() {
+        let (_dir, mut app, buffer) = fixture_app(0);
+        let distribution: Distribution = toml::from_str(
+            "[vim]\nfiles = []\nsource_dir = \"~/.vim\"\n\n[tmux]\nfiles = []\nsource_dir = \"~/../etc\"\ntarget_dir = \"relative\"\n").unwrap();
+        
+        let error = app.check_section_dirs(&distribution).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid command format: 2 invalid source_dir/target_dir values");
+        let output = output(&buffer);
+        assert!(output.contains("Valid source_dir for vim: "));
+        assert!(output.contains("source_dir for tmux must not contain '..': "));
+        assert!(output.contains("target_dir for tmux is not absolute: relative"));
+    }
+    
+    #[test]
+    fn sync_all_reports_each_file() {
+        let (dir, mut app, buffer) = fixture_app(0);
+        let repo = dir.path().join("repo").join("config").join("zsh");
+        let local = dir.path().join("config").join("zsh");
+        fs::create_dir_all(&local).unwrap();
+        fs::write(local.join("zshrc"), "export EDITOR=nvim\n").unwrap();
+        fs::write(local.join("zshenv"), "export PATH=~/bin:$PATH\n").unwrap();
+        fs::write(local.join("secret"), "token\n").unwrap();
+        fs::write(repo.join(".toolignore"), "secret\n").unwrap();
+        fs::create_dir_all(repo.join("broken")).unwrap();
+        fs::write(local.join("broken"), "not a directory\n").unwrap();
+        
+        let files = ["zshrc", "zshenv", "zprofile", "secret", "broken"];
+        let tools = vec![("zsh".to_string(), files.iter().map(|file| FileEntry::Simple(file.to_string())).collect())];
+        let mut file_manager = FileManager::new(&app.paths, &mut app.formatter, &app.dotignore, false);
+        let results = file_manager.sync_all(&tools, false).unwrap();
+        
+        assert_eq!(results.iter().map(SyncResult::path).collect::<Vec<_>>(),
+            ["zsh/zshrc", "zsh/zshenv", "zsh/zprofile", "zsh/secret", "zsh/broken"]);
+        assert!(matches!(results[0], SyncResult::Skipped { reason: SkipReason::Unchanged, .. }));
+        assert!(matches!(results[1], SyncResult::Synced { .. }));
+        assert!(matches!(results[2], SyncResult::Skipped { reason: SkipReason::NotInstalled, .. }));
+        assert!(matches!(&results[3], SyncResult::Skipped { reason: SkipReason::Ignored(message), .. } if message.contains("`secret`")));
+        assert!(matches!(results[4], SyncResult::Failed { .. }));
+        assert_eq!(results.iter().filter(|result| result.is_failure()).count(), 1);
+        assert_eq!(fs::read_to_string(repo.join("zshenv")).unwrap(), "export PATH=~/bin:$PATH\n");
+        
+        // Printed by sync_all itself, so the lines appear while the progress bar is up
+        let output = output(&buffer);
+        let lines = ["Processing tool: zsh", "Synced to repo: zsh/zshenv", "Local file not found: zsh/zprofile", "`secret`"];
+        let positions: Vec<usize> = lines.iter().map(|line| output.find(line).unwrap()).collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{}", output);
+    }
+    
+    #[test]
+    fn sync_since_skips_files_modified_before_the_date() {
+        let (dir, mut app, _buffer) = fixture_app(0);
+        let local = dir.path().join("config").join("zsh");
+        fs::create_dir_all(&local).unwrap();
+        for file in ["zshrc", "zshenv"] {
+            fs::write(local.join(file), "changed\n").unwrap();
+        }
+        let old = chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap().and_hms_opt(12, 0, 0).unwrap()
+            .and_local_timezone(chrono::Local).unwrap();
+        File::options().write(true).open(local.join("zshrc")).unwrap().set_modified(old.into()).unwrap();
+        
+        let since = chrono::NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let tools = vec![("zsh".to_string(), vec![FileEntry::Simple("zshrc".to_string()), FileEntry::Simple("zshenv".to_string())])];
+        let mut file_manager = FileManager::new(&app.paths, &mut app.formatter, &app.dotignore, false);
+        file_manager.since = Some(since);
+        let results = file_manager.sync_all(&tools, false).unwrap();
+        
+        assert!(matches!(results[0], SyncResult::Skipped { reason: SkipReason::NotModifiedSince(date), .. } if date == since));
+        assert!(matches!(results[1], SyncResult::Synced { .. }));
+        let repo = dir.path().join("repo").join("config").join("zsh");
+        assert_eq!(fs::read_to_string(repo.join("zshrc")).unwrap(), "export EDITOR=nvim\n");
+        assert_eq!(fs::read_to_string(repo.join("zshenv")).unwrap(), "changed\n");
+    }
+    
+    #[test]
+    fn table_pads_columns_to_display_width() {
+        let (mut formatter, buffer) = Formatter::new_test_sink();
+        let rows = vec![
+            vec!["zsh".to_string(), "modified".to_string(), "~/.zshrc".to_string()],
+            vec!["日本語".to_string(), "ok".to_string(), "~/.config/日本".to_string()],
+            vec!["nvim".to_string(), "identical".to_string(), "-".to_string()],
+        ];
+        formatter.table(&["TOOL", "STATE", "PATH"], &rows, &[None, Some(Color::Yellow), None]).unwrap();
+        
+        assert_eq!(output(&buffer).lines().collect::<Vec<_>>(), [
+            "TOOL    STATE      PATH",
+            "zsh     modified   ~/.zshrc",
+            "日本語  ok         ~/.config/日本",
+            "nvim    identical  -",
+        ]);
+    }
+    
+    #[test]
+    fn hunks_group_adjacent_changes() {
+        let old = b"one\ntwo\nthree\nfour\nfive\nsix\n";
+        let new = b"one\nTWO\nthree\nfour\nfive\nsix\nseven\n";
+        let hunks = FileManager::hunks(old, new);
+        
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].old_start, hunks[0].new_start), (2, 2));
+        assert_eq!(hunks[0].old_lines, ["two"]);
+        assert_eq!(hunks[0].new_lines, ["TWO"]);
+        assert_eq!((hunks[1].old_start, hunks[1].new_start), (7, 7));
+        assert!(hunks[1].old_lines.is_empty());
+        assert_eq!(hunks[1].new_lines, ["seven"]);
+        
+        let hunks = FileManager::hunks(b"a\nb\nc\n", b"a\nc\r\n");
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].old_start, 2);
+        assert_eq!(hunks[0].old_lines, ["b", "c"]);
+        assert_eq!(hunks[0].new_lines, ["c"]);
+    }
+    
+    #[test]
+    fn diff_file_compares_repo_and_local_copies() {
+        let (dir, mut app, _buffer) = fixture_app(0);
+        let local = dir.path().join("config").join("zsh");
+        {
+            let file_manager = FileManager::new(&app.paths, &mut app.formatter, &app.dotignore, false);
+            assert!(matches!(file_manager.diff_file("zsh", "zshrc").unwrap(), FileDiff::OnlyInRepo));
+            assert!(file_manager.diff_file("zsh", "zshenv").is_err());
+        }
+        
+        fs::create_dir_all(&local).unwrap();
+        fs::write(local.join("zshrc"), "export EDITOR=nvim\n").unwrap();
+        fs::write(local.join("zshenv"), "export PATH=~/bin:$PATH\n").unwrap();
+        {
+            let file_manager = FileManager::new(&app.paths, &mut app.formatter, &app.dotignore, false);
+            assert!(matches!(file_manager.diff_file("zsh", "zshrc").unwrap(), FileDiff::Identical));
+            assert!(matches!(file_manager.diff_file("zsh", "zshenv").unwrap(), FileDiff::OnlyInConfig));
+        }
+        
+        fs::write(local.join("zshrc"), "export EDITOR=vim\nalias ll='ls -l'\n").unwrap();
+        let file_manager = FileManager::new(&app.paths, &mut app.formatter, &app.dotignore, false);
+        match file_manager.diff_file("zsh", "zshrc").unwrap() {
+            FileDiff::Different { hunks } => {
+                assert_eq!(hunks.len(), 1);
+                assert_eq!((hunks[0].old_start, hunks[0].new_start), (1, 1));
+                assert_eq!(hunks[0].old_lines, ["export EDITOR=nvim"]);
+                assert_eq!(hunks[0].new_lines, ["export EDITOR=vim", "alias ll='ls -l'"]);
+            },
+            other => panic!("expected a difference, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn symlink_mode_links_installed_files() {
+        let config = ToolConfig { symlink_mode: true, ..ToolConfig::default() };
+        let (dir, mut app, buffer) = fixture_app_with(0, config);
+        let local = dir.path().join("config").join("zsh");
+        fs::create_dir_all(&local).unwrap();
+        fs::write(local.join("zshrc"), "export EDITOR=nvim\n").unwrap();
+        
+        app.run_install(ConflictStrategy::Overwrite, false, false).unwrap();
+        let repo_file = dir.path().join("repo").join("config").join("zsh").join("zshrc");
+        assert_eq!(fs::read_link(local.join("zshrc")).unwrap(), repo_file);
+        
+        app.run_install(ConflictStrategy::Skip, false, false).unwrap();
+        assert_eq!(fs::read_link(local.join("zshrc")).unwrap(), repo_file);
+        assert!(!output(&buffer).contains("Skipping (is a symlink)"));
+    }
+    
+    #[test]
+    fn install_backs_up_overwritten_files() {
+        let (dir, mut app, _buffer) = fixture_app(0);
+        let local = dir.path().join("config").join("zsh");
+        fs::create_dir_all(&local).unwrap();
+        fs::write(local.join("zshrc"), "export EDITOR=vi\n").unwrap();
+        
+        app.run_install(ConflictStrategy::Overwrite, false, false).unwrap();
+        assert_eq!(fs::read_to_string(local.join("zshrc")).unwrap(), "export EDITOR=nvim\n");
+        let runs: Vec<_> = fs::read_dir(dir.path().join("backups")).unwrap().map(|entry| entry.unwrap().path()).collect();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(fs::read_to_string(runs[0].join("zsh").join("zshrc")).unwrap(), "export EDITOR=vi\n");
+        
+        let config = ToolConfig { backup_dir: String::new(), ..ToolConfig::default() };
+        let (dir, mut app, _buffer) = fixture_app_with(0, config);
+        fs::create_dir_all(dir.path().join("config").join("zsh")).unwrap();
+        fs::write(dir.path().join("config").join("zsh").join("zshrc"), "export EDITOR=vi\n").unwrap();
+        app.run_install(ConflictStrategy::Overwrite, false, false).unwrap();
+        assert!(!dir.path().join("backups").exists());
+    }
+    
+    #[test]
+    fn link_keeps_an_existing_backup() {
+        let (dir, mut app, _buffer) = fixture_app(0);
+        let local = dir.path().join("config").join("zsh");
+        fs::create_dir_all(&local).unwrap();
+        fs::write(local.join("zshrc"), "export EDITOR=vi\n").unwrap();
+        fs::write(local.join("zshrc.bak"), "earlier backup\n").unwrap();
+        
+        app.run_link("zsh", "zshrc", false).unwrap();
+        assert!(local.join("zshrc").is_symlink());
+        assert_eq!(fs::read_to_string(local.join("zshrc.bak")).unwrap(), "earlier backup\n");
+        assert_eq!(fs::read_to_string(local.join("zshrc.bak.1")).unwrap(), "export EDITOR=vi\n");
+    }
+    
+    // Both tracked zsh files differ from their local copies
+    fn conflicting_app() -> (TempDir, App, Arc<Mutex<Vec<u8>>>) {
+        let (dir, app, buffer) = fixture_app(0);
+        let repo = dir.path().join("repo");
+        fs::write(repo.join("distribution.toml"), "[zsh]\nfiles = [\"zshrc\", \"zshenv\"]\n").unwrap();
+        fs::write(repo.join("config").join("zsh").join("zshenv"), "export PATH=~/bin:$PATH\n").unwrap();
+        let local = dir.path().join("config").join("zsh");
+        fs::create_dir_all(&local).unwrap();
+        fs::write(local.join("zshrc"), "local zshrc\n").unwrap();
+        fs::write(local.join("zshenv"), "local zshenv\n").unwrap();
+        (dir, app, buffer)
+    }
+    
+    #[test]
+    fn conflict_error_stops_at_the_first_conflict() {
+        let (_dir, mut app, buffer) = conflicting_app();
+        let error = app.run_install(ConflictStrategy::Error, false, false).unwrap_err();
+        
+        assert!(matches!(error.downcast_ref(), Some(DotfilesError::ConflictDetected { file, .. }) if file == "zshrc"));
+        assert!(!output(&buffer).contains("conflicts found"));
+    }
+    
+    #[test]
+    fn accumulate_errors_lists_every_conflict() {
+        let (dir, mut app, buffer) = conflicting_app();
+        let error = app.run_install(ConflictStrategy::Error, false, true).unwrap_err();
+        
+        assert_eq!(error.to_string(), "2 conflicts found");
+        let output = output(&buffer);
+        assert!(output.contains("2 conflicts found:"));
+        assert!(output.contains("zsh/zshrc: repo modified"));
+        assert!(output.contains("zsh/zshenv: repo modified"));
+        let local = dir.path().join("config").join("zsh");
+        assert_eq!(fs::read_to_string(local.join("zshenv")).unwrap(), "local zshenv\n");
+    }
+    
+    #[test]
+    fn audit_fix_deletes_the_repo_copy_of_renamed_and_encrypted_files() {
+        let (dir, mut app, _buffer) = fixture_app(0);
+        let repo = dir.path().join("repo");
+        fs::write(repo.join("distribution.toml"), concat!(
+            "[zsh]\nfiles = [\"zshrc\", { from = \"work_secrets\", to = \"secrets\" }]\n\n",
+            "[gh]\nencrypted = true\nfiles = [\"hosts.yml\"]\n",
+        )).unwrap();
+        fs::write(repo.join("config").join("zsh").join("work_secrets"), "token = \"abc\"\n").unwrap();
+        fs::create_dir_all(repo.join("config").join("gh")).unwrap();
+        fs::write(repo.join("config").join("gh").join("hosts.yml.gpg"), "ciphertext").unwrap();
+        fs::write(repo.join("config").join("gh").join("hosts.yml"), "password = \"hunter2\"\n").unwrap();
+        
+        assert!(app.run_audit(true).is_err());
+        assert!(!repo.join("config").join("zsh").join("work_secrets").exists());
+        assert!(!repo.join("config").join("gh").join("hosts.yml.gpg").exists());
+        assert!(repo.join("config").join("zsh").join("zshrc").exists());
+        let distribution = fs::read_to_string(repo.join("distribution.toml")).unwrap();
+        assert!(!distribution.contains("work_secrets"));
+        assert!(!distribution.contains("hosts.yml"));
+    }
+    
+    // The only test that sets DOTFILES_RS_* variables
+    #[test]
+    fn new_with_formatter_loads_the_config_and_captures_output() {
+        let (dir, _app, _buffer) = fixture_app(0);
+        std::env::set_var("DOTFILES_RS_REPO_DIR", dir.path().join("repo"));
+        std::env::set_var("DOTFILES_RS_CONFIG_DIR", dir.path().join("config"));
+        let (formatter, buffer) = Formatter::new_test_sink();
+        let app = App::new_with_formatter(formatter);
+        std::env::remove_var("DOTFILES_RS_REPO_DIR");
+        std::env::remove_var("DOTFILES_RS_CONFIG_DIR");
+        
+        let mut app = app.unwrap();
+        app.run(&Commands::Status { fail_fast: false, json: false, tool: None, tag: Vec::new(), from_stdin: false }).unwrap();
+        assert!(output(&buffer).contains("Not installed: zsh/zshrc"), "{}", output(&buffer));
+    }
+    
+    #[test]
+    fn filtered_tools_applies_tag_profile_and_enabled_filters() {
+        let (dir, mut app, buffer) = fixture_app(1);
+        fs::write(dir.path().join("repo").join("distribution.toml"), concat!(
+            "[zsh]\nfiles = [\"zshrc\"]\ntags = [\"shell\"]\n\n",
+            "[fish]\nfiles = [\"config.fish\"]\ntags = [\"shell\"]\nenabled = false\n\n",
+            "[git]\nfiles = [\"config\"]\ntags = [\"vcs\"]\n\n",
+            "[bash]\nfiles = [\"bashrc\"]\ntags = [\"shell\"]\n")).unwrap();
+        app.tag_filter = vec!["shell".to_string()];
+        app.profile_tools = Some(vec!["zsh".to_string(), "fish".to_string(), "git".to_string()]);
+        
+        assert_eq!(app.filtered_tools().unwrap(), vec!["zsh"]);
+        let output = output(&buffer);
+        for expected in ["Skipped (disabled): fish", "Skipped (no matching tag): git", "Skipped (not in profile): bash"] {
+            assert!(output.contains(expected), "missing {:?} in:\n{}", expected, output);
+        }
+    }
+    
+    #[test]
+    fn run_summary_line_uses_command_name() {
+        let summary = RunSummary {
+            command: Commands::SnapshotList.name().to_string(),
+            installed: 3,
+            skipped: 1,
+            warnings: 2,
+            ..RunSummary::default()
+        };
+        
+        assert_eq!(summary.to_string(), "snapshot-list: 3 installed, 1 skipped, 0 failed, 2 warnings");
+        assert_eq!(summary.exit_code(), 0);
+    }
+}
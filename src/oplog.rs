@@ -0,0 +1,234 @@
+use anyhow::{anyhow, Result};
+use chrono::{SecondsFormat, Utc};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::util::sha256_hex;
+
+// Append-only history of what dotfiles-rs did, one line per file:
+// <timestamp> <hostname> <command> <tool>/<file> <result> [before=<hash|none>]
+// Results starting with "skipped" record files that were left alone
+#[derive(Debug, Clone)]
+pub struct OperationLog {
+    path: PathBuf,
+    hostname: String,
+    undo_cache: UndoCache,
+}
+
+// What a file held before an operation overwrote it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Snapshot {
+    // The file didn't exist
+    Missing,
+    // SHA-256 of the content saved in the undo cache
+    Saved(String),
+}
+
+impl Snapshot {
+    fn parse(value: &str) -> Self {
+        if value == "none" {
+            Snapshot::Missing
+        } else {
+            Snapshot::Saved(value.to_string())
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            Snapshot::Missing => "none",
+            Snapshot::Saved(hash) => hash,
+        }
+    }
+}
+
+// Content-addressed copies of files taken before they are overwritten, kept
+// in the repository so `undo` can put them back. The directory carries its
+// own .gitignore so the copies are never committed
+#[derive(Debug, Clone)]
+pub struct UndoCache {
+    dir: PathBuf,
+}
+
+impl UndoCache {
+    pub const DIR_NAME: &'static str = ".undo_cache";
+
+    pub fn new(repo_dir: &Path) -> Self {
+        Self { dir: repo_dir.join(Self::DIR_NAME) }
+    }
+
+    // Save the current content of `path`, if any, before it is overwritten
+    pub fn save_before(&self, path: &Path) -> Result<Snapshot> {
+        if !path.exists() {
+            return Ok(Snapshot::Missing);
+        }
+
+        let content = fs::read(path)?;
+        let hash = sha256_hex(&content);
+        let cached = self.dir.join(&hash);
+        if !cached.exists() {
+            fs::create_dir_all(&self.dir)?;
+            let gitignore = self.dir.join(".gitignore");
+            if !gitignore.exists() {
+                fs::write(&gitignore, "*\n")?;
+            }
+            fs::write(&cached, &content)?;
+        }
+
+        Ok(Snapshot::Saved(hash))
+    }
+
+    // Put `path` back the way it was when `snapshot` was taken
+    pub fn restore(&self, snapshot: &Snapshot, path: &Path) -> Result<()> {
+        match snapshot {
+            Snapshot::Missing => {
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+            },
+            Snapshot::Saved(hash) => {
+                let cached = self.dir.join(hash);
+                if !cached.exists() {
+                    return Err(anyhow!("{} is missing from {}", hash, self.dir.display()));
+                }
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(&cached, path)?;
+            },
+        }
+        Ok(())
+    }
+}
+
+// A parsed line of operations.log
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub hostname: String,
+    pub command: String,
+    // tool/file
+    pub path: String,
+    pub result: String,
+    // The overwritten file, for operations that can be undone by restoring it
+    pub before: Option<Snapshot>,
+}
+
+impl LogEntry {
+    // The path can contain spaces, so it is whatever sits between the
+    // first three fields and the result
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(4, ' ');
+        let timestamp = fields.next()?;
+        let hostname = fields.next()?;
+        let command = fields.next()?;
+        let mut rest = fields.next()?;
+
+        let mut before = None;
+        if let Some((head, value)) = rest.rsplit_once(" before=") {
+            if !value.contains(' ') {
+                before = Some(Snapshot::parse(value));
+                rest = head;
+            }
+        }
+        let (path, result) = rest.rsplit_once(' ')?;
+
+        Some(Self {
+            timestamp: timestamp.to_string(),
+            hostname: hostname.to_string(),
+            command: command.to_string(),
+            path: path.to_string(),
+            result: result.to_string(),
+            before,
+        })
+    }
+
+    pub fn tool(&self) -> &str {
+        self.path.split('/').next().unwrap_or_default()
+    }
+
+    pub fn file(&self) -> &str {
+        self.path.split_once('/').map(|(_, file)| file).unwrap_or_default()
+    }
+
+    // Whether the operation left the file alone, so there is nothing to undo
+    pub fn is_skipped(&self) -> bool {
+        self.result.starts_with("skipped")
+    }
+}
+
+impl OperationLog {
+    pub const FILE_NAME: &'static str = "operations.log";
+
+    pub fn new(repo_dir: &Path) -> Self {
+        Self {
+            path: repo_dir.join(Self::FILE_NAME),
+            undo_cache: UndoCache::new(repo_dir),
+            hostname: hostname::get()
+                .map(|name| name.to_string_lossy().replace(' ', "_"))
+                .unwrap_or_else(|_| "unknown".to_string()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn undo_cache(&self) -> &UndoCache {
+        &self.undo_cache
+    }
+
+    // Nothing is logged when the repository doesn't exist, e.g. when running
+    // from embedded files on a fresh machine
+    pub fn is_enabled(&self) -> bool {
+        self.path.parent().is_some_and(Path::is_dir)
+    }
+
+    // Record one operation, with the file it overwrote if it can be undone
+    pub fn append(&self, command: &str, tool: &str, file: &str, result: &str, before: Option<&Snapshot>) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let mut line = format!("{} {} {} {}/{} {}",
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true), self.hostname, command, tool, file, result);
+        if let Some(before) = before {
+            line.push_str(&format!(" before={}", before.as_str()));
+        }
+
+        let mut log = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(log, "{}", line)?;
+        Ok(())
+    }
+
+    // Operations that haven't been undone yet, oldest first. Each `undo`
+    // entry cancels the latest operation before it that is still pending;
+    // skipped files changed nothing and are never pending
+    pub fn pending(&self) -> Result<Vec<LogEntry>> {
+        let mut pending = Vec::new();
+        for entry in self.read()? {
+            if entry.command == "undo" {
+                pending.pop();
+            } else if !entry.is_skipped() {
+                pending.push(entry);
+            }
+        }
+        Ok(pending)
+    }
+
+    // Every entry, oldest first; lines that don't parse are skipped
+    pub fn read(&self) -> Result<Vec<LogEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        Ok(content.lines().filter_map(LogEntry::parse).collect())
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::write(&self.path, "")?;
+        }
+        Ok(())
+    }
+}
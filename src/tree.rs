@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+// Paths arranged as a directory tree, drawn one line per entry:
+//
+// dotfiles
+// ├── nvim/
+// │   └── init.lua
+// └── zsh/
+//     └── .zshrc
+#[derive(Debug, Default)]
+pub struct Tree {
+    children: BTreeMap<String, Tree>,
+    is_dir: bool,
+    // Shown after the entry's name, e.g. a file's status
+    note: Option<String>,
+}
+
+// One rendered line: the connectors leading up to the entry, then the entry
+#[derive(Debug)]
+pub struct TreeLine {
+    pub prefix: String,
+    pub name: String,
+    pub is_dir: bool,
+    pub note: Option<String>,
+}
+
+struct Connectors {
+    branch: &'static str,
+    last: &'static str,
+    pipe: &'static str,
+    space: &'static str,
+}
+
+const UNICODE: Connectors = Connectors { branch: "├── ", last: "└── ", pipe: "│   ", space: "    " };
+const ASCII: Connectors = Connectors { branch: "+-- ", last: "+-- ", pipe: "|   ", space: "    " };
+
+impl Tree {
+    // Add the file at `path` (components separated by '/'), creating its
+    // parent directories
+    pub fn insert(&mut self, path: &str, note: Option<String>) {
+        self.node(path).note = note;
+    }
+
+    // Add a directory, which is shown as one even when it is empty
+    pub fn insert_dir(&mut self, path: &str, note: Option<String>) {
+        let node = self.node(path);
+        node.is_dir = true;
+        node.note = note;
+    }
+
+    fn node(&mut self, path: &str) -> &mut Tree {
+        let mut node = self;
+        for component in path.split('/').filter(|component| !component.is_empty()) {
+            node.is_dir = true;
+            node = node.children.entry(component.to_string()).or_default();
+        }
+        node
+    }
+
+    // Lines for every entry below the root, directories before their
+    // contents. `depth` limits how many levels are shown, 1 being only the
+    // root's children
+    pub fn render(&self, depth: Option<usize>, ascii: bool) -> Vec<TreeLine> {
+        let connectors = if ascii { &ASCII } else { &UNICODE };
+        let mut lines = Vec::new();
+        self.render_children("", 1, depth, connectors, &mut lines);
+        lines
+    }
+
+    fn render_children(&self, prefix: &str, level: usize, depth: Option<usize>, connectors: &Connectors, lines: &mut Vec<TreeLine>) {
+        if depth.is_some_and(|depth| level > depth) {
+            return;
+        }
+
+        let count = self.children.len();
+        for (index, (name, child)) in self.children.iter().enumerate() {
+            let is_last = index + 1 == count;
+            lines.push(TreeLine {
+                prefix: format!("{}{}", prefix, if is_last { connectors.last } else { connectors.branch }),
+                name: if child.is_dir { format!("{}/", name) } else { name.clone() },
+                is_dir: child.is_dir,
+                note: child.note.clone(),
+            });
+
+            let child_prefix = format!("{}{}", prefix, if is_last { connectors.space } else { connectors.pipe });
+            child.render_children(&child_prefix, level + 1, depth, connectors, lines);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::thread;
+use std::time::Duration;
+use walkdir::WalkDir;
+
+// How often to retry IO that can fail transiently, e.g. copies on NFS or
+// cloud-synced folders
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            delay: Duration::from_millis(100),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Override the defaults with values given on the command line
+    pub fn with_overrides(retries: Option<u32>, delay_ms: Option<u64>) -> Self {
+        let default = Self::default();
+        Self {
+            max_attempts: retries.unwrap_or(default.max_attempts).max(1),
+            delay: delay_ms.map(Duration::from_millis).unwrap_or(default.delay),
+        }
+    }
+}
+
+// Run `f` up to `max_attempts` times, doubling the delay after each failure.
+// The last error is returned if every attempt fails
+pub fn retry(max_attempts: u32, delay: Duration, mut f: impl FnMut() -> Result<()>) -> Result<()> {
+    let mut delay = delay;
+    let mut attempt = 1;
+
+    loop {
+        match f() {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= max_attempts => return Err(e),
+            Err(_) => {
+                thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            },
+        }
+    }
+}
+
+// The first executable called `name` in a PATH directory
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+// Run `command` with the platform shell in `dir` and capture its output
+pub fn run_shell(command: &str, dir: &Path) -> std::io::Result<Output> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command).current_dir(dir).output()
+}
+
+// Format a byte count for display, e.g. "512 B" or "1.5 KiB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+// Hex-encoded SHA-256 of a byte slice
+pub fn sha256_hex(content: &[u8]) -> String {
+    to_hex(&Sha256::digest(content))
+}
+
+/// Hex-encoded SHA-256 of the file at `path`, read in 64KB chunks so large
+/// files are never held in memory
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Open `path` in $EDITOR, falling back to vi, and wait for it to exit
+pub fn open_in_editor(path: &Path) -> Result<()> {
+    let editor = env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+
+    let status = Command::new(program).args(words).arg(path).status()
+        .map_err(|e| anyhow::anyhow!("Failed to start editor '{}': {}", editor, e))?;
+    if !status.success() {
+        anyhow::bail!("Editor '{}' exited with {}", editor, status);
+    }
+    Ok(())
+}
+
+// A machine reached with ssh and scp, logging in as `user` when one is given
+pub struct SshHost {
+    pub host: String,
+    user: Option<String>,
+    port: Option<u16>,
+}
+
+impl SshHost {
+    pub fn new(host: &str, user: Option<&str>, port: Option<u16>) -> Self {
+        Self { host: host.to_string(), user: user.map(String::from), port }
+    }
+
+    fn destination(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    // `path` on this host as scp takes it; relative paths are under the home directory
+    pub fn remote(&self, path: &Path) -> String {
+        format!("{}:{}", self.destination(), path.display())
+    }
+
+    pub fn scp(&self) -> Command {
+        let mut scp = Command::new("scp");
+        scp.arg("-q");
+        if let Some(port) = self.port {
+            scp.arg("-P").arg(port.to_string());
+        }
+        scp
+    }
+
+    // Run `command` through the login shell on the host
+    pub fn ssh(&self, command: &str) -> Command {
+        let mut ssh = Command::new("ssh");
+        if let Some(port) = self.port {
+            ssh.arg("-p").arg(port.to_string());
+        }
+        ssh.arg(self.destination()).arg(command);
+        ssh
+    }
+}
+
+// Create a symlink at `link` pointing to the file `target`
+pub fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(target, link);
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_file(target, link);
+}
+
+// Copy the directory tree at `from` to `to`, recreating symlinks as symlinks
+// instead of following them. Returns the number of files and links copied
+pub fn copy_tree(from: &Path, to: &Path) -> Result<usize> {
+    let mut copied = 0;
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if entry.file_type().is_symlink() {
+            symlink(&fs::read_link(entry.path())?, &target)?;
+            copied += 1;
+        } else if entry.file_type().is_file() {
+            fs::copy(entry.path(), &target)?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+// `<path>.bak`, or `<path>.bak.1`, `<path>.bak.2`, ... when that is taken,
+// so an earlier backup is never overwritten
+pub fn unused_backup_path(path: &Path) -> PathBuf {
+    let with_suffix = |suffix: String| {
+        let mut backup = path.as_os_str().to_os_string();
+        backup.push(suffix);
+        PathBuf::from(backup)
+    };
+    
+    std::iter::once(".bak".to_string())
+        .chain((1..).map(|n| format!(".bak.{}", n)))
+        .map(with_suffix)
+        .find(|backup| fs::symlink_metadata(backup).is_err())
+        .expect("an unused backup name")
+}
+
+// Quote `value` as a single word for a POSIX shell
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn hash_file_matches_hash_of_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large");
+        // Spans several 64KB chunks and ends partway through one
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&path, &content).unwrap();
+        
+        assert_eq!(hash_file(&path).unwrap(), sha256_hex(&content));
+        assert_eq!(sha256_hex(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+    
+    #[test]
+    fn backup_path_skips_existing_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".zshrc");
+        assert_eq!(unused_backup_path(&path), dir.path().join(".zshrc.bak"));
+        
+        fs::write(dir.path().join(".zshrc.bak"), "first").unwrap();
+        symlink(Path::new("missing"), &dir.path().join(".zshrc.bak.1")).unwrap();
+        assert_eq!(unused_backup_path(&path), dir.path().join(".zshrc.bak.2"));
+    }
+    
+    #[test]
+    fn copy_tree_keeps_symlinks() {
+        let from = tempfile::tempdir().unwrap();
+        let to = tempfile::tempdir().unwrap();
+        fs::create_dir(from.path().join("nvim")).unwrap();
+        fs::write(from.path().join("nvim/init.lua"), "-- init").unwrap();
+        symlink(Path::new("init.lua"), &from.path().join("nvim/alias.lua")).unwrap();
+
+        let copied = copy_tree(from.path(), &to.path().join("config")).unwrap();
+
+        assert_eq!(copied, 2);
+        let link = to.path().join("config/nvim/alias.lua");
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("init.lua"));
+        assert_eq!(fs::read_to_string(to.path().join("config/nvim/init.lua")).unwrap(), "-- init");
+    }
+}
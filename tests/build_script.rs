@@ -0,0 +1,5 @@
+// Cargo runs build.rs without its tests, so it is compiled here as a module
+// to run them
+#[allow(dead_code)]
+#[path = "../build.rs"]
+mod build_script;